@@ -5,6 +5,7 @@ use std::{
     env, fs,
     io::{self, BufRead},
     path::{Path, PathBuf},
+    sync::Arc,
 };
 use tracing::{info, Level};
 use tracing_subscriber::{self, fmt, EnvFilter};
@@ -12,7 +13,10 @@ use tracing_subscriber::{self, fmt, EnvFilter};
 mod tools;
 mod utils;
 
+use utils::journal::Journal;
+use utils::lock::LockManager;
 use utils::path::AllowedPaths;
+use utils::trash::TrashConfig;
 
 #[derive(Parser, Debug)]
 #[clap(
@@ -37,6 +41,26 @@ struct CliArgs {
     #[clap(long, env = "FS_REQUEST_TIMEOUT", default_value = "30")]
     request_timeout: u64,
 
+    /// Move deleted items to a per-directory trash instead of removing them
+    #[clap(long, env = "FS_TRASH", default_value = "true", action = clap::ArgAction::Set)]
+    trash: bool,
+
+    /// Purge trash entries older than this many days (0 keeps them forever)
+    #[clap(long, env = "FS_TRASH_MAX_AGE_DAYS", default_value = "30")]
+    trash_max_age_days: u64,
+
+    /// Maximum total size of each trash directory in bytes (0 for no limit)
+    #[clap(long, env = "FS_TRASH_MAX_SIZE", default_value = "1073741824")]
+    trash_max_size: u64,
+
+    /// Directory of the edit journal used by history, undo and redo (default: $XDG_STATE_HOME/fs-mcp-server/journal, private to the user)
+    #[clap(long, env = "FS_JOURNAL_DIR")]
+    journal_dir: Option<PathBuf>,
+
+    /// Number of revisions kept per file in the edit journal
+    #[clap(long, env = "FS_JOURNAL_MAX_REVISIONS", default_value = "100")]
+    journal_max_revisions: usize,
+
     /// Log level
     #[clap(long, env = "FS_LOG_LEVEL", default_value = "debug")]
     log_level: String,
@@ -70,8 +94,19 @@ async fn main() -> Result<()> {
     info!("Max file size: {} bytes", args.max_file_size);
     info!("Request timeout: {} seconds", args.request_timeout);
 
+    let trash_config = TrashConfig {
+        enabled: args.trash,
+        max_age_days: Some(args.trash_max_age_days).filter(|days| *days > 0),
+        max_size: Some(args.trash_max_size).filter(|size| *size > 0),
+    };
+    info!("Trash: {:?}", trash_config);
+
+    let journal_dir = args.journal_dir.clone().unwrap_or_else(Journal::default_dir);
+    info!("Journal: {} (up to {} revisions per file)", journal_dir.display(), args.journal_max_revisions);
+    let journal = Journal::new(journal_dir, args.journal_max_revisions);
+
     // Create and build server
-    let server = build_server(allowed_paths, args.max_file_size)?;
+    let server = build_server(allowed_paths, args.max_file_size, trash_config, journal)?;
 
     // Run server
     info!("Server initialized. Waiting for client connection...");
@@ -199,11 +234,22 @@ fn setup_logging(log_level: &str, log_file: Option<&Path>) -> Result<()> {
 }
 
 // Build the MCP server with all filesystem tools
-fn build_server(allowed_paths: AllowedPaths, max_file_size: u64) -> Result<modelcontextprotocol_server::Server> {
+fn build_server(
+    allowed_paths: AllowedPaths,
+    max_file_size: u64,
+    trash_config: TrashConfig,
+    journal: Journal,
+) -> Result<modelcontextprotocol_server::Server> {
     // Create a new server builder
     let mut server_builder =
         ServerBuilder::new("filesystem-server", "0.1.0").with_transport(StdioTransport::new());
 
+    // Advisory locks are shared by all tools of this server
+    let locks = Arc::new(LockManager::new(LockManager::default_dir()));
+
+    // The edit journal records every change made by the mutating tools
+    let journal = Arc::new(journal);
+
     // Add the list tool
     server_builder = server_builder.with_tool(
         "list",
@@ -230,7 +276,9 @@ fn build_server(allowed_paths: AllowedPaths, max_file_size: u64) -> Result<model
         tools::write::schema(),
         {
             let paths = allowed_paths.clone();
-            move |args| tools::write::execute(&args, &paths)
+            let locks = locks.clone();
+            let journal = journal.clone();
+            move |args| tools::write::execute(&args, &paths, &locks, &journal)
         },
     );
 
@@ -248,11 +296,49 @@ fn build_server(allowed_paths: AllowedPaths, max_file_size: u64) -> Result<model
     // Add the delete tool
     server_builder = server_builder.with_tool(
         "delete",
-        Some("Delete files or directories"),
+        Some("Delete files or directories (moved to the trash when trash mode is enabled)"),
         tools::delete::schema(),
         {
             let paths = allowed_paths.clone();
-            move |args| tools::delete::execute(&args, &paths)
+            let trash = trash_config.clone();
+            let locks = locks.clone();
+            let journal = journal.clone();
+            move |args| tools::delete::execute(&args, &paths, &trash, &locks, &journal)
+        },
+    );
+
+    // Add the list_trash tool
+    server_builder = server_builder.with_tool(
+        "list_trash",
+        Some("List items in the trash"),
+        tools::list_trash::schema(),
+        {
+            let paths = allowed_paths.clone();
+            move |args| tools::list_trash::execute(&args, &paths)
+        },
+    );
+
+    // Add the restore tool
+    server_builder = server_builder.with_tool(
+        "restore",
+        Some("Restore an item from the trash"),
+        tools::restore::schema(),
+        {
+            let paths = allowed_paths.clone();
+            let locks = locks.clone();
+            move |args| tools::restore::execute(&args, &paths, &locks)
+        },
+    );
+
+    // Add the empty_trash tool
+    server_builder = server_builder.with_tool(
+        "empty_trash",
+        Some("Permanently delete items from the trash"),
+        tools::empty_trash::schema(),
+        {
+            let paths = allowed_paths.clone();
+            let trash = trash_config.clone();
+            move |args| tools::empty_trash::execute(&args, &paths, &trash)
         },
     );
 
@@ -263,7 +349,8 @@ fn build_server(allowed_paths: AllowedPaths, max_file_size: u64) -> Result<model
         tools::copy::schema(),
         {
             let paths = allowed_paths.clone();
-            move |args| tools::copy::execute(&args, &paths)
+            let locks = locks.clone();
+            move |args| tools::copy::execute(&args, &paths, &locks)
         },
     );
 
@@ -274,7 +361,9 @@ fn build_server(allowed_paths: AllowedPaths, max_file_size: u64) -> Result<model
         tools::move_file::schema(),
         {
             let paths = allowed_paths.clone();
-            move |args| tools::move_file::execute(&args, &paths)
+            let locks = locks.clone();
+            let journal = journal.clone();
+            move |args| tools::move_file::execute(&args, &paths, &locks, &journal)
         },
     );
 
@@ -289,6 +378,17 @@ fn build_server(allowed_paths: AllowedPaths, max_file_size: u64) -> Result<model
         },
     );
 
+    // Add the outline tool
+    server_builder = server_builder.with_tool(
+        "outline",
+        Some("List the functions, types, classes or headings of a file with their line ranges"),
+        tools::outline::schema(),
+        {
+            let paths = allowed_paths.clone();
+            move |args| tools::outline::execute(&args, &paths)
+        },
+    );
+
     // Add the search tool
     server_builder = server_builder.with_tool(
         "search",
@@ -318,7 +418,122 @@ fn build_server(allowed_paths: AllowedPaths, max_file_size: u64) -> Result<model
         tools::edit::schema(),
         {
             let paths = allowed_paths.clone();
-            move |args| tools::edit::execute(&args, &paths)
+            let locks = locks.clone();
+            let journal = journal.clone();
+            move |args| tools::edit::execute(&args, &paths, &locks, &journal)
+        },
+    );
+
+    // Add the batch_edit tool
+    server_builder = server_builder.with_tool(
+        "batch_edit",
+        Some("Edit several files at once; either all edits are written or none"),
+        tools::batch_edit::schema(),
+        {
+            let paths = allowed_paths.clone();
+            let locks = locks.clone();
+            let journal = journal.clone();
+            move |args| tools::batch_edit::execute(&args, &paths, &locks, &journal)
+        },
+    );
+
+    // Add the edit_structured tool
+    server_builder = server_builder.with_tool(
+        "edit_structured",
+        Some("Set, delete, append or merge a value in a JSON, YAML or TOML file by key path"),
+        tools::edit_structured::schema(),
+        {
+            let paths = allowed_paths.clone();
+            let locks = locks.clone();
+            let journal = journal.clone();
+            move |args| tools::edit_structured::execute(&args, &paths, &locks, &journal)
+        },
+    );
+
+    // Add the patch tool
+    server_builder = server_builder.with_tool(
+        "patch",
+        Some("Apply a unified diff to one or more files"),
+        tools::patch::schema(),
+        {
+            let paths = allowed_paths.clone();
+            let trash = trash_config.clone();
+            let locks = locks.clone();
+            let journal = journal.clone();
+            move |args| tools::patch::execute(&args, &paths, &trash, &locks, &journal)
+        },
+    );
+
+    // Add the lock tool
+    server_builder = server_builder.with_tool(
+        "lock",
+        Some("Acquire or renew an advisory lock on a path"),
+        tools::lock::schema(),
+        {
+            let paths = allowed_paths.clone();
+            let locks = locks.clone();
+            move |args| tools::lock::execute(&args, &paths, &locks)
+        },
+    );
+
+    // Add the unlock tool
+    server_builder = server_builder.with_tool(
+        "unlock",
+        Some("Release an advisory lock on a path"),
+        tools::unlock::schema(),
+        {
+            let paths = allowed_paths.clone();
+            let locks = locks.clone();
+            move |args| tools::unlock::execute(&args, &paths, &locks)
+        },
+    );
+
+    // Add the list_locks tool
+    server_builder = server_builder.with_tool(
+        "list_locks",
+        Some("List active advisory locks"),
+        tools::list_locks::schema(),
+        {
+            let paths = allowed_paths.clone();
+            let locks = locks.clone();
+            move |args| tools::list_locks::execute(&args, &paths, &locks)
+        },
+    );
+
+    // Add the history tool
+    server_builder = server_builder.with_tool(
+        "history",
+        Some("List the recorded revisions of a file"),
+        tools::history::schema(),
+        {
+            let paths = allowed_paths.clone();
+            let journal = journal.clone();
+            move |args| tools::history::execute(&args, &paths, &journal)
+        },
+    );
+
+    // Add the undo tool
+    server_builder = server_builder.with_tool(
+        "undo",
+        Some("Restore a file to an earlier revision from the edit journal"),
+        tools::undo::schema(),
+        {
+            let paths = allowed_paths.clone();
+            let journal = journal.clone();
+            let locks = locks.clone();
+            move |args| tools::undo::execute(&args, &paths, &journal, &locks)
+        },
+    );
+
+    // Add the redo tool
+    server_builder = server_builder.with_tool(
+        "redo",
+        Some("Reapply revisions of a file that were undone"),
+        tools::redo::schema(),
+        {
+            let paths = allowed_paths.clone();
+            let journal = journal.clone();
+            move |args| tools::redo::execute(&args, &paths, &journal, &locks)
         },
     );
 
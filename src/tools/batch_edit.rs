@@ -0,0 +1,589 @@
+use anyhow::{anyhow, Result};
+use mcp_protocol::types::tool::{ToolCallResult, ToolContent};
+use serde_json::{json, Value};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+use crate::tools::edit::{self, PositionEncoding};
+use crate::utils::atomic::{self, StagedFile};
+use crate::utils::encoding::Charset;
+use crate::utils::journal::Journal;
+use crate::utils::lock::LockManager;
+use crate::utils::path::{AllowedPaths, PathError, is_text_file};
+use crate::utils::plan::{self, ActionKind, Plan};
+use crate::utils::precondition::{self, Preconditions};
+use crate::utils::result::error_result;
+
+// Define the schema for the tool
+pub fn schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "files": {
+                "type": "array",
+                "description": "Files to edit; either every file is changed or none is",
+                "items": {
+                    "type": "object",
+                    "properties": {
+                        "path": {
+                            "type": "string",
+                            "description": "Full path to the file to edit"
+                        },
+                        "operations": {
+                            "type": "array",
+                            "description": "List of edit operations to perform (in order), as for the edit tool",
+                            "items": edit::operations_schema()
+                        },
+                        "language": edit::language_schema(),
+                        "create_if_missing": {
+                            "type": "boolean",
+                            "description": "Create the file if it doesn't exist",
+                            "default": false
+                        },
+                        "if_match_hash": {
+                            "type": "string",
+                            "description": "Only proceed if the file's current SHA-256 hash (as returned by read or info) matches"
+                        },
+                        "if_unmodified_since": {
+                            "type": "string",
+                            "description": "Only proceed if the file has not been modified after this RFC 3339 timestamp (as returned by read or info)"
+                        }
+                    },
+                    "required": ["path", "operations"]
+                }
+            },
+            "position_encoding": edit::position_encoding_schema(),
+            "dry_run": {
+                "type": "boolean",
+                "description": "Validate and report the planned changes without modifying the filesystem",
+                "default": false
+            },
+            "session": {
+                "type": "string",
+                "description": "Session identifier used with the lock tool; paths locked by other sessions are refused"
+            }
+        },
+        "required": ["files"]
+    })
+}
+
+// A file edited in memory, waiting to be committed
+struct FileEdit {
+    display: String,
+    path: PathBuf,
+    existed: bool,
+    // The file as read, restored on rollback
+    original: Vec<u8>,
+    charset: Charset,
+    // The edited text, encoded in the file's charset
+    output: Vec<u8>,
+    operations_applied: usize,
+    errors: Vec<String>,
+    warnings: Vec<String>,
+}
+
+// Execute the batch_edit tool
+pub fn execute(args: &Value, allowed_paths: &AllowedPaths, locks: &LockManager, journal: &Journal) -> Result<ToolCallResult> {
+    // Extract parameters
+    let files = args.get("files")
+        .and_then(|v| v.as_array())
+        .ok_or_else(|| anyhow!("Missing or invalid files array"))?;
+
+    if files.is_empty() {
+        return Ok(error_result("No files specified".to_string()));
+    }
+
+    let dry_run = args.get("dry_run")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let session = args.get("session").and_then(|v| v.as_str());
+
+    let position_encoding = match PositionEncoding::from_args(args) {
+        Ok(encoding) => encoding,
+        Err(e) => return Ok(error_result(e.to_string())),
+    };
+
+    debug!("Batch editing {} files, dry_run: {}", files.len(), dry_run);
+
+    // Validate every file and apply its operations in memory
+    let mut edits: Vec<FileEdit> = files
+        .iter()
+        .map(|file| prepare_edit(file, allowed_paths, locks, session, position_encoding))
+        .collect();
+
+    // The same file twice would make the result depend on commit order
+    let mut seen = HashSet::new();
+    for file_edit in &mut edits {
+        if !file_edit.path.as_os_str().is_empty() && !seen.insert(file_edit.path.clone()) {
+            file_edit.errors.push("File is listed more than once".to_string());
+        }
+    }
+
+    let failed = edits.iter().filter(|e| !e.errors.is_empty()).count();
+
+    // Report what would be written
+    if dry_run {
+        let mut plan = Plan::new("batch_edit");
+        for file_edit in &edits {
+            if !file_edit.errors.is_empty() {
+                let action = if file_edit.existed { ActionKind::OverwriteFile } else { ActionKind::CreateFile };
+                plan.add_conflict(action, &file_edit.path, file_edit.errors.join("; "));
+                continue;
+            }
+            if !file_edit.existed {
+                if let Some(parent) = file_edit.path.parent() {
+                    plan::add_missing_dirs(&mut plan, parent);
+                }
+            }
+            let action = if file_edit.existed { ActionKind::OverwriteFile } else { ActionKind::CreateFile };
+            plan.add(action, &file_edit.path, Some(file_edit.output.len() as u64));
+            for warning in &file_edit.warnings {
+                plan.add_note(format!("{}: {}", file_edit.display, warning));
+            }
+        }
+        return Ok(plan.into_result(allowed_paths));
+    }
+
+    if failed > 0 {
+        let mut text = format!(
+            "Batch edit aborted: {} of {} files failed validation. No files were changed.\n",
+            failed,
+            edits.len()
+        );
+        append_file_results(&mut text, &edits);
+        return Ok(error_result(text.trim_end().to_string()));
+    }
+
+    match commit(&edits) {
+        Ok(warnings) => {
+            for file_edit in &edits {
+                let before = if file_edit.existed { Some(file_edit.original.clone()) } else { None };
+                journal.record(&file_edit.path, "batch_edit", Some(before), Some(&file_edit.output));
+            }
+
+            let mut text = format!("Batch edit committed: {} files changed\n", edits.len());
+            for file_edit in &edits {
+                text.push_str(&format!(
+                    "  - {}: {}{} operations applied (hash: {})\n",
+                    file_edit.display,
+                    if file_edit.existed { "" } else { "created, " },
+                    file_edit.operations_applied,
+                    precondition::hash_bytes(&file_edit.output)
+                ));
+            }
+            for file_edit in &edits {
+                for warning in &file_edit.warnings {
+                    text.push_str(&format!("Warning: {}: {}\n", file_edit.display, warning));
+                }
+            }
+            for warning in warnings {
+                text.push_str(&format!("Warning: {}\n", warning));
+            }
+
+            Ok(ToolCallResult {
+                content: vec![ToolContent::Text { text: text.trim_end().to_string() }],
+                is_error: Some(false),
+            })
+        }
+        Err(e) => Ok(error_result(format!("Batch edit failed: {}", e))),
+    }
+}
+
+// Validate one file entry and apply its operations in memory
+fn prepare_edit(
+    file: &Value,
+    allowed_paths: &AllowedPaths,
+    locks: &LockManager,
+    session: Option<&str>,
+    position_encoding: PositionEncoding,
+) -> FileEdit {
+    let path_str = file.get("path").and_then(|v| v.as_str()).unwrap_or_default();
+    let mut file_edit = FileEdit {
+        display: path_str.to_string(),
+        path: PathBuf::new(),
+        existed: false,
+        original: Vec::new(),
+        charset: Charset::UTF8,
+        output: Vec::new(),
+        operations_applied: 0,
+        errors: Vec::new(),
+        warnings: Vec::new(),
+    };
+
+    if let Err(e) = load_file(&mut file_edit, file, allowed_paths, locks, session) {
+        file_edit.errors.push(e);
+        return file_edit;
+    }
+
+    let operations = match file.get("operations").and_then(|v| v.as_array()) {
+        Some(operations) if !operations.is_empty() => operations,
+        _ => {
+            file_edit.errors.push("Missing or empty operations array".to_string());
+            return file_edit;
+        }
+    };
+
+    let language = match edit::source_language(file, &file_edit.path) {
+        Ok(language) => language,
+        Err(e) => {
+            file_edit.errors.push(e.to_string());
+            return file_edit;
+        }
+    };
+
+    // Edit the text as UTF-8 whatever the file's charset, and save it back in that charset
+    let mut content = file_edit.charset.decode(&file_edit.original);
+    let (operations_applied, operation_results) = edit::apply_operations(operations, &mut content, position_encoding, language);
+    match file_edit.charset.encode(&content) {
+        Ok(output) => file_edit.output = output,
+        Err(e) => file_edit.errors.push(e.to_string()),
+    }
+    file_edit.operations_applied = operations_applied;
+    file_edit.errors.extend(operation_results.iter().filter(|op| !op.success).map(|op| {
+        format!(
+            "Operation {}: {}",
+            op.operation_index,
+            op.error.as_deref().unwrap_or("Unknown error")
+        )
+    }));
+
+    file_edit
+}
+
+// Resolve, check and read the file of an entry
+fn load_file(
+    file_edit: &mut FileEdit,
+    file: &Value,
+    allowed_paths: &AllowedPaths,
+    locks: &LockManager,
+    session: Option<&str>,
+) -> Result<(), String> {
+    if file_edit.display.is_empty() {
+        return Err("Missing path".to_string());
+    }
+
+    let create_if_missing = file.get("create_if_missing")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let path = Path::new(&file_edit.display);
+    file_edit.path = match allowed_paths.validate_path(path) {
+        Ok(p) => p,
+        Err(PathError::NotFound) if create_if_missing => {
+            // Allow missing parent directories as long as the nearest existing ancestor is allowed
+            let mut existing = path;
+            while !existing.exists() {
+                existing = existing.parent().ok_or_else(|| "Invalid path: no parent directory".to_string())?;
+            }
+            allowed_paths.validate_path(existing).map_err(|e| path_error_message(e, &file_edit.display))?;
+            path.to_path_buf()
+        }
+        Err(PathError::NotFound) => {
+            return Err(format!("File not found: '{}'. Use create_if_missing=true to create it.", file_edit.display));
+        }
+        Err(e) => return Err(path_error_message(e, &file_edit.display)),
+    };
+
+    if file_edit.path.is_dir() {
+        return Err("Path is a directory, not a file".to_string());
+    }
+
+    let preconditions = Preconditions::from_args(file).map_err(|e| e.to_string())?;
+    preconditions.check(&file_edit.path).map_err(|e| e.to_string())?;
+    locks.check(&file_edit.path, session).map_err(|e| e.to_string())?;
+
+    file_edit.existed = file_edit.path.exists();
+    if file_edit.existed {
+        match is_text_file(&file_edit.path) {
+            Ok(true) => {}
+            Ok(false) => return Err("File appears to be binary, editing not supported".to_string()),
+            Err(e) => return Err(format!("Failed to determine file type: {}", e)),
+        }
+        file_edit.original = fs::read(&file_edit.path).map_err(|e| format!("Failed to read file: {}", e))?;
+        let (charset, warning) = Charset::detect_for_edit(&file_edit.original);
+        file_edit.charset = charset;
+        file_edit.warnings.extend(warning);
+    } else if !create_if_missing {
+        return Err(format!("File not found: '{}'", file_edit.display));
+    }
+
+    Ok(())
+}
+
+// Write every edited file, or none of them
+//
+// All new contents are staged in temp files first, so most failures leave
+// the targets untouched. If renaming a staged file fails, the files already
+// committed are restored to their original contents (or removed, if they
+// were created) before the error is returned.
+fn commit(edits: &[FileEdit]) -> Result<Vec<String>, String> {
+    // Make sure nothing changed since the files were read
+    for file_edit in edits {
+        let unchanged = if file_edit.existed {
+            precondition::hash_file(&file_edit.path)
+                .map(|hash| hash == precondition::hash_bytes(&file_edit.original))
+                .unwrap_or(false)
+        } else {
+            !file_edit.path.exists()
+        };
+        if !unchanged {
+            return Err(format!(
+                "'{}' changed while the batch was being prepared. No files were changed.",
+                file_edit.display
+            ));
+        }
+    }
+
+    // Create missing parent directories, remembering them for rollback
+    let mut created_dirs: Vec<PathBuf> = Vec::new();
+    for file_edit in edits.iter().filter(|e| !e.existed) {
+        if let Some(parent) = file_edit.path.parent() {
+            let mut missing: Vec<PathBuf> = parent
+                .ancestors()
+                .take_while(|p| !p.exists())
+                .map(|p| p.to_path_buf())
+                .collect();
+            missing.reverse();
+            for dir in missing {
+                if let Err(e) = fs::create_dir(&dir) {
+                    remove_dirs(&created_dirs);
+                    return Err(format!("Failed to create directory '{}': {}. No files were changed.", dir.display(), e));
+                }
+                created_dirs.push(dir);
+            }
+        }
+    }
+
+    // Stage every file before touching any target
+    let mut staged: Vec<StagedFile> = Vec::with_capacity(edits.len());
+    for file_edit in edits {
+        match atomic::stage_file(&file_edit.path, &file_edit.output) {
+            Ok(file) => staged.push(file),
+            Err(e) => {
+                drop(staged);
+                remove_dirs(&created_dirs);
+                return Err(format!("Failed to stage '{}': {}. No files were changed.", file_edit.display, e));
+            }
+        }
+    }
+
+    // Rename the staged files into place
+    let mut warnings = Vec::new();
+    let mut committed = 0;
+    let mut staged = staged.into_iter();
+    while let Some(file) = staged.next() {
+        match file.commit() {
+            Ok(outcome) => {
+                warnings.extend(outcome.warnings);
+                committed += 1;
+            }
+            Err(e) => {
+                let file_edit = &edits[committed];
+                drop(staged);
+                let rollback_errors = rollback(&edits[..committed]);
+                remove_dirs(&created_dirs);
+
+                let mut message = format!("Failed to write '{}': {}. ", file_edit.display, e);
+                if rollback_errors.is_empty() {
+                    message.push_str("All files were rolled back.");
+                } else {
+                    message.push_str(&format!("Rollback incomplete: {}", rollback_errors.join("; ")));
+                }
+                return Err(message);
+            }
+        }
+    }
+
+    Ok(warnings)
+}
+
+// Restore committed files to their state before the batch
+fn rollback(committed: &[FileEdit]) -> Vec<String> {
+    let mut errors = Vec::new();
+
+    for file_edit in committed.iter().rev() {
+        let restored = if file_edit.existed {
+            atomic::write_atomic(&file_edit.path, &file_edit.original).map(|_| ())
+        } else {
+            fs::remove_file(&file_edit.path)
+        };
+
+        if let Err(e) = restored {
+            warn!("Failed to roll back '{}': {}", file_edit.path.display(), e);
+            errors.push(format!("'{}': {}", file_edit.display, e));
+        }
+    }
+
+    errors
+}
+
+// Remove directories created for new files, innermost first
+fn remove_dirs(dirs: &[PathBuf]) {
+    for dir in dirs.iter().rev() {
+        if let Err(e) = fs::remove_dir(dir) {
+            warn!("Failed to remove directory '{}': {}", dir.display(), e);
+        }
+    }
+}
+
+fn append_file_results(text: &mut String, edits: &[FileEdit]) {
+    for file_edit in edits {
+        if file_edit.errors.is_empty() {
+            text.push_str(&format!(
+                "  - {}: ok ({} operations applied in memory)\n",
+                file_edit.display, file_edit.operations_applied
+            ));
+        } else {
+            text.push_str(&format!("  - {}: failed\n", file_edit.display));
+            for error in &file_edit.errors {
+                text.push_str(&format!("      {}\n", error));
+            }
+        }
+    }
+}
+
+fn path_error_message(error: PathError, path_str: &str) -> String {
+    match error {
+        PathError::OutsideAllowedPaths =>
+            "Path is outside of all allowed directories".to_string(),
+        PathError::NotFound =>
+            format!("Path not found: '{}'", path_str),
+        PathError::IoError(io_err) =>
+            format!("IO error: {}", io_err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_batch_edit_is_all_or_nothing() {
+        let temp_dir = tempdir().unwrap();
+        let allowed_paths = AllowedPaths::new(vec![temp_dir.path().to_path_buf()]).unwrap();
+        let lock_dir = tempdir().unwrap();
+        let locks = LockManager::new(lock_dir.path().to_path_buf());
+        let journal_dir = tempdir().unwrap();
+        let journal = Journal::new(journal_dir.path().to_path_buf(), 10);
+
+        let a = temp_dir.path().join("a.txt");
+        let b = temp_dir.path().join("b.txt");
+        fs::write(&a, "alpha\n").unwrap();
+        fs::write(&b, "beta\n").unwrap();
+
+        // The second file fails, so the first must not be written either
+        let args = json!({
+            "files": [
+                { "path": a, "operations": [{ "type": "replace", "find": "alpha", "replace": "ALPHA" }] },
+                { "path": b, "operations": [{ "type": "replace", "find": "missing", "replace": "x" }] }
+            ]
+        });
+        let result = execute(&args, &allowed_paths, &locks, &journal).unwrap();
+        assert_eq!(result.is_error, Some(true));
+        assert_eq!(fs::read_to_string(&a).unwrap(), "alpha\n");
+        assert_eq!(fs::read_to_string(&b).unwrap(), "beta\n");
+
+        let c = temp_dir.path().join("sub").join("c.txt");
+        let args = json!({
+            "files": [
+                { "path": a, "operations": [{ "type": "replace", "find": "alpha", "replace": "ALPHA" }] },
+                { "path": c, "create_if_missing": true, "operations": [{ "type": "insert", "position": 0, "content": "new\n" }] }
+            ]
+        });
+        let result = execute(&args, &allowed_paths, &locks, &journal).unwrap();
+        assert_eq!(result.is_error, Some(false));
+        assert_eq!(fs::read_to_string(&a).unwrap(), "ALPHA\n");
+        assert_eq!(fs::read_to_string(&c).unwrap(), "new\n");
+    }
+
+    #[test]
+    fn test_batch_edit_keeps_charsets() {
+        let temp_dir = tempdir().unwrap();
+        let allowed_paths = AllowedPaths::new(vec![temp_dir.path().to_path_buf()]).unwrap();
+        let lock_dir = tempdir().unwrap();
+        let locks = LockManager::new(lock_dir.path().to_path_buf());
+        let journal_dir = tempdir().unwrap();
+        let journal = Journal::new(journal_dir.path().to_path_buf(), 10);
+
+        let latin1 = temp_dir.path().join("latin1.txt");
+        let utf16 = temp_dir.path().join("utf16.txt");
+        fs::write(&latin1, b"caf\xe9\n").unwrap();
+        fs::write(&utf16, b"\xff\xfeh\x00i\x00").unwrap();
+
+        let args = json!({
+            "files": [
+                { "path": latin1, "operations": [{ "type": "replace", "find": "café", "replace": "crème" }] },
+                { "path": utf16, "operations": [{ "type": "replace", "find": "hi", "replace": "hé" }] }
+            ]
+        });
+        let result = execute(&args, &allowed_paths, &locks, &journal).unwrap();
+        assert_eq!(result.is_error, Some(false));
+        assert_eq!(fs::read(&latin1).unwrap(), b"cr\xe8me\n");
+        assert_eq!(fs::read(&utf16).unwrap(), b"\xff\xfeh\x00\xe9\x00");
+
+        // Text the file's charset cannot hold fails the whole batch
+        let args = json!({
+            "files": [
+                { "path": utf16, "operations": [{ "type": "replace", "find": "hé", "replace": "hi" }] },
+                { "path": latin1, "operations": [{ "type": "replace", "find": "crème", "replace": "日本" }] }
+            ]
+        });
+        let result = execute(&args, &allowed_paths, &locks, &journal).unwrap();
+        assert_eq!(result.is_error, Some(true));
+        assert_eq!(fs::read(&utf16).unwrap(), b"\xff\xfeh\x00\xe9\x00");
+    }
+
+    #[test]
+    fn test_batch_edit_damaged_utf8_file() {
+        let temp_dir = tempdir().unwrap();
+        let allowed_paths = AllowedPaths::new(vec![temp_dir.path().to_path_buf()]).unwrap();
+        let lock_dir = tempdir().unwrap();
+        let locks = LockManager::new(lock_dir.path().to_path_buf());
+        let journal_dir = tempdir().unwrap();
+        let journal = Journal::new(journal_dir.path().to_path_buf(), 10);
+
+        // One invalid byte does not make a UTF-8 file windows-1252
+        let file = temp_dir.path().join("damaged.txt");
+        fs::write(&file, b"let name = \"caf\xc3\xa9\";\n// damaged: \xff\n").unwrap();
+        let args = json!({
+            "files": [{ "path": file, "operations": [{ "type": "replace", "find": "café", "replace": "naïve" }] }]
+        });
+        let result = execute(&args, &allowed_paths, &locks, &journal).unwrap();
+        assert_eq!(result.is_error, Some(false));
+        assert_eq!(fs::read(&file).unwrap(), "let name = \"naïve\";\n// damaged: \u{fffd}\n".as_bytes());
+        match &result.content[0] {
+            ToolContent::Text { text } => assert!(text.contains("is not valid UTF-8"), "{}", text),
+            _ => panic!("Expected text content"),
+        }
+    }
+
+    #[test]
+    fn test_rollback_restores_files() {
+        let temp_dir = tempdir().unwrap();
+        let existing = temp_dir.path().join("existing.txt");
+        let created = temp_dir.path().join("created.txt");
+        fs::write(&existing, "changed").unwrap();
+        fs::write(&created, "new").unwrap();
+
+        let edit_of = |path: &Path, existed: bool, original: &str| FileEdit {
+            display: path.display().to_string(),
+            path: path.to_path_buf(),
+            existed,
+            original: original.as_bytes().to_vec(),
+            charset: Charset::UTF8,
+            output: Vec::new(),
+            operations_applied: 1,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+        };
+
+        let errors = rollback(&[edit_of(&existing, true, "original"), edit_of(&created, false, "")]);
+        assert!(errors.is_empty());
+        assert_eq!(fs::read_to_string(&existing).unwrap(), "original");
+        assert!(!created.exists());
+    }
+}
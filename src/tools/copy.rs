@@ -8,7 +8,9 @@ use std::{
 use tracing::debug;
 use walkdir::WalkDir;
 
+use crate::utils::lock::{self, LockManager};
 use crate::utils::path::{AllowedPaths, PathError};
+use crate::utils::plan::{ActionKind, Plan};
 
 // Define the schema for the tool
 pub fn schema() -> Value {
@@ -32,6 +34,15 @@ pub fn schema() -> Value {
                 "type": "boolean",
                 "description": "Whether to copy directories recursively",
                 "default": true
+            },
+            "dry_run": {
+                "type": "boolean",
+                "description": "Validate and report the planned changes without modifying the filesystem",
+                "default": false
+            },
+            "session": {
+                "type": "string",
+                "description": "Session identifier used with the lock tool; paths locked by other sessions are refused"
             }
         },
         "required": ["source", "destination"]
@@ -39,7 +50,7 @@ pub fn schema() -> Value {
 }
 
 // Execute the copy tool
-pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallResult> {
+pub fn execute(args: &Value, allowed_paths: &AllowedPaths, locks: &LockManager) -> Result<ToolCallResult> {
     // Extract required parameters
     let source_str = args.get("source")
         .and_then(|v| v.as_str())
@@ -58,9 +69,15 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
         .and_then(|v| v.as_bool())
         .unwrap_or(true);
     
+    let dry_run = args.get("dry_run")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    
+    let session = args.get("session").and_then(|v| v.as_str());
+    
     debug!(
-        "Copying from '{}' to '{}', overwrite: {}, recursive: {}",
-        source_str, destination_str, overwrite, recursive
+        "Copying from '{}' to '{}', overwrite: {}, recursive: {}, dry_run: {}",
+        source_str, destination_str, overwrite, recursive, dry_run
     );
     
     // Create Path objects
@@ -173,6 +190,30 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
         }
     };
     
+    // Refuse to write into a path locked by another session; the source is only read
+    let target = match validated_source.file_name() {
+        Some(name) if !source_metadata.is_dir() && validated_destination.is_dir() => validated_destination.join(name),
+        _ => validated_destination.clone(),
+    };
+    if let Err(e) = locks.check(&target, session) {
+        return Ok(lock::error_result(e));
+    }
+    
+    // Report what would be copied
+    if dry_run {
+        let mut plan = Plan::new("copy");
+        if !source_metadata.is_dir() {
+            plan_copy_file(&mut plan, &validated_source, &validated_destination, overwrite);
+        } else if !recursive {
+            plan.add_error(format!("Source is a directory but recursive is false: '{}'", source_str));
+        } else if validated_destination.exists() && !validated_destination.is_dir() {
+            plan.add_conflict(ActionKind::CreateDir, &validated_destination, "Destination exists but is not a directory");
+        } else {
+            plan_copy_dir(&mut plan, &validated_source, &validated_destination, overwrite);
+        }
+        return Ok(plan.into_result(allowed_paths));
+    }
+    
     // Perform the copy
     if source_metadata.is_dir() {
         // Directory copy
@@ -347,3 +388,61 @@ fn copy_dir_recursive(source: &Path, destination: &Path, overwrite: bool) -> Res
         is_error: Some(!errors.is_empty()),
     })
 }
+
+// Plan a single file copy, mirroring copy_file
+fn plan_copy_file(plan: &mut Plan, source: &Path, destination: &Path, overwrite: bool) {
+    if destination.is_dir() {
+        match source.file_name() {
+            Some(file_name) => plan_copy_file(plan, source, &destination.join(file_name), overwrite),
+            None => plan.add_error("Invalid source filename"),
+        }
+        return;
+    }
+    
+    let exists = destination.exists();
+    let action = if exists { ActionKind::OverwriteFile } else { ActionKind::CreateFile };
+    if exists && !overwrite {
+        plan.add_conflict(action, destination, "File already exists and overwrite is false");
+        return;
+    }
+    
+    let bytes = fs::metadata(source).map(|m| m.len()).ok();
+    plan.add(action, destination, bytes).source = Some(source.display().to_string());
+}
+
+// Plan a recursive directory copy, mirroring copy_dir_recursive
+fn plan_copy_dir(plan: &mut Plan, source: &Path, destination: &Path, overwrite: bool) {
+    if !destination.exists() {
+        plan.add(ActionKind::CreateDir, destination, None);
+    }
+    
+    for entry_result in WalkDir::new(source) {
+        let entry = match entry_result {
+            Ok(e) => e,
+            Err(e) => {
+                plan.add_error(format!("Error walking directory: {}", e));
+                continue;
+            }
+        };
+        
+        // Skip the root directory itself
+        if entry.path() == source {
+            continue;
+        }
+        
+        let relative_path = entry.path().strip_prefix(source).unwrap();
+        let target_path = destination.join(relative_path);
+        
+        if entry.file_type().is_dir() {
+            if !target_path.exists() {
+                plan.add(ActionKind::CreateDir, &target_path, None);
+            } else if !target_path.is_dir() {
+                plan.add_conflict(ActionKind::CreateDir, &target_path, "Destination exists but is not a directory");
+            }
+        } else if target_path.is_dir() {
+            plan.add_conflict(ActionKind::OverwriteFile, &target_path, "Destination is a directory");
+        } else {
+            plan_copy_file(plan, entry.path(), &target_path, overwrite);
+        }
+    }
+}
@@ -5,9 +5,15 @@ use std::{
     fs,
     path::Path,
 };
-use tracing::debug;
+use tracing::{debug, warn};
+use walkdir::WalkDir;
 
+use crate::utils::journal::Journal;
+use crate::utils::lock::{self, LockManager};
 use crate::utils::path::{AllowedPaths, PathError};
+use crate::utils::plan::{ActionKind, Plan};
+use crate::utils::precondition::{self, Preconditions};
+use crate::utils::trash::{self, TrashConfig};
 
 // Define the schema for the tool
 pub fn schema() -> Value {
@@ -27,6 +33,28 @@ pub fn schema() -> Value {
                 "type": "boolean",
                 "description": "Force deletion even if errors occur",
                 "default": false
+            },
+            "permanent": {
+                "type": "boolean",
+                "description": "Delete permanently instead of moving to the trash (only relevant when trash mode is enabled)",
+                "default": false
+            },
+            "dry_run": {
+                "type": "boolean",
+                "description": "Validate and report the planned changes without modifying the filesystem",
+                "default": false
+            },
+            "if_match_hash": {
+                "type": "string",
+                "description": "Only proceed if the file's current SHA-256 hash (as returned by read or info) matches"
+            },
+            "if_unmodified_since": {
+                "type": "string",
+                "description": "Only proceed if the file has not been modified after this RFC 3339 timestamp (as returned by read or info)"
+            },
+            "session": {
+                "type": "string",
+                "description": "Session identifier used with the lock tool; paths locked by other sessions are refused"
             }
         },
         "required": ["path"]
@@ -34,7 +62,13 @@ pub fn schema() -> Value {
 }
 
 // Execute the delete tool
-pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallResult> {
+pub fn execute(
+    args: &Value,
+    allowed_paths: &AllowedPaths,
+    trash_config: &TrashConfig,
+    locks: &LockManager,
+    journal: &Journal,
+) -> Result<ToolCallResult> {
     // Extract path parameter (required)
     let path_str = args.get("path")
         .and_then(|v| v.as_str())
@@ -49,9 +83,29 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
         .and_then(|v| v.as_bool())
         .unwrap_or(false);
     
+    let permanent = args.get("permanent")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    
+    let dry_run = args.get("dry_run")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    
+    let session = args.get("session").and_then(|v| v.as_str());
+    
+    let preconditions = match Preconditions::from_args(args) {
+        Ok(p) => p,
+        Err(e) => {
+            return Ok(ToolCallResult {
+                content: vec![ToolContent::Text { text: e.to_string() }],
+                is_error: Some(true),
+            });
+        }
+    };
+    
     debug!(
-        "Deleting path: '{}', recursive: {}, force: {}",
-        path_str, recursive, force
+        "Deleting path: '{}', recursive: {}, force: {}, permanent: {}, dry_run: {}",
+        path_str, recursive, force, permanent, dry_run
     );
     
     // Create Path object
@@ -87,36 +141,120 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
         });
     }
     
+    // Refuse to touch a path that changed since the caller last saw it
+    if let Err(e) = preconditions.check(&validated_path) {
+        return Ok(precondition::error_result(e, &validated_path));
+    }
+    
+    // Refuse to touch a path locked by another session
+    if let Err(e) = locks.check(&validated_path, session) {
+        return Ok(lock::error_result(e));
+    }
+    
     // Determine if it's a file or directory
     let is_dir = validated_path.is_dir();
+    let item_type = if is_dir { "directory" } else { "file" };
     let relative_path = allowed_paths.closest_relative_path(&validated_path);
     
+    // Items already in the trash, and the allowed directories themselves, are never trashed
+    let trash_root = allowed_paths.root_for(&validated_path).filter(|root| {
+        trash_config.enabled
+            && !permanent
+            && validated_path != *root
+            && !trash::is_in_trash(&validated_path, root)
+    });
+    
+    // Keep the non-recursive contract: only empty directories without recursive
+    let non_empty_dir = if is_dir && !recursive && (dry_run || trash_root.is_some()) {
+        match fs::read_dir(&validated_path) {
+            Ok(mut entries) => entries.next().is_some(),
+            Err(e) => {
+                return Ok(ToolCallResult {
+                    content: vec![ToolContent::Text {
+                        text: format!("Failed to read directory '{}': {}", relative_path, e),
+                    }],
+                    is_error: Some(true),
+                });
+            }
+        }
+    } else {
+        false
+    };
+    
+    // Report every path that would be removed, deepest first
+    if dry_run {
+        let mut plan = Plan::new("delete");
+        let action = if trash_root.is_some() { ActionKind::Trash } else { ActionKind::Remove };
+        
+        if non_empty_dir {
+            plan.add_conflict(action, &validated_path, "Directory is not empty and recursive is false");
+        } else {
+            for entry_result in WalkDir::new(&validated_path).contents_first(true).follow_links(false) {
+                match entry_result {
+                    Ok(entry) => {
+                        let bytes = entry.metadata().ok().filter(|m| m.is_file()).map(|m| m.len());
+                        plan.add(action, entry.path(), bytes);
+                    }
+                    Err(e) => plan.add_error(format!("Error walking directory: {}", e)),
+                }
+            }
+        }
+        
+        return Ok(plan.into_result(allowed_paths));
+    }
+    
+    // Keep the contents of a deleted file for undo (directories are only recoverable from the trash)
+    let before = if is_dir { None } else { journal.capture(&validated_path) };
+    
     // Delete the path
-    let result = if is_dir {
-        if recursive {
-            fs::remove_dir_all(&validated_path)
+    let result = if let Some(root) = trash_root {
+        if non_empty_dir {
+            Err(format!("Directory is not empty: '{}'. Use recursive=true to delete it", relative_path))
         } else {
-            fs::remove_dir(&validated_path)
+            match trash::move_to_trash(&validated_path, root) {
+                Ok(entry) => {
+                    if let Err(e) = trash::enforce_retention(root, trash_config) {
+                        warn!("Failed to enforce trash retention: {}", e);
+                    }
+                    Ok(format!(
+                        "Moved {} to trash: '{}' (trash entry: '{}'). Use the restore tool to recover it.",
+                        item_type, relative_path, entry.name
+                    ))
+                }
+                Err(e) => Err(format!(
+                    "Failed to move path to trash: {}. Use permanent=true to delete it permanently",
+                    e
+                )),
+            }
         }
     } else {
-        fs::remove_file(&validated_path)
+        let removed = if is_dir {
+            if recursive {
+                fs::remove_dir_all(&validated_path)
+            } else {
+                fs::remove_dir(&validated_path)
+            }
+        } else {
+            fs::remove_file(&validated_path)
+        };
+        removed
+            .map(|_| format!("Deleted {}: '{}'", item_type, relative_path))
+            .map_err(|e| format!("Failed to delete path: {}", e))
     };
     
     // Handle the result
     match result {
-        Ok(_) => {
-            let item_type = if is_dir { "directory" } else { "file" };
+        Ok(text) => {
+            journal.record(&validated_path, "delete", before, None);
+
             Ok(ToolCallResult {
-                content: vec![ToolContent::Text {
-                    text: format!("Deleted {}: '{}'", item_type, relative_path),
-                }],
+                content: vec![ToolContent::Text { text }],
                 is_error: Some(false),
             })
         }
         Err(e) => {
             // If force is enabled, return success with a warning
             if force {
-                let item_type = if is_dir { "directory" } else { "file" };
                 Ok(ToolCallResult {
                     content: vec![ToolContent::Text {
                         text: format!("Deletion completed with warning: {} (path: '{}')", e, relative_path),
@@ -125,9 +263,7 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
                 })
             } else {
                 Ok(ToolCallResult {
-                    content: vec![ToolContent::Text {
-                        text: format!("Failed to delete path: {}", e),
-                    }],
+                    content: vec![ToolContent::Text { text: e }],
                     is_error: Some(true),
                 })
             }
@@ -6,9 +6,20 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use tracing::{debug, warn};
 use chrono::{DateTime, Utc};
+use regex::RegexBuilder;
 use std::time::SystemTime;
 
+use crate::utils::atomic;
+use crate::utils::diff;
+use crate::utils::encoding::Charset;
+use crate::utils::journal::Journal;
+use crate::utils::lock::{self, LockManager};
 use crate::utils::path::{AllowedPaths, is_text_file};
+use crate::utils::plan::{self, ActionKind, Plan};
+use crate::utils::precondition::{self, Preconditions};
+use crate::utils::syntax::{self, SourceLanguage, SyntaxItem};
+use crate::utils::text::{self, LineEnding, TextFormat};
+use crate::utils::validate::{self, Validation};
 
 // Define operation types
 #[derive(Debug, Deserialize)]
@@ -22,16 +33,22 @@ enum EditOperation {
         occurrence: i32,
         #[serde(default = "default_case_sensitive")]
         case_sensitive: bool,
+        #[serde(default)]
+        require_unique: bool,
+        #[serde(default)]
+        match_mode: MatchMode,
+        #[serde(default = "default_fuzzy_threshold")]
+        fuzzy_threshold: f64,
     },
     #[serde(rename = "insert")]
     Insert {
-        position: usize,
+        position: Position,
         content: String,
     },
     #[serde(rename = "delete")]
     Delete {
-        start: usize,
-        end: usize,
+        start: Position,
+        end: Position,
     },
     #[serde(rename = "replace_lines")]
     ReplaceLines {
@@ -39,14 +56,156 @@ enum EditOperation {
         end_line: usize,
         content: String,
     },
+    #[serde(rename = "regex_replace")]
+    RegexReplace {
+        pattern: String,
+        replace: String,
+        #[serde(default = "default_case_sensitive")]
+        case_sensitive: bool,
+        #[serde(default)]
+        multi_line: bool,
+        #[serde(default)]
+        dot_all: bool,
+        #[serde(default)]
+        expected_count: Option<usize>,
+        #[serde(default)]
+        limit: Option<usize>,
+    },
+    #[serde(rename = "insert_before")]
+    InsertBefore(AnchoredInsert),
+    #[serde(rename = "insert_after")]
+    InsertAfter(AnchoredInsert),
+    #[serde(rename = "prepend")]
+    Prepend {
+        content: String,
+    },
+    #[serde(rename = "append")]
+    Append {
+        content: String,
+    },
+    #[serde(rename = "normalize_line_endings")]
+    NormalizeLineEndings {
+        line_ending: String,
+        #[serde(default)]
+        trailing_newline: Option<bool>,
+        #[serde(default)]
+        bom: Option<bool>,
+    },
+    #[serde(rename = "replace_item")]
+    ReplaceItem {
+        item: String,
+        content: String,
+    },
+    #[serde(rename = "insert_item_after")]
+    InsertItemAfter {
+        item: String,
+        content: String,
+    },
+    #[serde(rename = "delete_item")]
+    DeleteItem {
+        item: String,
+    },
+}
+
+// A position in the file: a flat offset, or a 0-based line and column
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+enum Position {
+    Offset(usize),
+    LineColumn { line: usize, column: usize },
+}
+
+impl std::fmt::Display for Position {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Position::Offset(offset) => write!(f, "{}", offset),
+            Position::LineColumn { line, column } => write!(f, "line {}, column {}", line, column),
+        }
+    }
+}
+
+// Unit in which offsets and columns are counted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub(crate) enum PositionEncoding {
+    // Unicode scalar values
+    #[default]
+    Char,
+    // UTF-16 code units, as used by LSP and JavaScript strings
+    Utf16,
+}
+
+impl PositionEncoding {
+    // Parse the `position_encoding` argument of a tool call
+    pub(crate) fn from_args(args: &Value) -> Result<Self> {
+        match args.get("position_encoding").and_then(|v| v.as_str()) {
+            None | Some("char") => Ok(PositionEncoding::Char),
+            Some("utf16") => Ok(PositionEncoding::Utf16),
+            Some(other) => Err(anyhow!("Invalid position_encoding '{}' (expected char or utf16)", other)),
+        }
+    }
+    
+    fn width(&self, c: char) -> usize {
+        match self {
+            PositionEncoding::Char => 1,
+            PositionEncoding::Utf16 => c.len_utf16(),
+        }
+    }
+    
+    fn len(&self, text: &str) -> usize {
+        text.chars().map(|c| self.width(c)).sum()
+    }
+}
+
+// How the `find` text of a replace operation is matched
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum MatchMode {
+    // Exact substring match
+    #[default]
+    Exact,
+    // Any run of whitespace matches any other run of whitespace
+    Whitespace,
+    // Whole lines matched ignoring their indentation; the replacement is re-indented
+    Indent,
+    // Whole lines matched by edit-distance similarity
+    Fuzzy,
+}
+
+// Lines inserted next to the line(s) matching an anchor
+#[derive(Debug, Deserialize)]
+struct AnchoredInsert {
+    anchor: String,
+    content: String,
+    #[serde(default)]
+    regex: bool,
+    #[serde(default = "default_case_sensitive")]
+    case_sensitive: bool,
+    #[serde(default)]
+    occurrence: i32,
+    #[serde(default)]
+    require_unique: bool,
+    #[serde(default = "default_match_indentation")]
+    match_indentation: bool,
 }
 
 // Struct to track operation results
 #[derive(Debug, Serialize)]
-struct OperationResult {
-    operation_index: usize,
-    success: bool,
-    error: Option<String>,
+pub(crate) struct OperationResult {
+    pub(crate) operation_index: usize,
+    pub(crate) success: bool,
+    pub(crate) error: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub(crate) replaced_spans: Vec<ReplacedSpan>,
+}
+
+// Lines (0-based, inclusive) of text matched by an operation, before it was replaced
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub(crate) struct ReplacedSpan {
+    pub(crate) start_line: usize,
+    pub(crate) end_line: usize,
+    // Similarity (0.0 to 1.0) of a fuzzy match to the find text
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub(crate) similarity: Option<f64>,
 }
 
 // Struct representing the edit response
@@ -57,8 +216,14 @@ struct EditResponse {
     operations_applied: usize,
     operations_failed: usize,
     failed_operations: Vec<OperationResult>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    replacements: Vec<OperationResult>,
     #[serde(skip_serializing_if = "Option::is_none")]
     backup_path: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    diff: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    validation: Option<Validation>,
     metadata: FileMetadata,
 }
 
@@ -66,6 +231,7 @@ struct EditResponse {
 struct FileMetadata {
     path: String,
     modified: String,
+    hash: String,
     size: u64,
 }
 
@@ -74,6 +240,392 @@ fn default_case_sensitive() -> bool {
     true
 }
 
+fn default_match_indentation() -> bool {
+    true
+}
+
+fn default_fuzzy_threshold() -> f64 {
+    0.8
+}
+
+// Longest find text accepted by fuzzy matching, which compares it against every window of lines
+const MAX_FUZZY_FIND_LEN: usize = 4096;
+
+// Most character pairs fuzzy matching compares in one operation (edit distance is quadratic)
+const MAX_FUZZY_WORK: usize = 200_000_000;
+
+// Schema of a position: a flat offset or a line and column, counted in position_encoding units
+fn position_schema(description: &str) -> Value {
+    json!({
+        "description": format!("{}: a 0-based offset, or {{\"line\", \"column\"}} (both 0-based), counted in position_encoding units", description),
+        "oneOf": [
+            { "type": "integer", "minimum": 0 },
+            {
+                "type": "object",
+                "properties": {
+                    "line": { "type": "integer", "minimum": 0 },
+                    "column": { "type": "integer", "minimum": 0 }
+                },
+                "required": ["line", "column"]
+            }
+        ]
+    })
+}
+
+// Schema of the language argument used by the item operations, shared with batch_edit
+pub(crate) fn language_schema() -> Value {
+    json!({
+        "type": "string",
+        "enum": ["rust", "python", "typescript", "tsx", "javascript", "go"],
+        "description": "Source language for replace_item, insert_item_after and delete_item; detected from the extension if omitted"
+    })
+}
+
+// Language of a file for the item operations: the `language` argument, or detected from the extension
+pub(crate) fn source_language(args: &Value, path: &Path) -> Result<Option<SourceLanguage>> {
+    match args.get("language").and_then(|v| v.as_str()) {
+        Some(name) => SourceLanguage::parse(name)
+            .map(Some)
+            .ok_or_else(|| anyhow!("Invalid language '{}' (expected rust, python, typescript, tsx, javascript or go)", name)),
+        None => Ok(SourceLanguage::from_path(path)),
+    }
+}
+
+// Schema of the position_encoding argument, shared with batch_edit
+pub(crate) fn position_encoding_schema() -> Value {
+    json!({
+        "type": "string",
+        "enum": ["char", "utf16"],
+        "description": "Unit of insert and delete positions and columns: Unicode characters, or UTF-16 code units as used by LSP",
+        "default": "char"
+    })
+}
+
+// Schema of a single edit operation, shared with batch_edit
+pub(crate) fn operations_schema() -> Value {
+    json!({
+        "type": "object",
+        "oneOf": [
+            {
+                "type": "object",
+                "properties": {
+                    "type": {
+                        "type": "string",
+                        "enum": ["replace"],
+                        "description": "Replace operation"
+                    },
+                    "find": {
+                        "type": "string",
+                        "description": "Text to find (exact match)"
+                    },
+                    "replace": {
+                        "type": "string",
+                        "description": "Text to insert as replacement"
+                    },
+                    "occurrence": {
+                        "type": "integer",
+                        "description": "Which occurrence to replace (0-based, -1 for all)",
+                        "default": 0
+                    },
+                    "case_sensitive": {
+                        "type": "boolean",
+                        "description": "Whether the search is case-sensitive",
+                        "default": true
+                    },
+                    "require_unique": {
+                        "type": "boolean",
+                        "description": "Fail unless the text matches exactly once; the error lists every candidate with its line number and context",
+                        "default": false
+                    },
+                    "match_mode": {
+                        "type": "string",
+                        "enum": ["exact", "whitespace", "indent", "fuzzy"],
+                        "description": "How to match find: exact text; whitespace (runs of whitespace match any whitespace); indent (whole lines ignoring indentation, replacement re-indented to the match); fuzzy (whole lines by similarity ignoring indentation, best match only, replacement re-indented)",
+                        "default": "exact"
+                    },
+                    "fuzzy_threshold": {
+                        "type": "number",
+                        "description": "Minimum similarity (0.0 to 1.0) accepted by fuzzy matching",
+                        "default": 0.8
+                    }
+                },
+                "required": ["type", "find", "replace"]
+            },
+            {
+                "type": "object",
+                "properties": {
+                    "type": {
+                        "type": "string",
+                        "enum": ["insert"],
+                        "description": "Insert operation"
+                    },
+                    "position": position_schema("Position to insert at"),
+                    "content": {
+                        "type": "string",
+                        "description": "Text to insert"
+                    }
+                },
+                "required": ["type", "position", "content"]
+            },
+            {
+                "type": "object",
+                "properties": {
+                    "type": {
+                        "type": "string",
+                        "enum": ["delete"],
+                        "description": "Delete operation"
+                    },
+                    "start": position_schema("Start position (inclusive)"),
+                    "end": position_schema("End position (exclusive)")
+                },
+                "required": ["type", "start", "end"]
+            },
+            {
+                "type": "object",
+                "properties": {
+                    "type": {
+                        "type": "string",
+                        "enum": ["replace_lines"],
+                        "description": "Replace lines operation"
+                    },
+                    "start_line": {
+                        "type": "integer",
+                        "description": "Start line number (0-based, inclusive)"
+                    },
+                    "end_line": {
+                        "type": "integer",
+                        "description": "End line number (0-based, inclusive)"
+                    },
+                    "content": {
+                        "type": "string",
+                        "description": "Text to insert as replacement"
+                    }
+                },
+                "required": ["type", "start_line", "end_line", "content"]
+            },
+            {
+                "type": "object",
+                "properties": {
+                    "type": {
+                        "type": "string",
+                        "enum": ["regex_replace"],
+                        "description": "Regular expression replace operation"
+                    },
+                    "pattern": {
+                        "type": "string",
+                        "description": "Regular expression to find"
+                    },
+                    "replace": {
+                        "type": "string",
+                        "description": "Replacement text; $1 or ${name} insert capture groups, $$ inserts a literal $"
+                    },
+                    "case_sensitive": {
+                        "type": "boolean",
+                        "description": "Whether the match is case-sensitive",
+                        "default": true
+                    },
+                    "multi_line": {
+                        "type": "boolean",
+                        "description": "Make ^ and $ match at line boundaries",
+                        "default": false
+                    },
+                    "dot_all": {
+                        "type": "boolean",
+                        "description": "Make . match newlines",
+                        "default": false
+                    },
+                    "expected_count": {
+                        "type": "integer",
+                        "description": "Fail without changing anything unless the pattern matches exactly this many times"
+                    },
+                    "limit": {
+                        "type": "integer",
+                        "minimum": 1,
+                        "description": "Replace at most this many matches, starting from the first (default: all); 0 is refused"
+                    }
+                },
+                "required": ["type", "pattern", "replace"]
+            },
+            {
+                "type": "object",
+                "properties": {
+                    "type": {
+                        "type": "string",
+                        "enum": ["insert_before"],
+                        "description": "Insert lines before an anchor line"
+                    },
+                    "anchor": {
+                        "type": "string",
+                        "description": "Text (or regular expression with regex: true) identifying the anchor line"
+                    },
+                    "content": {
+                        "type": "string",
+                        "description": "Lines to insert"
+                    },
+                    "regex": {
+                        "type": "boolean",
+                        "description": "Treat the anchor as a regular expression matched against each line",
+                        "default": false
+                    },
+                    "case_sensitive": {
+                        "type": "boolean",
+                        "description": "Whether the anchor match is case-sensitive",
+                        "default": true
+                    },
+                    "occurrence": {
+                        "type": "integer",
+                        "description": "Which matching line to use (0-based, -1 for all)",
+                        "default": 0
+                    },
+                    "require_unique": {
+                        "type": "boolean",
+                        "description": "Fail if the anchor matches more than one line",
+                        "default": false
+                    },
+                    "match_indentation": {
+                        "type": "boolean",
+                        "description": "Re-indent the inserted lines to the anchor line's indentation",
+                        "default": true
+                    }
+                },
+                "required": ["type", "anchor", "content"]
+            },
+            {
+                "type": "object",
+                "properties": {
+                    "type": {
+                        "type": "string",
+                        "enum": ["insert_after"],
+                        "description": "Insert lines after an anchor line"
+                    },
+                    "anchor": {
+                        "type": "string",
+                        "description": "Text (or regular expression with regex: true) identifying the anchor line"
+                    },
+                    "content": {
+                        "type": "string",
+                        "description": "Lines to insert"
+                    },
+                    "regex": {
+                        "type": "boolean",
+                        "description": "Treat the anchor as a regular expression matched against each line",
+                        "default": false
+                    },
+                    "case_sensitive": {
+                        "type": "boolean",
+                        "description": "Whether the anchor match is case-sensitive",
+                        "default": true
+                    },
+                    "occurrence": {
+                        "type": "integer",
+                        "description": "Which matching line to use (0-based, -1 for all)",
+                        "default": 0
+                    },
+                    "require_unique": {
+                        "type": "boolean",
+                        "description": "Fail if the anchor matches more than one line",
+                        "default": false
+                    },
+                    "match_indentation": {
+                        "type": "boolean",
+                        "description": "Re-indent the inserted lines to the anchor line's indentation",
+                        "default": true
+                    }
+                },
+                "required": ["type", "anchor", "content"]
+            },
+            {
+                "type": "object",
+                "properties": {
+                    "type": {
+                        "type": "string",
+                        "enum": ["prepend"],
+                        "description": "Insert lines at the start of the file"
+                    },
+                    "content": {
+                        "type": "string",
+                        "description": "Lines to insert"
+                    }
+                },
+                "required": ["type", "content"]
+            },
+            {
+                "type": "object",
+                "properties": {
+                    "type": {
+                        "type": "string",
+                        "enum": ["append"],
+                        "description": "Insert lines at the end of the file"
+                    },
+                    "content": {
+                        "type": "string",
+                        "description": "Lines to insert"
+                    }
+                },
+                "required": ["type", "content"]
+            },
+            {
+                "type": "object",
+                "properties": {
+                    "type": {
+                        "type": "string",
+                        "enum": ["normalize_line_endings"],
+                        "description": "Convert every line ending in the file; the only operation that changes the line endings, BOM or trailing newline, which every other operation keeps"
+                    },
+                    "line_ending": {
+                        "type": "string",
+                        "enum": ["lf", "crlf"],
+                        "description": "Line ending to use"
+                    },
+                    "trailing_newline": {
+                        "type": "boolean",
+                        "description": "Add (true) or remove (false) the newline at the end of the file; unchanged if omitted"
+                    },
+                    "bom": {
+                        "type": "boolean",
+                        "description": "Add (true) or remove (false) the UTF-8 byte order mark; unchanged if omitted"
+                    }
+                },
+                "required": ["type", "line_ending"]
+            },
+            {
+                "type": "object",
+                "properties": {
+                    "type": {
+                        "type": "string",
+                        "enum": ["replace_item", "insert_item_after"],
+                        "description": "Replace a function, type, impl block, class or method (with its doc comments, attributes and decorators), or insert a new item after it"
+                    },
+                    "item": {
+                        "type": "string",
+                        "description": "Item name or path: \"parse\", \"impl Foo::bar\" or \"Foo::bar\" (Rust), \"Foo.bar\" (Python, TypeScript, Go methods)"
+                    },
+                    "content": {
+                        "type": "string",
+                        "description": "Source of the new item, re-indented to the item's indentation"
+                    }
+                },
+                "required": ["type", "item", "content"]
+            },
+            {
+                "type": "object",
+                "properties": {
+                    "type": {
+                        "type": "string",
+                        "enum": ["delete_item"],
+                        "description": "Delete a function, type, impl block, class or method with its doc comments, attributes and decorators"
+                    },
+                    "item": {
+                        "type": "string",
+                        "description": "Item name or path, as for replace_item"
+                    }
+                },
+                "required": ["type", "item"]
+            }
+        ]
+    })
+}
+
 // Define the schema for the tool
 pub fn schema() -> Value {
     json!({
@@ -85,102 +637,14 @@ pub fn schema() -> Value {
             },
             "operations": {
                 "type": "array",
-                "description": "List of edit operations to perform (in order)",
-                "items": {
-                    "type": "object",
-                    "oneOf": [
-                        {
-                            "type": "object",
-                            "properties": {
-                                "type": {
-                                    "type": "string",
-                                    "enum": ["replace"],
-                                    "description": "Replace operation"
-                                },
-                                "find": {
-                                    "type": "string",
-                                    "description": "Text to find (exact match)"
-                                },
-                                "replace": {
-                                    "type": "string",
-                                    "description": "Text to insert as replacement"
-                                },
-                                "occurrence": {
-                                    "type": "integer",
-                                    "description": "Which occurrence to replace (0-based, -1 for all)",
-                                    "default": 0
-                                },
-                                "case_sensitive": {
-                                    "type": "boolean",
-                                    "description": "Whether the search is case-sensitive",
-                                    "default": true
-                                }
-                            },
-                            "required": ["type", "find", "replace"]
-                        },
-                        {
-                            "type": "object",
-                            "properties": {
-                                "type": {
-                                    "type": "string",
-                                    "enum": ["insert"],
-                                    "description": "Insert operation"
-                                },
-                                "position": {
-                                    "type": "integer",
-                                    "description": "Character position to insert at (0-based)"
-                                },
-                                "content": {
-                                    "type": "string",
-                                    "description": "Text to insert"
-                                }
-                            },
-                            "required": ["type", "position", "content"]
-                        },
-                        {
-                            "type": "object",
-                            "properties": {
-                                "type": {
-                                    "type": "string",
-                                    "enum": ["delete"],
-                                    "description": "Delete operation"
-                                },
-                                "start": {
-                                    "type": "integer",
-                                    "description": "Start character position (0-based, inclusive)"
-                                },
-                                "end": {
-                                    "type": "integer",
-                                    "description": "End character position (0-based, exclusive)"
-                                }
-                            },
-                            "required": ["type", "start", "end"]
-                        },
-                        {
-                            "type": "object",
-                            "properties": {
-                                "type": {
-                                    "type": "string",
-                                    "enum": ["replace_lines"],
-                                    "description": "Replace lines operation"
-                                },
-                                "start_line": {
-                                    "type": "integer",
-                                    "description": "Start line number (0-based, inclusive)"
-                                },
-                                "end_line": {
-                                    "type": "integer",
-                                    "description": "End line number (0-based, inclusive)"
-                                },
-                                "content": {
-                                    "type": "string",
-                                    "description": "Text to insert as replacement"
-                                }
-                            },
-                            "required": ["type", "start_line", "end_line", "content"]
-                        }
-                    ]
-                }
+                "description": "List of edit operations to perform (in order). Each operation keeps the file's line endings, BOM and trailing newline, even if its text removes or adds them; use normalize_line_endings to change them",
+                "items": operations_schema()
+            },
+            "position_encoding": position_encoding_schema(),
+            "language": language_schema(),
+            "charset": {
+                "type": "string",
+                "description": "Character encoding to save the edited file in, e.g. \"utf-8\"; defaults to the file's detected charset, so a Latin-1 or UTF-16 file stays one"
             },
             "create_if_missing": {
                 "type": "boolean",
@@ -191,6 +655,48 @@ pub fn schema() -> Value {
                 "type": "boolean",
                 "description": "Create a backup of the original file before editing",
                 "default": false
+            },
+            "dry_run": {
+                "type": "boolean",
+                "description": "Validate and report the planned changes without modifying the filesystem",
+                "default": false
+            },
+            "diff": {
+                "type": "boolean",
+                "description": "Include a unified diff of the change in the response",
+                "default": false
+            },
+            "context_lines": {
+                "type": "integer",
+                "description": "Number of unchanged lines shown around each change in the diff",
+                "default": 3
+            },
+            "validate": {
+                "type": "boolean",
+                "description": "Parse the edited file (JSON, YAML, TOML, Rust, Python, TypeScript, Go; chosen by language or extension) and report its first syntax errors with 0-based line and column",
+                "default": false
+            },
+            "reject_new_errors": {
+                "type": "boolean",
+                "description": "Refuse to write the edit if it introduces syntax errors into a file that parsed before; unlike write's reject_invalid, a file that was already invalid can still be edited (implies validate)",
+                "default": false
+            },
+            "preview": {
+                "type": "boolean",
+                "description": "Return the diff of the change without writing the file",
+                "default": false
+            },
+            "if_match_hash": {
+                "type": "string",
+                "description": "Only proceed if the file's current SHA-256 hash (as returned by read or info) matches"
+            },
+            "if_unmodified_since": {
+                "type": "string",
+                "description": "Only proceed if the file has not been modified after this RFC 3339 timestamp (as returned by read or info)"
+            },
+            "session": {
+                "type": "string",
+                "description": "Session identifier used with the lock tool; paths locked by other sessions are refused"
             }
         },
         "required": ["path", "operations"]
@@ -198,7 +704,7 @@ pub fn schema() -> Value {
 }
 
 // Execute the edit tool
-pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallResult> {
+pub fn execute(args: &Value, allowed_paths: &AllowedPaths, locks: &LockManager, journal: &Journal) -> Result<ToolCallResult> {
     // Extract path parameter (required)
     let path_str = args.get("path")
         .and_then(|v| v.as_str())
@@ -227,9 +733,57 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
         .and_then(|v| v.as_bool())
         .unwrap_or(false);
     
+    let dry_run = args.get("dry_run")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    
+    let preview = args.get("preview")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    
+    // A preview is only useful with the diff
+    let show_diff = preview || args.get("diff")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    
+    let context_lines = args.get("context_lines")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(3) as usize;
+    
+    // Rejecting new errors requires validating the result
+    let reject_new_errors = args.get("reject_new_errors")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    
+    let validate = reject_new_errors || args.get("validate")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    
+    let session = args.get("session").and_then(|v| v.as_str());
+    
+    let position_encoding = match PositionEncoding::from_args(args) {
+        Ok(encoding) => encoding,
+        Err(e) => {
+            return Ok(ToolCallResult {
+                content: vec![ToolContent::Text { text: e.to_string() }],
+                is_error: Some(true),
+            });
+        }
+    };
+    
+    let preconditions = match Preconditions::from_args(args) {
+        Ok(p) => p,
+        Err(e) => {
+            return Ok(ToolCallResult {
+                content: vec![ToolContent::Text { text: e.to_string() }],
+                is_error: Some(true),
+            });
+        }
+    };
+    
     debug!(
-        "Editing file: '{}', operations: {}, create_if_missing: {}, backup: {}",
-        path_str, operations.len(), create_if_missing, backup
+        "Editing file: '{}', operations: {}, create_if_missing: {}, backup: {}, dry_run: {}, preview: {}",
+        path_str, operations.len(), create_if_missing, backup, dry_run, preview
     );
     
     // Create Path object
@@ -305,8 +859,30 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
         });
     }
     
+    // Refuse to touch a file that changed since the caller last saw it
+    if let Err(e) = preconditions.check(&validated_path) {
+        return Ok(precondition::error_result(e, &validated_path));
+    }
+    
+    // Refuse to touch a path locked by another session
+    if let Err(e) = locks.check(&validated_path, session) {
+        return Ok(lock::error_result(e));
+    }
+    
+    let target_charset = match args.get("charset").and_then(|v| v.as_str()).map(Charset::parse) {
+        Some(Ok(charset)) => Some(charset),
+        Some(Err(e)) => {
+            return Ok(ToolCallResult {
+                content: vec![ToolContent::Text { text: e.to_string() }],
+                is_error: Some(true),
+            });
+        }
+        None => None,
+    };
+    
     // Read the file content or create an empty string if it doesn't exist and create_if_missing is true
-    let content = if validated_path.exists() {
+    let mut warnings = Vec::new();
+    let (charset, content) = if validated_path.exists() {
         // Check if it's a text file
         if !is_text_file(&validated_path)? {
             return Ok(ToolCallResult {
@@ -317,15 +893,19 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
             });
         }
         
-        fs::read_to_string(&validated_path).context("Failed to read file")?
+        // Edit the text as UTF-8 whatever the file's charset
+        let bytes = fs::read(&validated_path).context("Failed to read file")?;
+        let (charset, warning) = Charset::detect_for_edit(&bytes);
+        warnings.extend(warning);
+        (charset, charset.decode(&bytes))
     } else if create_if_missing {
         // Create parent directories if they don't exist
         if let Some(parent) = validated_path.parent() {
-            if !parent.exists() {
+            if !parent.exists() && !dry_run && !preview {
                 fs::create_dir_all(parent).context("Failed to create parent directories")?;
             }
         }
-        String::new() // Empty string for new files
+        (Charset::UTF8, String::new()) // Empty string for new files
     } else {
         return Ok(ToolCallResult {
             content: vec![ToolContent::Text {
@@ -335,61 +915,150 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
         });
     };
     
-    // Create a backup if requested
-    let backup_path = if backup && validated_path.exists() {
-        let backup_path_buf = PathBuf::from(format!("{}.bak", validated_path.display()));
-        fs::copy(&validated_path, &backup_path_buf).context("Failed to create backup")?;
-        Some(backup_path_buf.to_string_lossy().to_string())
+    let language = match source_language(args, &validated_path) {
+        Ok(language) => language,
+        Err(e) => {
+            return Ok(ToolCallResult {
+                content: vec![ToolContent::Text { text: e.to_string() }],
+                is_error: Some(true),
+            });
+        }
+    };
+    
+    // Apply operations
+    let mut modified_content = content.clone();
+    let (operations_applied, operation_results) = apply_operations(operations, &mut modified_content, position_encoding, language);
+    
+    // Keep failed operations and those that report replaced spans
+    let (succeeded, failed_operations): (Vec<_>, Vec<_>) = operation_results.into_iter()
+        .partition(|r| r.success);
+    let operations_failed = failed_operations.len();
+    let replacements = succeeded.into_iter()
+        .filter(|r| !r.replaced_spans.is_empty())
+        .collect::<Vec<_>>();
+    
+    // Parse the result, and note whether the edit broke a file that parsed before
+    let validation = if validate {
+        validate::validate(&modified_content, &validated_path, language)
     } else {
         None
     };
+    let already_invalid = validation.as_ref().is_some_and(|v| !v.valid)
+        && validate::validate(&content, &validated_path, language).is_some_and(|v| !v.valid);
+    let rejected = reject_new_errors && validation.as_ref().is_some_and(|v| !v.valid) && !already_invalid;
     
-    // Apply operations
-    let mut modified_content = content;
-    let mut operation_results = Vec::new();
-    let mut operations_applied = 0;
-    let mut operations_failed = 0;
+    // Save the file in its own charset unless asked to convert it
+    let target_charset = target_charset.unwrap_or(charset);
+    let output = target_charset.encode(&modified_content);
     
-    for (i, op) in operations.iter().enumerate() {
-        let operation: EditOperation = match serde_json::from_value(op.clone()) {
-            Ok(op) => op,
-            Err(e) => {
-                warn!("Invalid operation format: {}", e);
-                operations_failed += 1;
-                operation_results.push(OperationResult {
-                    operation_index: i,
-                    success: false,
-                    error: Some(format!("Invalid operation format: {}", e)),
-                });
-                continue;
-            }
-        };
-        
-        match apply_operation(&operation, &mut modified_content) {
-            Ok(_) => {
-                operations_applied += 1;
-            },
-            Err(e) => {
-                warn!("Failed to apply operation {}: {}", i, e);
-                operations_failed += 1;
-                operation_results.push(OperationResult {
-                    operation_index: i,
-                    success: false,
-                    error: Some(format!("Operation failed: {}", e)),
-                });
-            }
+    // Report the resulting write without touching the file
+    if dry_run {
+        let mut plan = Plan::new("edit");
+        if let Some(parent) = validated_path.parent() {
+            plan::add_missing_dirs(&mut plan, parent);
         }
+        if backup && validated_path.exists() {
+            let backup_path_buf = PathBuf::from(format!("{}.bak", validated_path.display()));
+            let backup_bytes = fs::metadata(&validated_path).map(|m| m.len()).ok();
+            plan.add(ActionKind::CreateFile, &backup_path_buf, backup_bytes).source =
+                Some(validated_path.display().to_string());
+        }
+        let action = if validated_path.exists() { ActionKind::OverwriteFile } else { ActionKind::CreateFile };
+        let bytes = output.as_ref().map(|bytes| bytes.len()).unwrap_or(modified_content.len());
+        plan.add(action, &validated_path, Some(bytes as u64));
+        if target_charset != charset {
+            plan.add_note(format!("The file would be converted from {} to {}", charset, target_charset));
+        }
+        if let Err(e) = &output {
+            plan.add_error(e.to_string());
+        }
+        for warning in &warnings {
+            plan.add_note(format!("Warning: {}", warning));
+        }
+        for op in &failed_operations {
+            plan.add_error(format!(
+                "Operation {}: {}",
+                op.operation_index,
+                op.error.as_deref().unwrap_or("Unknown error")
+            ));
+        }
+        match &validation {
+            Some(v) if rejected => plan.add_error(format!("Edit would be refused: {}", v.describe())),
+            Some(v) => plan.add_note(v.describe()),
+            None if validate => plan.add_note("No syntax validation is available for this file type"),
+            None => {}
+        }
+        return Ok(plan.into_result(allowed_paths));
     }
     
-    // Only keep failed operations in the results
-    let failed_operations = operation_results.into_iter()
-        .filter(|r| !r.success)
-        .collect::<Vec<_>>();
+    // Diff against the original content, labelled with the path relative to its allowed directory
+    let diff = if show_diff {
+        let relative_path = allowed_paths.closest_relative_path(&validated_path);
+        Some(diff::unified_diff(&content, &modified_content, &relative_path, context_lines))
+    } else {
+        None
+    };
     
-    // Write the modified content back to the file
-    fs::write(&validated_path, &modified_content).context("Failed to write modified content")?;
+    // Show the change without writing it
+    if preview {
+        let mut text = format!("Preview of edit to {} (file not modified)\n", validated_path.display());
+        text.push_str(&format!("Operations applied: {}\n", operations_applied));
+        append_failed_operations(&mut text, &failed_operations);
+        append_replacements(&mut text, &replacements);
+        append_validation(&mut text, validation.as_ref(), already_invalid);
+        for warning in &warnings {
+            text.push_str(&format!("Warning: {}\n", warning));
+        }
+        append_diff(&mut text, diff.as_deref().unwrap_or_default());
+        
+        return Ok(ToolCallResult {
+            content: vec![ToolContent::Text { text }],
+            is_error: Some(operations_failed > 0),
+        });
+    }
     
-    // Get file metadata
+    // Leave the file untouched rather than introduce syntax errors
+    if rejected {
+        let mut text = format!(
+            "Refusing to edit '{}': the edit introduces syntax errors\n",
+            validated_path.display()
+        );
+        append_validation(&mut text, validation.as_ref(), false);
+        if let Some(diff) = &diff {
+            append_diff(&mut text, diff);
+        }
+        return Ok(ToolCallResult {
+            content: vec![ToolContent::Text { text }],
+            is_error: Some(true),
+        });
+    }
+    
+    let output = match output {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            return Ok(ToolCallResult {
+                content: vec![ToolContent::Text { text: e.to_string() }],
+                is_error: Some(true),
+            });
+        }
+    };
+    
+    // Create a backup if requested
+    let backup_path = if backup && validated_path.exists() && !dry_run && !preview {
+        let backup_path_buf = PathBuf::from(format!("{}.bak", validated_path.display()));
+        fs::copy(&validated_path, &backup_path_buf).context("Failed to create backup")?;
+        Some(backup_path_buf.to_string_lossy().to_string())
+    } else {
+        None
+    };
+    
+    // Write the modified content back to the file atomically, keeping the previous contents for undo
+    let before = journal.capture(&validated_path);
+    let write_outcome = atomic::write_atomic(&validated_path, &output)
+        .context("Failed to write modified content")?;
+    journal.record(&validated_path, "edit", before, Some(&output));
+    
+    // Get file metadata
     let metadata = fs::metadata(&validated_path).context("Failed to get file metadata")?;
     let size = metadata.len();
     let modified = metadata.modified().unwrap_or_else(|_| SystemTime::now());
@@ -402,10 +1071,14 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
         operations_applied,
         operations_failed,
         failed_operations,
+        replacements,
         backup_path,
+        diff,
+        validation,
         metadata: FileMetadata {
             path: validated_path.to_string_lossy().to_string(),
             modified: modified_str,
+            hash: precondition::hash_bytes(&output),
             size,
         },
     };
@@ -417,22 +1090,31 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
     let mut text = format!("File edited: {}\n", response.path);
     text.push_str(&format!("Operations applied: {}\n", response.operations_applied));
     
-    if response.operations_failed > 0 {
-        text.push_str(&format!("Operations failed: {}\n", response.operations_failed));
-        text.push_str("Failed operations:\n");
-        for op in &response.failed_operations {
-            text.push_str(&format!("  - Operation {}: {}\n", 
-                op.operation_index, 
-                op.error.as_deref().unwrap_or("Unknown error")));
-        }
-    }
+    append_failed_operations(&mut text, &response.failed_operations);
+    append_replacements(&mut text, &response.replacements);
+    append_validation(&mut text, response.validation.as_ref(), already_invalid);
     
     if let Some(backup) = &response.backup_path {
         text.push_str(&format!("Backup created: {}\n", backup));
     }
     
+    if target_charset != charset {
+        text.push_str(&format!("Converted from {} to {}\n", charset, target_charset));
+    } else if !charset.is_utf8() {
+        text.push_str(&format!("Charset: {}\n", charset));
+    }
+    
+    for warning in warnings.iter().chain(&write_outcome.warnings) {
+        text.push_str(&format!("Warning: {}\n", warning));
+    }
+    
     text.push_str(&format!("File size: {} bytes\n", response.metadata.size));
     text.push_str(&format!("Last modified: {}\n", response.metadata.modified));
+    text.push_str(&format!("Hash: {}\n", response.metadata.hash));
+    
+    if let Some(diff) = &response.diff {
+        append_diff(&mut text, diff);
+    }
     
     Ok(ToolCallResult {
         content: vec![ToolContent::Text { text }],
@@ -440,72 +1122,192 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
     })
 }
 
-// Apply a single operation to the content
-fn apply_operation(operation: &EditOperation, content: &mut String) -> Result<()> {
+fn append_validation(text: &mut String, validation: Option<&Validation>, already_invalid: bool) {
+    if let Some(validation) = validation {
+        text.push_str(&format!("{}\n", validation.describe()));
+        if already_invalid {
+            text.push_str("Note: the file already had syntax errors before this edit\n");
+        }
+    }
+}
+
+fn append_failed_operations(text: &mut String, failed_operations: &[OperationResult]) {
+    if failed_operations.is_empty() {
+        return;
+    }
+    
+    text.push_str(&format!("Operations failed: {}\n", failed_operations.len()));
+    text.push_str("Failed operations:\n");
+    for op in failed_operations {
+        text.push_str(&format!("  - Operation {}: {}\n", 
+            op.operation_index, 
+            op.error.as_deref().unwrap_or("Unknown error")));
+    }
+}
+
+fn append_replacements(text: &mut String, replacements: &[OperationResult]) {
+    if replacements.is_empty() {
+        return;
+    }
+    
+    text.push_str("Replaced spans (0-based lines):\n");
+    for op in replacements {
+        let lines = op.replaced_spans.iter()
+            .map(|span| if span.start_line == span.end_line {
+                span.start_line.to_string()
+            } else {
+                format!("{}-{}", span.start_line, span.end_line)
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
+        text.push_str(&format!("  - Operation {}: {} matches at lines {}",
+            op.operation_index, op.replaced_spans.len(), lines));
+        if let Some(similarity) = op.replaced_spans.iter().find_map(|span| span.similarity) {
+            text.push_str(&format!(" (similarity {:.2})", similarity));
+        }
+        text.push('\n');
+    }
+}
+
+pub(crate) fn append_diff(text: &mut String, diff: &str) {
+    if diff.is_empty() {
+        text.push_str("Diff: no changes\n");
+    } else {
+        text.push_str("Diff:\n");
+        text.push_str(diff);
+    }
+}
+
+// Apply a list of JSON edit operations in order, returning the number applied
+// and the results of the operations that failed or reported replaced spans
+pub(crate) fn apply_operations(
+    operations: &[Value],
+    content: &mut String,
+    encoding: PositionEncoding,
+    language: Option<SourceLanguage>,
+) -> (usize, Vec<OperationResult>) {
+    let mut operation_results = Vec::new();
+    let mut operations_applied = 0;
+    
+    for (i, op) in operations.iter().enumerate() {
+        let operation: EditOperation = match serde_json::from_value(op.clone()) {
+            Ok(op) => op,
+            Err(e) => {
+                warn!("Invalid operation format: {}", e);
+                operation_results.push(OperationResult {
+                    operation_index: i,
+                    success: false,
+                    error: Some(format!("Invalid operation format: {}", e)),
+                    replaced_spans: Vec::new(),
+                });
+                continue;
+            }
+        };
+        
+        // Line endings, BOM and trailing newline survive every operation except an explicit normalization
+        let format = (!content.is_empty()).then(|| TextFormat::detect(content));
+        
+        match apply_operation(&operation, content, encoding, language) {
+            Ok(replaced_spans) => {
+                operations_applied += 1;
+                if let Some(format) = format {
+                    if !matches!(operation, EditOperation::NormalizeLineEndings { .. }) {
+                        format.restore(content);
+                    }
+                }
+                if !replaced_spans.is_empty() {
+                    operation_results.push(OperationResult {
+                        operation_index: i,
+                        success: true,
+                        error: None,
+                        replaced_spans,
+                    });
+                }
+            },
+            Err(e) => {
+                warn!("Failed to apply operation {}: {}", i, e);
+                operation_results.push(OperationResult {
+                    operation_index: i,
+                    success: false,
+                    error: Some(format!("Operation failed: {}", e)),
+                    replaced_spans: Vec::new(),
+                });
+            }
+        }
+    }
+    
+    (operations_applied, operation_results)
+}
+
+// Apply a single operation to the content, returning the spans it replaced
+// (only reported by operations that can match several places)
+fn apply_operation(
+    operation: &EditOperation,
+    content: &mut String,
+    encoding: PositionEncoding,
+    language: Option<SourceLanguage>,
+) -> Result<Vec<ReplacedSpan>> {
     match operation {
-        EditOperation::Replace { find, replace, occurrence, case_sensitive } => {
+        EditOperation::Replace { find, replace, occurrence, case_sensitive, require_unique, match_mode, fuzzy_threshold } => {
             if find.is_empty() {
                 return Err(anyhow!("Find string cannot be empty"));
             }
             
+            match match_mode {
+                MatchMode::Exact => {},
+                MatchMode::Whitespace => {
+                    return replace_whitespace_normalized(content, find, replace, *occurrence, *case_sensitive, *require_unique);
+                },
+                MatchMode::Indent => {
+                    return replace_indented_block(content, find, replace, *occurrence, *case_sensitive, *require_unique);
+                },
+                MatchMode::Fuzzy => {
+                    return replace_fuzzy(content, find, replace, *case_sensitive, *require_unique, *fuzzy_threshold);
+                },
+            }
+            
+            if *require_unique {
+                let offsets = match_offsets(content, find, *case_sensitive);
+                if offsets.len() > 1 {
+                    return Err(anyhow!("{}", ambiguous_match_error(content, find, &offsets)));
+                }
+            }
+            
             let mut replaced = 0;
             
             // For case-insensitive search, we need a custom implementation
             if !case_sensitive {
-                let find_lower = find.to_lowercase();
+                // Match on char boundaries; lowercasing can change byte lengths, so offsets
+                // into a lowercased copy would not line up with the original
+                let regex = RegexBuilder::new(&regex::escape(find))
+                    .case_insensitive(true)
+                    .build()
+                    .map_err(|e| anyhow!("Failed to build case-insensitive pattern: {}", e))?;
                 let mut new_content = String::with_capacity(content.len());
                 let mut last_end = 0;
-                let mut occurrences = Vec::new();
-                
-                // Find all occurrences
-                let chars: Vec<char> = content.chars().collect();
-                let mut i = 0;
-                while i <= chars.len().saturating_sub(find.len()) {
-                    let slice: String = chars[i..i+find.len()].iter().collect();
-                    if slice.to_lowercase() == find_lower {
-                        occurrences.push(i);
-                        i += find.len();
-                    } else {
-                        i += 1;
-                    }
-                }
                 
                 // Replace the specified occurrences
-                let mut done = false;
-                for (idx, pos) in occurrences.iter().enumerate() {
+                for (idx, m) in regex.find_iter(content).enumerate() {
                     if *occurrence == -1 || idx == *occurrence as usize {
-                        let current_slice = &content[last_end..*pos];
-                        new_content.push_str(current_slice);
+                        new_content.push_str(&content[last_end..m.start()]);
                         new_content.push_str(replace);
-                        last_end = pos + find.len();
+                        last_end = m.end();
                         replaced += 1;
                         if *occurrence != -1 {
-                            done = true;
                             break;
                         }
                     }
                 }
                 
                 // Add remaining content
-                if !done || *occurrence == -1 {
-                    new_content.push_str(&content[last_end..]);
-                } else {
-                    // For single replacement, we need to add the rest
-                    new_content.push_str(&content[last_end..]);
-                }
-                
+                new_content.push_str(&content[last_end..]);
                 *content = new_content;
             } else {
                 // Case-sensitive search is simpler
                 if *occurrence == -1 {
                     // Replace all occurrences
-                    let new_content = content.replace(find, replace);
-                    replaced = if find.is_empty() { 
-                        0 
-                    } else { 
-                        (content.len() - new_content.len()) / find.len() + (new_content.len() - content.len()) / replace.len() 
-                    };
-                    *content = new_content;
+                    replaced = content.matches(find.as_str()).count();
+                    *content = content.replace(find, replace);
                 } else {
                     // Replace a specific occurrence
                     let occurrence_usize = *occurrence as usize;
@@ -546,29 +1348,26 @@ fn apply_operation(operation: &EditOperation, content: &mut String) -> Result<()
             }
         },
         EditOperation::Insert { position, content: insert_content } => {
-            let effective_position = if *position > content.len() {
-                // Allow inserting at the end by clamping the position
-                content.len()
-            } else {
-                *position
-            };
+            // Positions past the end are clamped, so inserting at the end is always possible
+            let effective_position = resolve_position(content, position, encoding)?;
             
             // Split the content at the position and insert the new content
             let (before, after) = content.split_at(effective_position);
             *content = format!("{}{}{}", before, insert_content, after);
         },
         EditOperation::Delete { start, end } => {
-            if *start >= content.len() {
-                return Err(anyhow!("Delete start position {} is beyond the end of the file (length: {})", start, content.len()));
+            let start_byte = resolve_position(content, start, encoding)?;
+            if start_byte >= content.len() {
+                return Err(anyhow!("Delete start position {} is beyond the end of the file (length: {})", start, encoding.len(content)));
             }
-            let effective_end = std::cmp::min(*end, content.len());
-            if start >= &effective_end {
-                return Err(anyhow!("Delete start position {} must be less than end position {}", start, effective_end));
+            let end_byte = resolve_position(content, end, encoding)?;
+            if start_byte >= end_byte {
+                return Err(anyhow!("Delete start position {} must be less than end position {}", start, end));
             }
             
             // Split the content and remove the specified range
-            let (before, rest) = content.split_at(*start);
-            let after = &rest[(effective_end - *start)..];
+            let (before, rest) = content.split_at(start_byte);
+            let after = &rest[(end_byte - start_byte)..];
             *content = format!("{}{}", before, after);
         },
         EditOperation::ReplaceLines { start_line, end_line, content: replacement } => {
@@ -576,8 +1375,8 @@ fn apply_operation(operation: &EditOperation, content: &mut String) -> Result<()
                 return Err(anyhow!("Start line {} must be less than or equal to end line {}", start_line, end_line));
             }
             
-            // Split the content into lines
-            let lines: Vec<&str> = content.lines().collect();
+            // Split the content into lines, keeping each line's own ending
+            let lines: Vec<&str> = content.split_inclusive('\n').collect();
             let line_count = lines.len();
             
             if *start_line >= line_count {
@@ -585,43 +1384,567 @@ fn apply_operation(operation: &EditOperation, content: &mut String) -> Result<()
             }
             
             let effective_end_line = std::cmp::min(*end_line, line_count - 1);
+            let eol = TextFormat::detect(content).eol();
             
-            // Rebuild the content with the replacement
-            let mut new_content = String::with_capacity(content.len());
-            
-            // Add lines before the replacement
-            for i in 0..*start_line {
-                new_content.push_str(lines[i]);
-                new_content.push('\n');
+            // The replacement uses the file's line endings and keeps the last replaced line's ending
+            *content = replace_line_windows(&lines, &[(*start_line, effective_end_line)], eol, |_| normalize_block(replacement, eol), false);
+        },
+        EditOperation::RegexReplace { pattern, replace, case_sensitive, multi_line, dot_all, expected_count, limit } => {
+            if *limit == Some(0) {
+                return Err(anyhow!("limit must be at least 1; omit it to replace every match"));
             }
             
-            // Add the replacement (handling line endings)
-            new_content.push_str(replacement);
-            if !replacement.ends_with('\n') && effective_end_line < line_count - 1 {
-                new_content.push('\n');
-            }
+            let regex = RegexBuilder::new(pattern)
+                .case_insensitive(!case_sensitive)
+                .multi_line(*multi_line)
+                .dot_matches_new_line(*dot_all)
+                .build()
+                .map_err(|e| anyhow!("Invalid regex pattern: {}", e))?;
             
-            // Add lines after the replacement
-            for i in (effective_end_line + 1)..line_count {
-                new_content.push_str(lines[i]);
-                if i < line_count - 1 {
-                    new_content.push('\n');
+            let match_count = regex.find_iter(content).count();
+            if let Some(expected) = expected_count {
+                if match_count != *expected {
+                    return Err(anyhow!("Expected {} matches of /{}/ but found {}", expected, pattern, match_count));
                 }
             }
-            
-            // Special case for the last line with no newline
-            if !content.ends_with('\n') && line_count > 0 && effective_end_line == line_count - 1 {
-                // Remove the trailing newline we added
-                if new_content.ends_with('\n') {
-                    new_content.pop();
+            if match_count == 0 {
+                if *expected_count == Some(0) {
+                    return Ok(Vec::new());
                 }
+                return Err(anyhow!("Pattern /{}/ not found in file", pattern));
             }
             
+            let max_replacements = limit.unwrap_or(match_count);
+            
+            // Record the lines of each replaced match before changing the content
+            let mut spans = Vec::new();
+            let mut line = 0;
+            let mut scanned = 0;
+            for m in regex.find_iter(content).take(max_replacements) {
+                line += content[scanned..m.start()].matches('\n').count();
+                let end_line = line + content[m.start()..m.end()].trim_end_matches('\n').matches('\n').count();
+                spans.push(ReplacedSpan { start_line: line, end_line, similarity: None });
+                scanned = m.start();
+            }
+            
+            *content = regex.replacen(content, max_replacements, replace.as_str()).into_owned();
+            return Ok(spans);
+        },
+        EditOperation::InsertBefore(insert) => {
+            *content = insert_at_anchor(content, insert, false)?;
+        },
+        EditOperation::InsertAfter(insert) => {
+            *content = insert_at_anchor(content, insert, true)?;
+        },
+        EditOperation::Prepend { content: insert_content } => {
+            let eol = TextFormat::detect(content).eol();
+            let block = normalize_block(insert_content, eol);
+            
+            // Keep a byte order mark at the very start of the file
+            let bom_len = if content.starts_with('\u{feff}') { '\u{feff}'.len_utf8() } else { 0 };
+            let (bom, rest) = content.split_at(bom_len);
+            let mut new_content = String::with_capacity(content.len() + block.len() + eol.len());
+            new_content.push_str(bom);
+            new_content.push_str(&block);
+            if !rest.is_empty() {
+                new_content.push_str(eol);
+            }
+            new_content.push_str(rest);
             *content = new_content;
         },
+        EditOperation::Append { content: insert_content } => {
+            let eol = TextFormat::detect(content).eol();
+            let block = normalize_block(insert_content, eol);
+            let had_final_newline = content.ends_with('\n');
+            
+            if !content.is_empty() && !had_final_newline {
+                content.push_str(eol);
+            }
+            content.push_str(&block);
+            if had_final_newline {
+                content.push_str(eol);
+            }
+        },
+        EditOperation::NormalizeLineEndings { line_ending, trailing_newline, bom } => {
+            let eol = LineEnding::parse(line_ending)
+                .and_then(|ending| ending.as_str())
+                .ok_or_else(|| anyhow!("Invalid line ending '{}' (expected lf or crlf)", line_ending))?;
+            
+            *content = text::convert_line_endings(content, eol);
+            match trailing_newline {
+                Some(true) if !content.is_empty() && !content.ends_with('\n') => content.push_str(eol),
+                Some(false) => {
+                    while content.ends_with('\n') {
+                        content.truncate(content.len() - eol.len());
+                    }
+                },
+                _ => {},
+            }
+            match bom {
+                Some(true) if !content.starts_with(text::UTF8_BOM) => content.insert_str(0, text::UTF8_BOM),
+                Some(false) => {
+                    if let Some(rest) = content.strip_prefix(text::UTF8_BOM) {
+                        *content = rest.to_string();
+                    }
+                },
+                _ => {},
+            }
+        },
+        EditOperation::ReplaceItem { item, content: replacement } => {
+            let found = locate_item(content, language, item)?;
+            let (start, indent) = item_line_start(content, &found);
+            let block = reindent_block(replacement, &indent, TextFormat::detect(content).eol());
+            content.replace_range(start..found.end_byte, &block);
+            return Ok(vec![ReplacedSpan { start_line: found.start_line, end_line: found.end_line, similarity: None }]);
+        },
+        EditOperation::InsertItemAfter { item, content: insert } => {
+            let found = locate_item(content, language, item)?;
+            let (_, indent) = item_line_start(content, &found);
+            let eol = TextFormat::detect(content).eol();
+            
+            // Insert after the rest of the item's last line, separated by a blank line
+            let end = content[found.end_byte..].find('\n')
+                .map_or(content.len(), |i| found.end_byte + i);
+            let end = if content[..end].ends_with('\r') { end - 1 } else { end };
+            let block = format!("{}{}{}", eol, eol, reindent_block(insert, &indent, eol));
+            content.insert_str(end, &block);
+        },
+        EditOperation::DeleteItem { item } => {
+            let found = locate_item(content, language, item)?;
+            let (start, indent) = item_line_start(content, &found);
+            
+            // Take the line break too unless code follows the item on its last line
+            let rest = &content[found.end_byte..];
+            let line_rest = rest.find('\n').map_or(rest, |i| &rest[..=i]);
+            let end = if line_rest.trim().is_empty() { found.end_byte + line_rest.len() } else { found.end_byte };
+            content.replace_range(start..end, "");
+            
+            // Don't leave two blank lines where the item was, or a blank line at the end of its block
+            let before_blank = start == 0 || content[..start].ends_with("\n\n") || content[..start].ends_with("\n\r\n");
+            if before_blank {
+                let after = &content[start..];
+                let blank = if after.starts_with("\r\n") { 2 } else if after.starts_with('\n') { 1 } else { 0 };
+                if blank > 0 {
+                    content.replace_range(start..start + blank, "");
+                } else if start > 0 && (after.is_empty() || leading_whitespace(after).len() < indent.len()) {
+                    let eol_len = if content[..start - 1].ends_with('\r') { 2 } else { 1 };
+                    content.replace_range(start - eol_len..start, "");
+                }
+            }
+            return Ok(vec![ReplacedSpan { start_line: found.start_line, end_line: found.end_line, similarity: None }]);
+        },
+    }
+    
+    Ok(Vec::new())
+}
+
+// Byte offsets of every non-overlapping match of a literal string
+fn match_offsets(content: &str, find: &str, case_sensitive: bool) -> Vec<usize> {
+    if case_sensitive {
+        return content.match_indices(find).map(|(i, _)| i).collect();
+    }
+    match RegexBuilder::new(&regex::escape(find)).case_insensitive(true).build() {
+        Ok(regex) => regex.find_iter(content).map(|m| m.start()).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+// Describe every candidate of an ambiguous match with its line and the lines around it
+fn ambiguous_match_error(content: &str, find: &str, offsets: &[usize]) -> String {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut message = format!(
+        "Text '{}' matches {} times but require_unique needs exactly one match; add surrounding text to `find` to pick one. Candidates (0-based lines):",
+        find,
+        offsets.len()
+    );
+    
+    for offset in offsets {
+        let line = content[..*offset].matches('\n').count();
+        message.push_str(&format!("\n    line {}:", line));
+        let first = line.saturating_sub(1);
+        let last = std::cmp::min(line + 1, lines.len().saturating_sub(1));
+        for (i, text) in lines.iter().enumerate().take(last + 1).skip(first) {
+            let marker = if i == line { '>' } else { ' ' };
+            message.push_str(&format!("\n      {} {:>4} | {}", marker, i, text));
+        }
     }
     
-    Ok(())
+    message
+}
+
+// Byte offset of a position, clamped to the end of the file (or of the line, for a column)
+fn resolve_position(content: &str, position: &Position, encoding: PositionEncoding) -> Result<usize> {
+    match position {
+        Position::Offset(offset) => units_to_byte(content, *offset, encoding),
+        Position::LineColumn { line, column } => {
+            let line_start = match line {
+                0 => 0,
+                _ => content.match_indices('\n')
+                    .nth(line - 1)
+                    .map(|(i, _)| i + 1)
+                    .ok_or_else(|| anyhow!("Line {} is beyond the end of the file (line count: {})", line, content.lines().count()))?,
+            };
+            let line_text = content[line_start..].split('\n').next().unwrap_or_default();
+            let line_text = line_text.strip_suffix('\r').unwrap_or(line_text);
+            Ok(line_start + units_to_byte(line_text, *column, encoding)?)
+        }
+    }
+}
+
+// Byte offset of the character boundary `units` into the text, clamped to its end
+fn units_to_byte(text: &str, units: usize, encoding: PositionEncoding) -> Result<usize> {
+    let mut counted = 0;
+    for (i, c) in text.char_indices() {
+        if counted == units {
+            return Ok(i);
+        }
+        counted += encoding.width(c);
+        if counted > units {
+            return Err(anyhow!("Position {} falls inside the character '{}'", units, c));
+        }
+    }
+    Ok(text.len())
+}
+
+// Pick the matches selected by an occurrence index (-1 for all)
+fn select_occurrences<T: Copy>(matches: &[T], occurrence: i32, find: &str) -> Result<Vec<T>> {
+    if matches.is_empty() {
+        return Err(anyhow!("Text '{}' not found in file", find));
+    }
+    if occurrence == -1 {
+        return Ok(matches.to_vec());
+    }
+    usize::try_from(occurrence)
+        .ok()
+        .and_then(|index| matches.get(index))
+        .map(|m| vec![*m])
+        .ok_or_else(|| anyhow!("Occurrence {} of '{}' not found", occurrence, find))
+}
+
+// Replace text matching find with any run of whitespace standing for any other run
+fn replace_whitespace_normalized(content: &mut String, find: &str, replace: &str, occurrence: i32, case_sensitive: bool, require_unique: bool) -> Result<Vec<ReplacedSpan>> {
+    let tokens: Vec<String> = find.split_whitespace().map(regex::escape).collect();
+    if tokens.is_empty() {
+        return Err(anyhow!("Find string cannot be only whitespace"));
+    }
+    let regex = RegexBuilder::new(&tokens.join(r"\s+"))
+        .case_insensitive(!case_sensitive)
+        .build()
+        .map_err(|e| anyhow!("Failed to build whitespace-tolerant pattern: {}", e))?;
+    
+    let matches: Vec<(usize, usize)> = regex.find_iter(content).map(|m| (m.start(), m.end())).collect();
+    if require_unique && matches.len() > 1 {
+        let offsets: Vec<usize> = matches.iter().map(|(start, _)| *start).collect();
+        return Err(anyhow!("{}", ambiguous_match_error(content, find, &offsets)));
+    }
+    let selected = select_occurrences(&matches, occurrence, find)?;
+    
+    let mut spans = Vec::new();
+    let mut new_content = String::with_capacity(content.len());
+    let mut last_end = 0;
+    for (start, end) in selected {
+        let start_line = content[..start].matches('\n').count();
+        let end_line = start_line + content[start..end].trim_end_matches('\n').matches('\n').count();
+        spans.push(ReplacedSpan { start_line, end_line, similarity: None });
+        new_content.push_str(&content[last_end..start]);
+        new_content.push_str(replace);
+        last_end = end;
+    }
+    new_content.push_str(&content[last_end..]);
+    
+    *content = new_content;
+    Ok(spans)
+}
+
+// Replace whole lines matching find when indentation and trailing whitespace are ignored,
+// re-indenting the replacement to the indentation of the matched block
+fn replace_indented_block(content: &mut String, find: &str, replace: &str, occurrence: i32, case_sensitive: bool, require_unique: bool) -> Result<Vec<ReplacedSpan>> {
+    let normalize = |line: &str| {
+        let line = line.trim();
+        if case_sensitive { line.to_string() } else { line.to_lowercase() }
+    };
+    let find_lines: Vec<String> = block_lines(find).into_iter().map(normalize).collect();
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let content_lines: Vec<String> = lines.iter().map(|line| normalize(line)).collect();
+    
+    // Non-overlapping windows of lines equal to the find lines
+    let mut windows = Vec::new();
+    let mut i = 0;
+    while i + find_lines.len() <= content_lines.len() {
+        if content_lines[i..i + find_lines.len()] == find_lines[..] {
+            windows.push((i, i + find_lines.len() - 1));
+            i += find_lines.len();
+        } else {
+            i += 1;
+        }
+    }
+    
+    if require_unique && windows.len() > 1 {
+        let offsets: Vec<usize> = windows.iter()
+            .map(|(start, _)| lines[..*start].iter().map(|line| line.len()).sum())
+            .collect();
+        return Err(anyhow!("{}", ambiguous_match_error(content, find, &offsets)));
+    }
+    let selected = select_occurrences(&windows, occurrence, find)?;
+    
+    let eol = TextFormat::detect(content).eol();
+    *content = replace_line_windows(&lines, &selected, eol, |window| reindent_block(replace, block_indent(window), eol), replace.is_empty());
+    
+    Ok(selected.iter()
+        .map(|(start_line, end_line)| ReplacedSpan { start_line: *start_line, end_line: *end_line, similarity: None })
+        .collect())
+}
+
+// Replace the window of whole lines most similar to find, if it is at least as similar as the threshold,
+// re-indenting the replacement to the indentation of the matched block
+fn replace_fuzzy(content: &mut String, find: &str, replace: &str, case_sensitive: bool, require_unique: bool, threshold: f64) -> Result<Vec<ReplacedSpan>> {
+    if !(0.0..=1.0).contains(&threshold) {
+        return Err(anyhow!("Fuzzy threshold {} must be between 0.0 and 1.0", threshold));
+    }
+    if find.chars().count() > MAX_FUZZY_FIND_LEN {
+        return Err(anyhow!("Fuzzy matching is limited to find strings of {} characters", MAX_FUZZY_FIND_LEN));
+    }
+    
+    // Lines are compared without their indentation, as in indent mode
+    let fold = |text: String| if case_sensitive { text } else { text.to_lowercase() };
+    let find_text = fold(block_lines(find).iter().map(|line| line.trim()).collect::<Vec<_>>().join("\n"));
+    let find_len = block_lines(find).len();
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    
+    // Score every window of the same number of lines as find. The edit distance is at least
+    // the difference in length, so windows whose length alone rules out the threshold are skipped,
+    // and the work left is bounded before any window is compared
+    let window_text = |start: usize| fold(lines[start..start + find_len].iter()
+        .map(|line| line.trim())
+        .collect::<Vec<_>>()
+        .join("\n"));
+    let find_chars = find_text.chars().count();
+    let mut work = 0;
+    let mut candidates = Vec::new();
+    for start in 0..(lines.len() + 1).saturating_sub(find_len) {
+        let window_chars = window_text(start).chars().count();
+        let longest = find_chars.max(window_chars);
+        if longest > 0 && find_chars.abs_diff(window_chars) as f64 / longest as f64 > 1.0 - threshold {
+            continue;
+        }
+        work += find_chars * window_chars;
+        if work > MAX_FUZZY_WORK {
+            return Err(anyhow!(
+                "Fuzzy matching '{}' would compare too much text; use a shorter find, a higher fuzzy_threshold or another match_mode",
+                find
+            ));
+        }
+        candidates.push(start);
+    }
+    let scores: Vec<(usize, f64)> = candidates.into_iter()
+        .map(|start| (start, strsim::normalized_levenshtein(&find_text, &window_text(start))))
+        .collect();
+    
+    let best = scores.iter()
+        .copied()
+        .filter(|(_, score)| *score >= threshold)
+        .max_by(|a, b| a.1.total_cmp(&b.1));
+    let (start, score) = match best {
+        Some(best) => best,
+        None => {
+            let closest = scores.iter().map(|(_, score)| *score).fold(0.0, f64::max);
+            return Err(anyhow!("No lines similar to '{}' found (best similarity {:.2}, threshold {:.2})", find, closest, threshold));
+        }
+    };
+    
+    if require_unique {
+        let others: Vec<usize> = scores.iter()
+            .filter(|(other, other_score)| *other_score >= threshold && other.abs_diff(start) >= find_len)
+            .map(|(other, _)| lines[..*other].iter().map(|line| line.len()).sum())
+            .collect();
+        if !others.is_empty() {
+            let mut offsets = vec![lines[..start].iter().map(|line| line.len()).sum()];
+            offsets.extend(others);
+            offsets.sort_unstable();
+            return Err(anyhow!("{}", ambiguous_match_error(content, find, &offsets)));
+        }
+    }
+    
+    let eol = TextFormat::detect(content).eol();
+    let end = start + find_len - 1;
+    *content = replace_line_windows(&lines, &[(start, end)], eol, |window| reindent_block(replace, block_indent(window), eol), replace.is_empty());
+    
+    Ok(vec![ReplacedSpan { start_line: start, end_line: end, similarity: Some(score) }])
+}
+
+// The smallest indentation of the non-blank lines of a block
+fn block_indent<'a>(lines: &[&'a str]) -> &'a str {
+    lines.iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| leading_whitespace(line))
+        .min_by_key(|indent| indent.len())
+        .unwrap_or("")
+}
+
+// Rebuild content with each window of lines (0-based, inclusive) replaced by a block of lines;
+// the line ending of a window's last line is kept, unless the block is removed entirely
+fn replace_line_windows<F>(lines: &[&str], windows: &[(usize, usize)], eol: &str, block_for: F, remove: bool) -> String
+where
+    F: Fn(&[&str]) -> String,
+{
+    let mut new_content = String::new();
+    let mut next = 0;
+    for (start, end) in windows {
+        lines[next..*start].iter().for_each(|line| new_content.push_str(line));
+        if !remove {
+            new_content.push_str(&block_for(&lines[*start..=*end]));
+            if lines[*end].ends_with('\n') {
+                new_content.push_str(eol);
+            }
+        }
+        next = end + 1;
+    }
+    lines[next..].iter().for_each(|line| new_content.push_str(line));
+    new_content
+}
+
+// Insert lines before or after the line(s) matching an anchor
+fn insert_at_anchor(content: &str, insert: &AnchoredInsert, after: bool) -> Result<String> {
+    if insert.anchor.is_empty() {
+        return Err(anyhow!("Anchor cannot be empty"));
+    }
+    
+    let regex = if insert.regex {
+        Some(RegexBuilder::new(&insert.anchor)
+            .case_insensitive(!insert.case_sensitive)
+            .build()
+            .map_err(|e| anyhow!("Invalid regex pattern: {}", e))?)
+    } else {
+        None
+    };
+    let anchor_lower = insert.anchor.to_lowercase();
+    
+    // Lines including their terminators, so the rest of the file is kept byte for byte
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let matches: Vec<usize> = lines.iter()
+        .enumerate()
+        .filter(|(_, line)| {
+            let text = line.trim_end_matches(['\r', '\n']);
+            match &regex {
+                Some(regex) => regex.is_match(text),
+                None if insert.case_sensitive => text.contains(&insert.anchor),
+                None => text.to_lowercase().contains(&anchor_lower),
+            }
+        })
+        .map(|(i, _)| i)
+        .collect();
+    
+    if matches.is_empty() {
+        return Err(anyhow!("Anchor '{}' not found in file", insert.anchor));
+    }
+    if insert.require_unique && matches.len() > 1 {
+        let candidates = matches.iter().map(|i| i.to_string()).collect::<Vec<_>>().join(", ");
+        return Err(anyhow!("Anchor '{}' is not unique: matches {} lines (0-based lines {})",
+            insert.anchor, matches.len(), candidates));
+    }
+    
+    let selected: Vec<usize> = if insert.occurrence == -1 {
+        matches
+    } else {
+        let index = usize::try_from(insert.occurrence)
+            .map_err(|_| anyhow!("Invalid occurrence {}", insert.occurrence))?;
+        match matches.get(index) {
+            Some(line) => vec![*line],
+            None => return Err(anyhow!("Occurrence {} of anchor '{}' not found (found {} matching lines)",
+                insert.occurrence, insert.anchor, matches.len())),
+        }
+    };
+    
+    let eol = TextFormat::detect(content).eol();
+    let mut new_content = String::with_capacity(content.len() + insert.content.len() * selected.len());
+    
+    for (i, line) in lines.iter().enumerate() {
+        if !selected.contains(&i) {
+            new_content.push_str(line);
+            continue;
+        }
+        
+        let block = if insert.match_indentation {
+            reindent_block(&insert.content, leading_whitespace(line), eol)
+        } else {
+            normalize_block(&insert.content, eol)
+        };
+        
+        if after {
+            new_content.push_str(line);
+            if line.ends_with('\n') {
+                new_content.push_str(&block);
+                new_content.push_str(eol);
+            } else {
+                // The anchor is the last line and has no newline; keep it that way
+                new_content.push_str(eol);
+                new_content.push_str(&block);
+            }
+        } else {
+            new_content.push_str(&block);
+            new_content.push_str(eol);
+            new_content.push_str(line);
+        }
+    }
+    
+    Ok(new_content)
+}
+
+fn leading_whitespace(line: &str) -> &str {
+    let trimmed = line.trim_start_matches([' ', '\t']);
+    &line[..line.len() - trimmed.len()]
+}
+
+// Split inserted text into lines, dropping one trailing newline
+fn block_lines(text: &str) -> Vec<&str> {
+    let text = text.strip_suffix('\n').unwrap_or(text);
+    let text = text.strip_suffix('\r').unwrap_or(text);
+    text.split('\n').map(|line| line.strip_suffix('\r').unwrap_or(line)).collect()
+}
+
+// Join inserted text with the file's line ending, without a trailing line ending
+fn normalize_block(text: &str, eol: &str) -> String {
+    block_lines(text).join(eol)
+}
+
+// Shift inserted text so its least indented line starts at the given indentation,
+// keeping the relative indentation of the other lines
+fn reindent_block(text: &str, indent: &str, eol: &str) -> String {
+    let lines = block_lines(text);
+    let common = lines.iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| leading_whitespace(line).len())
+        .min()
+        .unwrap_or(0);
+    
+    lines.iter()
+        .map(|line| if line.trim().is_empty() {
+            String::new()
+        } else {
+            format!("{}{}", indent, &line[common..])
+        })
+        .collect::<Vec<_>>()
+        .join(eol)
+}
+
+// Find the item addressed by a replace_item, insert_item_after or delete_item operation
+fn locate_item(content: &str, language: Option<SourceLanguage>, item: &str) -> Result<SyntaxItem> {
+    let language = language.ok_or_else(|| anyhow!(
+        "Cannot tell the source language from the file extension; pass language (rust, python, typescript, tsx, javascript or go)"
+    ))?;
+    syntax::find_item(content, language, item).map_err(|e| anyhow!("{}", e))
+}
+
+// Where an item's lines start and their indentation: the start of its first line if only
+// whitespace precedes it, otherwise the item itself with no indentation
+fn item_line_start(content: &str, item: &SyntaxItem) -> (usize, String) {
+    let line_start = content[..item.start_byte].rfind('\n').map_or(0, |i| i + 1);
+    let prefix = &content[line_start..item.start_byte];
+    if prefix.trim().is_empty() {
+        (line_start, prefix.to_string())
+    } else {
+        (item.start_byte, String::new())
+    }
 }
 
 #[cfg(test)]
@@ -638,9 +1961,12 @@ mod tests {
             replace: String::from("Hi"),
             occurrence: 0,
             case_sensitive: true,
+            require_unique: false,
+            match_mode: MatchMode::Exact,
+            fuzzy_threshold: 0.8,
         };
         
-        apply_operation(&operation, &mut content).unwrap();
+        apply_operation(&operation, &mut content, PositionEncoding::Char, None).unwrap();
         assert_eq!(content, "Hi, world! Hello, again!");
         
         // Replace all occurrences
@@ -649,21 +1975,198 @@ mod tests {
             replace: String::from("planet"),
             occurrence: -1,
             case_sensitive: true,
+            require_unique: false,
+            match_mode: MatchMode::Exact,
+            fuzzy_threshold: 0.8,
         };
         
-        apply_operation(&operation, &mut content).unwrap();
+        apply_operation(&operation, &mut content, PositionEncoding::Char, None).unwrap();
         assert_eq!(content, "Hi, planet! Hello, again!");
     }
     
+    #[test]
+    fn test_replace_require_unique() {
+        let mut content = String::from("let a = 1;\nlet b = 1;\nlet c = 2;\n");
+        let operation = |find: &str| EditOperation::Replace {
+            find: String::from(find),
+            replace: String::from("= 3"),
+            occurrence: 0,
+            case_sensitive: true,
+            require_unique: true,
+            match_mode: MatchMode::Exact,
+            fuzzy_threshold: 0.8,
+        };
+        
+        // Two candidates: the error names both lines and nothing is changed
+        let error = apply_operation(&operation("= 1"), &mut content, PositionEncoding::Char, None).unwrap_err().to_string();
+        assert!(error.contains("matches 2 times"), "{}", error);
+        assert!(error.contains("line 0:") && error.contains("line 1:"), "{}", error);
+        assert!(error.contains(">    1 | let b = 1;"), "{}", error);
+        assert_eq!(content, "let a = 1;\nlet b = 1;\nlet c = 2;\n");
+        
+        apply_operation(&operation("b = 1"), &mut content, PositionEncoding::Char, None).unwrap();
+        assert_eq!(content, "let a = 1;\nlet = 3;\nlet c = 2;\n");
+    }
+    
+    #[test]
+    fn test_replace_match_modes() {
+        let operation = |find: &str, replace: &str, match_mode: MatchMode| EditOperation::Replace {
+            find: String::from(find),
+            replace: String::from(replace),
+            occurrence: 0,
+            case_sensitive: true,
+            require_unique: true,
+            match_mode,
+            fuzzy_threshold: 0.8,
+        };
+        
+        // Whitespace: differently spaced text still matches
+        let mut content = String::from("if (a  &&\n    b) {\n");
+        apply_operation(&operation("if (a && b)", "if (c)", MatchMode::Whitespace), &mut content, PositionEncoding::Char, None).unwrap();
+        assert_eq!(content, "if (c) {\n");
+        
+        // Indent: the block matches at any indentation and the replacement follows it
+        let mut content = String::from("fn f() {\n        if x {\n            y();\n        }\n}\n");
+        let spans = apply_operation(&operation("if x {\n    y();\n}", "if x {\n    z();\n}\n", MatchMode::Indent), &mut content, PositionEncoding::Char, None).unwrap();
+        assert_eq!(content, "fn f() {\n        if x {\n            z();\n        }\n}\n");
+        assert_eq!(spans, vec![ReplacedSpan { start_line: 1, end_line: 3, similarity: None }]);
+        
+        // Fuzzy: a near miss is replaced and its similarity reported
+        let mut content = String::from("let total = price * qty;\nprintln!(\"{}\", total);\n");
+        let spans = apply_operation(&operation("let total = price * quantity;", "let total = price * qty * 2;", MatchMode::Fuzzy), &mut content, PositionEncoding::Char, None).unwrap();
+        assert_eq!(content, "let total = price * qty * 2;\nprintln!(\"{}\", total);\n");
+        assert_eq!(spans.len(), 1);
+        assert!(spans[0].similarity.unwrap() > 0.8 && spans[0].similarity.unwrap() < 1.0);
+        
+        // Fuzzy: nothing above the threshold is an error
+        assert!(apply_operation(&operation("something else entirely", "x", MatchMode::Fuzzy), &mut content, PositionEncoding::Char, None).is_err());
+        
+        // Fuzzy: windows of the wrong length are skipped, and too much work is refused
+        let long_find = "x".repeat(4000);
+        let mut content = "short line\n".repeat(100_000);
+        let error = apply_operation(&operation(&long_find, "x", MatchMode::Fuzzy), &mut content, PositionEncoding::Char, None).unwrap_err();
+        assert!(error.to_string().contains("No lines similar"), "{}", error);
+        let mut content = format!("{}\n", "y".repeat(4000)).repeat(100);
+        let error = apply_operation(&operation(&long_find, "x", MatchMode::Fuzzy), &mut content, PositionEncoding::Char, None).unwrap_err();
+        assert!(error.to_string().contains("too much text"), "{}", error);
+    }
+    
+    #[test]
+    fn test_regex_replace_operation() {
+        let mut content = String::from("fn a() {}\nfn b() {}\nlet x = 1;\nfn c() {}\n");
+        let operation = EditOperation::RegexReplace {
+            pattern: String::from(r"^fn (?P<name>\w+)\(\)"),
+            replace: String::from("pub fn ${name}_v2()"),
+            case_sensitive: true,
+            multi_line: true,
+            dot_all: false,
+            expected_count: Some(3),
+            limit: Some(2),
+        };
+        
+        let spans = apply_operation(&operation, &mut content, PositionEncoding::Char, None).unwrap();
+        assert_eq!(content, "pub fn a_v2() {}\npub fn b_v2() {}\nlet x = 1;\nfn c() {}\n");
+        assert_eq!(spans, vec![
+            ReplacedSpan { start_line: 0, end_line: 0, similarity: None },
+            ReplacedSpan { start_line: 1, end_line: 1, similarity: None },
+        ]);
+        
+        // A mismatched expected_count leaves the content untouched
+        let operation = EditOperation::RegexReplace {
+            pattern: String::from(r"let (\w+)"),
+            replace: String::from("const $1"),
+            case_sensitive: true,
+            multi_line: false,
+            dot_all: false,
+            expected_count: Some(2),
+            limit: None,
+        };
+        assert!(apply_operation(&operation, &mut content, PositionEncoding::Char, None).is_err());
+        assert!(content.contains("let x = 1;"));
+        
+        // A zero limit is refused rather than read as "replace all"
+        let operation = EditOperation::RegexReplace {
+            pattern: String::from(r"let (\w+)"),
+            replace: String::from("const $1"),
+            case_sensitive: true,
+            multi_line: false,
+            dot_all: false,
+            expected_count: None,
+            limit: Some(0),
+        };
+        let error = apply_operation(&operation, &mut content, PositionEncoding::Char, None).unwrap_err();
+        assert!(error.to_string().contains("limit must be at least 1"), "{}", error);
+        assert!(content.contains("let x = 1;"));
+        
+        // Dot-all matches span lines and report them
+        let operation = EditOperation::RegexReplace {
+            pattern: String::from(r"b_v2.*let"),
+            replace: String::from("let"),
+            case_sensitive: true,
+            multi_line: false,
+            dot_all: true,
+            expected_count: None,
+            limit: None,
+        };
+        let spans = apply_operation(&operation, &mut content, PositionEncoding::Char, None).unwrap();
+        assert_eq!(spans, vec![ReplacedSpan { start_line: 1, end_line: 2, similarity: None }]);
+        assert_eq!(content, "pub fn a_v2() {}\npub fn let x = 1;\nfn c() {}\n");
+    }
+    
+    #[test]
+    fn test_anchored_insert_operations() {
+        let mut content = String::from("fn main() {\r\n    let a = 1;\r\n    let b = 2;\r\n}");
+        let insert = |anchor: &str, content: &str| AnchoredInsert {
+            anchor: String::from(anchor),
+            content: String::from(content),
+            regex: false,
+            case_sensitive: true,
+            occurrence: 0,
+            require_unique: true,
+            match_indentation: true,
+        };
+        
+        // Inserted lines take the anchor's indentation and the file's CRLF line endings
+        let operation = EditOperation::InsertAfter(insert("let a", "if a {\n    run();\n}\n"));
+        apply_operation(&operation, &mut content, PositionEncoding::Char, None).unwrap();
+        assert_eq!(content, "fn main() {\r\n    let a = 1;\r\n    if a {\r\n        run();\r\n    }\r\n    let b = 2;\r\n}");
+        
+        let operation = EditOperation::InsertBefore(insert("let b", "// b"));
+        apply_operation(&operation, &mut content, PositionEncoding::Char, None).unwrap();
+        assert!(content.contains("    }\r\n    // b\r\n    let b = 2;"));
+        
+        // Ambiguous anchors are refused when uniqueness is required
+        let operation = EditOperation::InsertBefore(insert("let", "x"));
+        let error = apply_operation(&operation, &mut content, PositionEncoding::Char, None).unwrap_err().to_string();
+        assert!(error.contains("not unique"), "{}", error);
+        
+        // Inserting after a last line without a newline keeps it that way
+        let operation = EditOperation::InsertAfter(AnchoredInsert { regex: true, ..insert("^}$", "// end") });
+        apply_operation(&operation, &mut content, PositionEncoding::Char, None).unwrap();
+        assert!(content.ends_with("}\r\n// end"));
+    }
+    
+    #[test]
+    fn test_prepend_append_operations() {
+        let mut content = String::from("\u{feff}a\nb\n");
+        apply_operation(&EditOperation::Prepend { content: String::from("top\n") }, &mut content, PositionEncoding::Char, None).unwrap();
+        apply_operation(&EditOperation::Append { content: String::from("bottom") }, &mut content, PositionEncoding::Char, None).unwrap();
+        assert_eq!(content, "\u{feff}top\na\nb\nbottom\n");
+        
+        let mut content = String::from("a");
+        apply_operation(&EditOperation::Append { content: String::from("b") }, &mut content, PositionEncoding::Char, None).unwrap();
+        assert_eq!(content, "a\nb");
+    }
+    
     #[test]
     fn test_insert_operation() {
         let mut content = String::from("Hello world!");
         let operation = EditOperation::Insert {
-            position: 5,
+            position: Position::Offset(5),
             content: String::from(", beautiful"),
         };
         
-        apply_operation(&operation, &mut content).unwrap();
+        apply_operation(&operation, &mut content, PositionEncoding::Char, None).unwrap();
         assert_eq!(content, "Hello, beautiful world!");
     }
     
@@ -671,14 +2174,60 @@ mod tests {
     fn test_delete_operation() {
         let mut content = String::from("Hello, beautiful world!");
         let operation = EditOperation::Delete {
-            start: 5,
-            end: 16,
+            start: Position::Offset(5),
+            end: Position::Offset(16),
         };
         
-        apply_operation(&operation, &mut content).unwrap();
+        apply_operation(&operation, &mut content, PositionEncoding::Char, None).unwrap();
         assert_eq!(content, "Hello world!");
     }
     
+    #[test]
+    fn test_positions_on_non_ascii_content() {
+        // "é" is one char but two bytes; "😀" is one char, two UTF-16 units and four bytes
+        let mut content = String::from("café 😀 ok\nnaïve\n");
+        let insert = |position: Position| EditOperation::Insert { position, content: String::from("!") };
+        
+        apply_operation(&insert(Position::Offset(4)), &mut content, PositionEncoding::Char, None).unwrap();
+        assert_eq!(content, "café! 😀 ok\nnaïve\n");
+        
+        // In UTF-16 units the emoji counts twice, and offsets inside it are refused
+        apply_operation(&insert(Position::Offset(8)), &mut content, PositionEncoding::Utf16, None).unwrap();
+        assert_eq!(content, "café! 😀! ok\nnaïve\n");
+        assert!(apply_operation(&insert(Position::Offset(7)), &mut content, PositionEncoding::Utf16, None).is_err());
+        
+        // Line and column addressing; columns past the end of a line clamp to it
+        apply_operation(&insert(Position::LineColumn { line: 1, column: 3 }), &mut content, PositionEncoding::Char, None).unwrap();
+        apply_operation(&insert(Position::LineColumn { line: 0, column: 100 }), &mut content, PositionEncoding::Char, None).unwrap();
+        assert_eq!(content, "café! 😀! ok!\nnaï!ve\n");
+        assert!(apply_operation(&insert(Position::LineColumn { line: 5, column: 0 }), &mut content, PositionEncoding::Char, None).is_err());
+        
+        let operation = EditOperation::Delete {
+            start: Position::LineColumn { line: 0, column: 6 },
+            end: Position::LineColumn { line: 0, column: 9 },
+        };
+        apply_operation(&operation, &mut content, PositionEncoding::Char, None).unwrap();
+        assert_eq!(content, "café! ok!\nnaï!ve\n");
+        
+        // Positions deserialize from either form
+        let operation: EditOperation = serde_json::from_value(json!({"type": "delete", "start": 3, "end": {"line": 0, "column": 5}})).unwrap();
+        apply_operation(&operation, &mut content, PositionEncoding::Char, None).unwrap();
+        assert_eq!(content, "caf ok!\nnaï!ve\n");
+        
+        // Case-insensitive replace lands on the right bytes after multi-byte characters
+        let operation = EditOperation::Replace {
+            find: String::from("NAÏ!VE"),
+            replace: String::from("naive"),
+            occurrence: 0,
+            case_sensitive: false,
+            require_unique: false,
+            match_mode: MatchMode::Exact,
+            fuzzy_threshold: 0.8,
+        };
+        apply_operation(&operation, &mut content, PositionEncoding::Char, None).unwrap();
+        assert_eq!(content, "caf ok!\nnaive\n");
+    }
+    
     #[test]
     fn test_replace_lines_operation() {
         let mut content = String::from("Line 1\nLine 2\nLine 3\nLine 4");
@@ -688,7 +2237,123 @@ mod tests {
             content: String::from("New Line 2\nNew Line 3"),
         };
         
-        apply_operation(&operation, &mut content).unwrap();
+        apply_operation(&operation, &mut content, PositionEncoding::Char, None).unwrap();
         assert_eq!(content, "Line 1\nNew Line 2\nNew Line 3\nLine 4");
     }
+    
+    #[test]
+    fn test_operations_preserve_text_format() {
+        // CRLF endings, BOM and the missing trailing newline survive operations that insert LF text
+        let mut content = String::from("\u{feff}Line 1\r\nLine 2\r\nLine 3");
+        let operations = vec![
+            json!({"type": "replace_lines", "start_line": 1, "end_line": 1, "content": "New 2a\nNew 2b\n"}),
+            json!({"type": "replace", "find": "Line 3", "replace": "Line 3\nLine 4\n"}),
+            json!({"type": "delete", "start": 0, "end": 1}),
+        ];
+        let (applied, results) = apply_operations(&operations, &mut content, PositionEncoding::Char, None);
+        assert_eq!(applied, 3, "{:?}", results);
+        assert_eq!(content, "\u{feff}Line 1\r\nNew 2a\r\nNew 2b\r\nLine 3\r\nLine 4");
+        
+        // Even an operation whose text drops the BOM or adds a trailing newline keeps them
+        let operations = vec![json!({"type": "replace", "find": "\u{feff}Line 1", "replace": "Line 1"}),
+                              json!({"type": "replace", "find": "Line 4", "replace": "Line 4\r\n"})];
+        let (applied, results) = apply_operations(&operations, &mut content, PositionEncoding::Char, None);
+        assert_eq!(applied, 2, "{:?}", results);
+        assert_eq!(content, "\u{feff}Line 1\r\nNew 2a\r\nNew 2b\r\nLine 3\r\nLine 4");
+        
+        // An explicit normalization changes the format
+        let operations = vec![json!({"type": "normalize_line_endings", "line_ending": "lf", "trailing_newline": true})];
+        apply_operations(&operations, &mut content, PositionEncoding::Char, None);
+        assert_eq!(content, "\u{feff}Line 1\nNew 2a\nNew 2b\nLine 3\nLine 4\n");
+        
+        let operations = vec![json!({"type": "normalize_line_endings", "line_ending": "lf", "bom": false})];
+        apply_operations(&operations, &mut content, PositionEncoding::Char, None);
+        assert_eq!(content, "Line 1\nNew 2a\nNew 2b\nLine 3\nLine 4\n");
+    }
+    
+    #[test]
+    fn test_item_operations() {
+        let rust = Some(SourceLanguage::Rust);
+        let mut content = String::from("struct Foo;\n\nimpl Foo {\n    /// Old docs\n    fn bar(&self) -> u32 {\n        1\n    }\n\n    fn baz(&self) {}\n}\n");
+        
+        let replace = EditOperation::ReplaceItem {
+            item: String::from("Foo::bar"),
+            content: String::from("fn bar(&self) -> u32 {\n    2\n}\n"),
+        };
+        let spans = apply_operation(&replace, &mut content, PositionEncoding::Char, rust).unwrap();
+        assert_eq!(spans[0].start_line, 3);
+        assert_eq!(content, "struct Foo;\n\nimpl Foo {\n    fn bar(&self) -> u32 {\n        2\n    }\n\n    fn baz(&self) {}\n}\n");
+        
+        let insert = EditOperation::InsertItemAfter {
+            item: String::from("impl Foo::bar"),
+            content: String::from("fn qux(&self) {}"),
+        };
+        apply_operation(&insert, &mut content, PositionEncoding::Char, rust).unwrap();
+        assert!(content.contains("    }\n\n    fn qux(&self) {}\n\n    fn baz"));
+        
+        let delete = EditOperation::DeleteItem { item: String::from("qux") };
+        apply_operation(&delete, &mut content, PositionEncoding::Char, rust).unwrap();
+        assert_eq!(content, "struct Foo;\n\nimpl Foo {\n    fn bar(&self) -> u32 {\n        2\n    }\n\n    fn baz(&self) {}\n}\n");
+        
+        let delete = EditOperation::DeleteItem { item: String::from("baz") };
+        apply_operation(&delete, &mut content, PositionEncoding::Char, rust).unwrap();
+        assert!(content.ends_with("        2\n    }\n}\n"));
+        
+        let delete = EditOperation::DeleteItem { item: String::from("Foo") };
+        apply_operation(&delete, &mut content, PositionEncoding::Char, rust).unwrap();
+        assert!(content.starts_with("impl Foo {"));
+        
+        // Without a language the item operations fail instead of guessing
+        assert!(apply_operation(&EditOperation::DeleteItem { item: String::from("baz") }, &mut content, PositionEncoding::Char, None).is_err());
+    }
+    
+    // Run the edit tool on a file holding `initial`, returning its text, is_error and the file afterwards
+    fn edit_file(initial: &[u8], mut args: Value) -> (String, bool, Vec<u8>) {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let allowed_paths = AllowedPaths::new(vec![temp_dir.path().to_path_buf()]).unwrap();
+        let locks = LockManager::new(temp_dir.path().join(".locks"));
+        let journal = Journal::new(temp_dir.path().join(".journal"), 10);
+        let path = temp_dir.path().join("file.txt");
+        fs::write(&path, initial).unwrap();
+        
+        args["path"] = json!(path);
+        let result = execute(&args, &allowed_paths, &locks, &journal).unwrap();
+        let text = match &result.content[0] {
+            ToolContent::Text { text } => text.clone(),
+            _ => panic!("Expected text content"),
+        };
+        (text, result.is_error == Some(true), fs::read(&path).unwrap())
+    }
+    
+    #[test]
+    fn test_edit_damaged_utf8_file() {
+        // One invalid byte does not make a UTF-8 file windows-1252
+        let (text, is_error, bytes) = edit_file(
+            b"let name = \"caf\xc3\xa9\";\n// damaged: \xff\n",
+            json!({ "operations": [{ "type": "replace", "find": "café", "replace": "naïve" }] }),
+        );
+        assert!(!is_error, "{}", text);
+        assert!(text.contains("Warning: The file is not valid UTF-8"), "{}", text);
+        assert_eq!(bytes, "let name = \"naïve\";\n// damaged: \u{fffd}\n".as_bytes());
+    }
+    
+    #[test]
+    fn test_edit_reject_new_errors() {
+        let edit = |find: &str, replace: &str| json!({
+            "operations": [{ "type": "replace", "find": find, "replace": replace }],
+            "language": "rust",
+            "reject_new_errors": true,
+        });
+        
+        // Breaking a valid file is refused and leaves it untouched
+        let (text, is_error, bytes) = edit_file(b"fn main() {\n    let a = 1;\n}\n", edit("1;", "1 +;"));
+        assert!(is_error, "{}", text);
+        assert!(text.contains("introduces syntax errors"), "{}", text);
+        assert_eq!(bytes, b"fn main() {\n    let a = 1;\n}\n");
+        
+        // An already invalid file can still be edited, even if it stays invalid
+        let (text, is_error, bytes) = edit_file(b"fn main() {\n    let a = ;\n}\nfn other() {}\n", edit("other", "renamed"));
+        assert!(!is_error, "{}", text);
+        assert_eq!(bytes, b"fn main() {\n    let a = ;\n}\nfn renamed() {}\n");
+    }
 }
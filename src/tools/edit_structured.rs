@@ -0,0 +1,279 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use mcp_protocol::types::tool::{ToolCallResult, ToolContent};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+use std::time::SystemTime;
+use tracing::debug;
+
+use crate::tools::edit;
+use crate::utils::atomic;
+use crate::utils::diff;
+use crate::utils::encoding::Charset;
+use crate::utils::journal::Journal;
+use crate::utils::lock::{self, LockManager};
+use crate::utils::path::{AllowedPaths, PathError};
+use crate::utils::plan::{ActionKind, Plan};
+use crate::utils::precondition::{self, Preconditions};
+use crate::utils::result::error_result;
+use crate::utils::structured::{self, DataFormat, Operation, StructuredError};
+
+// Define the schema for the tool
+pub fn schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "path": {
+                "type": "string",
+                "description": "Full path to the JSON, YAML or TOML file to edit"
+            },
+            "key_path": {
+                "type": "string",
+                "description": "Location to change: a JSON Pointer (\"/dependencies/serde/version\") or a dotted path with array indices (\"dependencies.serde.features[0]\"); \"-\" as an index addresses the end of an array, and an empty path the whole document"
+            },
+            "operation": {
+                "type": "string",
+                "enum": ["set", "delete", "append", "merge"],
+                "description": "set: replace or add the value; delete: remove the key or element; append: push the value onto an array (created if missing); merge: merge an object into the existing one, where null removes a key"
+            },
+            "value": {
+                "description": "JSON value to set, append or merge (not used by delete)"
+            },
+            "format": {
+                "type": "string",
+                "enum": ["json", "yaml", "toml"],
+                "description": "File format; detected from the extension if omitted"
+            },
+            "allow_comment_loss": {
+                "type": "boolean",
+                "description": "Allow editing a YAML file that has comments; YAML is re-serialized, so its comments are lost",
+                "default": false
+            },
+            "create_if_missing": {
+                "type": "boolean",
+                "description": "Create the file if it doesn't exist",
+                "default": false
+            },
+            "dry_run": {
+                "type": "boolean",
+                "description": "Validate and report the planned changes without modifying the filesystem",
+                "default": false
+            },
+            "diff": {
+                "type": "boolean",
+                "description": "Include a unified diff of the change in the response",
+                "default": false
+            },
+            "context_lines": {
+                "type": "integer",
+                "description": "Number of unchanged lines shown around each change in the diff",
+                "default": 3
+            },
+            "if_match_hash": {
+                "type": "string",
+                "description": "Only proceed if the file's current SHA-256 hash (as returned by read or info) matches"
+            },
+            "if_unmodified_since": {
+                "type": "string",
+                "description": "Only proceed if the file has not been modified after this RFC 3339 timestamp (as returned by read or info)"
+            },
+            "session": {
+                "type": "string",
+                "description": "Session identifier used with the lock tool; paths locked by other sessions are refused"
+            }
+        },
+        "required": ["path", "key_path", "operation"]
+    })
+}
+
+// Execute the edit_structured tool
+pub fn execute(args: &Value, allowed_paths: &AllowedPaths, locks: &LockManager, journal: &Journal) -> Result<ToolCallResult> {
+    // Extract parameters
+    let path_str = args.get("path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("Missing path parameter"))?;
+
+    let key_path_str = args.get("key_path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("Missing key_path parameter"))?;
+
+    let operation_str = args.get("operation")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("Missing operation parameter"))?;
+
+    let Some(operation) = Operation::parse(operation_str) else {
+        return Ok(error_result(format!(
+            "Invalid operation '{}' (expected set, delete, append or merge)",
+            operation_str
+        )));
+    };
+
+    let value = args.get("value").cloned().unwrap_or(Value::Null);
+    if operation != Operation::Delete && args.get("value").is_none() {
+        return Ok(error_result(format!("The {} operation requires a value", operation)));
+    }
+
+    let allow_comment_loss = args.get("allow_comment_loss")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let create_if_missing = args.get("create_if_missing")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let dry_run = args.get("dry_run")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let show_diff = args.get("diff")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let context_lines = args.get("context_lines")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(3) as usize;
+
+    let session = args.get("session").and_then(|v| v.as_str());
+
+    let preconditions = match Preconditions::from_args(args) {
+        Ok(p) => p,
+        Err(e) => return Ok(error_result(e.to_string())),
+    };
+
+    let key_path = match structured::parse_key_path(key_path_str) {
+        Ok(key_path) => key_path,
+        Err(e) => return Ok(error_result(e.to_string())),
+    };
+
+    // Use the explicit format, or detect it from the extension
+    let path = Path::new(path_str);
+    let format = match args.get("format").and_then(|v| v.as_str()) {
+        Some(name) => match DataFormat::parse(name) {
+            Some(format) => format,
+            None => return Ok(error_result(format!("Invalid format '{}' (expected json, yaml or toml)", name))),
+        },
+        None => match DataFormat::from_path(path) {
+            Some(format) => format,
+            None => return Ok(error_result(format!(
+                "Cannot detect the format of '{}' from its extension; pass format (json, yaml or toml)",
+                path_str
+            ))),
+        },
+    };
+
+    debug!(
+        "Structured edit of '{}' ({}): {} at '{}', dry_run: {}",
+        path_str, format, operation, key_path_str, dry_run
+    );
+
+    // Validate the path, allowing a new file in an existing allowed directory
+    let validated_path = match allowed_paths.validate_path(path) {
+        Ok(p) => p,
+        Err(PathError::NotFound) if create_if_missing => {
+            match path.parent().map(|parent| allowed_paths.validate_path(parent)) {
+                Some(Ok(_)) => path.to_path_buf(),
+                _ => return Ok(error_result(format!("Parent directory not found or not allowed: '{}'", path_str))),
+            }
+        }
+        Err(PathError::NotFound) => {
+            return Ok(error_result(format!(
+                "File not found: '{}'. Use create_if_missing=true to create it.",
+                path_str
+            )));
+        }
+        Err(PathError::OutsideAllowedPaths) => {
+            return Ok(error_result("Path is outside of all allowed directories".to_string()));
+        }
+        Err(PathError::IoError(io_err)) => return Ok(error_result(format!("IO error: {}", io_err))),
+    };
+
+    if validated_path.is_dir() {
+        return Ok(error_result(format!("Path is a directory, not a file: '{}'", path_str)));
+    }
+
+    // Refuse to touch a file that changed since the caller last saw it
+    if let Err(e) = preconditions.check(&validated_path) {
+        return Ok(precondition::error_result(e, &validated_path));
+    }
+
+    // Refuse to touch a path locked by another session
+    if let Err(e) = locks.check(&validated_path, session) {
+        return Ok(lock::error_result(e));
+    }
+
+    // Edit the text as UTF-8 whatever the file's charset
+    let exists = validated_path.exists();
+    let mut warnings = Vec::new();
+    let (charset, content) = if exists {
+        let bytes = fs::read(&validated_path).context("Failed to read file")?;
+        let (charset, warning) = Charset::detect_for_edit(&bytes);
+        warnings.extend(warning);
+        (charset, charset.decode(&bytes))
+    } else {
+        (Charset::UTF8, String::new())
+    };
+
+    let edited = match structured::edit(&content, format, &key_path, operation, &value, allow_comment_loss) {
+        Ok(edited) => edited,
+        // Report the refusal as a conflict of the planned write
+        Err(e @ StructuredError::CommentLoss) if dry_run => {
+            let mut plan = Plan::new("edit_structured");
+            plan.add_conflict(ActionKind::OverwriteFile, &validated_path, e.to_string());
+            return Ok(plan.into_result(allowed_paths));
+        }
+        Err(e) => return Ok(error_result(e.to_string())),
+    };
+    warnings.extend(edited.warnings);
+
+    // Save the file in its own charset
+    let output = match charset.encode(&edited.content) {
+        Ok(output) => output,
+        Err(e) => return Ok(error_result(e.to_string())),
+    };
+
+    // Report the resulting write without touching the file
+    if dry_run {
+        let mut plan = Plan::new("edit_structured");
+        let action = if exists { ActionKind::OverwriteFile } else { ActionKind::CreateFile };
+        plan.add(action, &validated_path, Some(output.len() as u64));
+        plan.add_note(format!("{} at '{}' ({})", operation, key_path_str, format));
+        for warning in &warnings {
+            plan.add_note(warning.clone());
+        }
+        return Ok(plan.into_result(allowed_paths));
+    }
+
+    // Write the new document atomically, keeping the previous contents for undo
+    let before = journal.capture(&validated_path);
+    let write_outcome = atomic::write_atomic(&validated_path, &output)
+        .context("Failed to write modified content")?;
+    journal.record(&validated_path, "edit_structured", before, Some(&output));
+
+    // Get file metadata
+    let metadata = fs::metadata(&validated_path).context("Failed to get file metadata")?;
+    let modified = metadata.modified().unwrap_or_else(|_| SystemTime::now());
+
+    let mut text = format!("File edited: {}\n", validated_path.display());
+    text.push_str(&format!("Format: {}\n", format));
+    text.push_str(&format!("Operation: {} at '{}'\n", operation, key_path_str));
+    if edited.content == content {
+        text.push_str("The document was already in the requested state\n");
+    }
+    for warning in warnings.iter().chain(&write_outcome.warnings) {
+        text.push_str(&format!("Warning: {}\n", warning));
+    }
+    text.push_str(&format!("File size: {} bytes\n", metadata.len()));
+    text.push_str(&format!("Last modified: {}\n", DateTime::<Utc>::from(modified).to_rfc3339()));
+    text.push_str(&format!("Hash: {}\n", precondition::hash_bytes(&output)));
+
+    if show_diff {
+        let relative_path = allowed_paths.closest_relative_path(&validated_path);
+        edit::append_diff(&mut text, &diff::unified_diff(&content, &edited.content, &relative_path, context_lines));
+    }
+
+    Ok(ToolCallResult {
+        content: vec![ToolContent::Text { text }],
+        is_error: Some(false),
+    })
+}
@@ -0,0 +1,124 @@
+use anyhow::Result;
+use chrono::Duration;
+use mcp_protocol::types::tool::{ToolCallResult, ToolContent};
+use serde_json::{json, Value};
+use tracing::debug;
+
+use crate::utils::path::{AllowedPaths, PathError};
+use crate::utils::trash::{self, TrashConfig};
+
+// Define the schema for the tool
+pub fn schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "root": {
+                "type": "string",
+                "description": "Only empty the trash of the allowed directory containing this path (default: all allowed directories)"
+            },
+            "name": {
+                "type": "string",
+                "description": "Permanently delete only this trash entry"
+            },
+            "older_than_days": {
+                "type": "integer",
+                "description": "Only delete entries trashed more than this many days ago"
+            },
+            "retention_only": {
+                "type": "boolean",
+                "description": "Only apply the server's configured retention limits (age and total size)",
+                "default": false
+            }
+        },
+        "required": []
+    })
+}
+
+// Execute the empty_trash tool
+pub fn execute(args: &Value, allowed_paths: &AllowedPaths, trash_config: &TrashConfig) -> Result<ToolCallResult> {
+    // Extract optional parameters
+    let root_str = args.get("root").and_then(|v| v.as_str());
+    let name = args.get("name").and_then(|v| v.as_str());
+
+    let older_than_days = args.get("older_than_days")
+        .and_then(|v| v.as_u64());
+
+    let retention_only = args.get("retention_only")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    debug!(
+        "Emptying trash, root: {:?}, name: {:?}, older_than_days: {:?}, retention_only: {}",
+        root_str, name, older_than_days, retention_only
+    );
+
+    let roots = match trash::selected_roots(allowed_paths, root_str) {
+        Ok(roots) => roots,
+        Err(e) => {
+            let error_message = match e {
+                PathError::OutsideAllowedPaths =>
+                    "Root is outside of all allowed directories".to_string(),
+                PathError::NotFound =>
+                    format!("Root not found: '{}'", root_str.unwrap_or_default()),
+                PathError::IoError(io_err) =>
+                    format!("IO error: {}", io_err),
+            };
+
+            return Ok(ToolCallResult {
+                content: vec![ToolContent::Text { text: error_message }],
+                is_error: Some(true),
+            });
+        }
+    };
+
+    let mut removed = 0;
+    let mut freed = 0;
+    let mut errors = Vec::new();
+
+    for root in &roots {
+        let result = if let Some(name) = name {
+            match trash::remove_entry(root, name) {
+                Ok(bytes) => Ok((1, bytes)),
+                // The entry only has to exist in one of the selected roots
+                Err(trash::TrashError::EntryNotFound(_)) => Ok((0, 0)),
+                Err(e) => Err(e),
+            }
+        } else if retention_only {
+            trash::enforce_retention(root, trash_config)
+        } else {
+            trash::empty(root, older_than_days.map(|days| Duration::days(days as i64)))
+        };
+
+        match result {
+            Ok((count, bytes)) => {
+                removed += count;
+                freed += bytes;
+            }
+            Err(e) => errors.push(format!("{}: {}", root.display(), e)),
+        }
+    }
+
+    if let Some(name) = name {
+        if removed == 0 && errors.is_empty() {
+            return Ok(ToolCallResult {
+                content: vec![ToolContent::Text {
+                    text: format!("Trash entry not found: '{}'", name),
+                }],
+                is_error: Some(true),
+            });
+        }
+    }
+
+    let mut text = format!("Permanently deleted {} trash entries ({} bytes freed)\n", removed, freed);
+    if !errors.is_empty() {
+        text.push_str("\nErrors:\n");
+        for error in &errors {
+            text.push_str(&format!("- {}\n", error));
+        }
+    }
+
+    Ok(ToolCallResult {
+        content: vec![ToolContent::Text { text }],
+        is_error: Some(!errors.is_empty()),
+    })
+}
@@ -0,0 +1,108 @@
+use anyhow::{anyhow, Result};
+use mcp_protocol::types::tool::{ToolCallResult, ToolContent};
+use serde_json::{json, Value};
+use std::path::Path;
+use tracing::debug;
+
+use crate::utils::journal::{Journal, Revision};
+use crate::utils::path::{AllowedPaths, PathError};
+
+// Define the schema for the tool
+pub fn schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "path": {
+                "type": "string",
+                "description": "Full path to the file whose history to list"
+            }
+        },
+        "required": ["path"]
+    })
+}
+
+// Execute the history tool
+pub fn execute(args: &Value, allowed_paths: &AllowedPaths, journal: &Journal) -> Result<ToolCallResult> {
+    // Extract parameters
+    let path_str = args.get("path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("Missing path parameter"))?;
+
+    debug!("Listing history of '{}'", path_str);
+
+    // Validate the path (the file itself may have been deleted)
+    let validated_path = match allowed_paths.validate_path(Path::new(path_str)) {
+        Ok(p) => p,
+        Err(e) => {
+            let error_message = match e {
+                PathError::OutsideAllowedPaths =>
+                    "Path is outside of all allowed directories".to_string(),
+                PathError::NotFound =>
+                    format!("Parent directory not found: '{}'", path_str),
+                PathError::IoError(io_err) =>
+                    format!("IO error: {}", io_err),
+            };
+
+            return Ok(ToolCallResult {
+                content: vec![ToolContent::Text { text: error_message }],
+                is_error: Some(true),
+            });
+        }
+    };
+
+    let history = match journal.history(&validated_path) {
+        Ok(history) => history,
+        Err(e) => {
+            return Ok(ToolCallResult {
+                content: vec![ToolContent::Text { text: e.to_string() }],
+                is_error: Some(true),
+            });
+        }
+    };
+
+    let mut text = format!(
+        "History of {} (current revision: {})\n",
+        allowed_paths.closest_relative_path(&validated_path),
+        history.current_revision()
+    );
+    if history.position == history.revisions.len() {
+        text.push_str("Revisions (newest last):\n");
+    } else {
+        text.push_str("Revisions (newest last; undone revisions can be redone):\n");
+    }
+
+    for (index, revision) in history.revisions.iter().enumerate() {
+        let marker = if index + 1 == history.position {
+            " (current)"
+        } else if index >= history.position {
+            " (undone)"
+        } else {
+            ""
+        };
+        text.push_str(&format!("  - {}{}\n", describe(revision), marker));
+    }
+
+    Ok(ToolCallResult {
+        content: vec![ToolContent::Text { text: text.trim_end().to_string() }],
+        is_error: Some(false),
+    })
+}
+
+// One-line description of a revision, e.g. "Revision 3 [edit] 2024-01-01T00:00:00Z: 1a2b3c4d5e6f -> 6f5e4d3c2b1a"
+pub(crate) fn describe(revision: &Revision) -> String {
+    format!(
+        "Revision {} [{}] {}: {} -> {}",
+        revision.id,
+        revision.tool,
+        revision.timestamp.to_rfc3339(),
+        short_hash(revision.before.as_deref()),
+        short_hash(revision.after.as_deref())
+    )
+}
+
+pub(crate) fn short_hash(hash: Option<&str>) -> String {
+    match hash {
+        Some(hash) => hash.chars().take(12).collect(),
+        None => "(no file)".to_string(),
+    }
+}
@@ -10,6 +10,7 @@ use tracing::debug;
 use chrono::{DateTime, Utc};
 
 use crate::utils::path::{AllowedPaths, PathError};
+use crate::utils::precondition;
 
 // Define the schema for the tool
 pub fn schema() -> Value {
@@ -128,6 +129,13 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
     // Check if the file is hidden
     let is_hidden = name.starts_with('.');
     
+    // Content hash for files, usable as an if_match_hash precondition
+    let hash = if metadata.is_file() {
+        precondition::hash_file(&validated_path).ok()
+    } else {
+        None
+    };
+    
     // Get relative path
     let relative_path = allowed_paths.closest_relative_path(&validated_path);
     
@@ -140,6 +148,7 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
         "size": if metadata.is_file() { metadata.len() } else { 0 },
         "created": created_time,
         "modified": modified_time,
+        "hash": hash,
         "accessed": accessed_time,
         "permissions": {
             "readable": readable,
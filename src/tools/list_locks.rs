@@ -0,0 +1,67 @@
+use anyhow::Result;
+use mcp_protocol::types::tool::{ToolCallResult, ToolContent};
+use serde_json::{json, Value};
+use tracing::debug;
+
+use crate::utils::lock::LockManager;
+use crate::utils::path::AllowedPaths;
+
+// Define the schema for the tool
+pub fn schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "session": {
+                "type": "string",
+                "description": "Only list locks held by this session"
+            }
+        },
+        "required": []
+    })
+}
+
+// Execute the list_locks tool
+pub fn execute(args: &Value, allowed_paths: &AllowedPaths, locks: &LockManager) -> Result<ToolCallResult> {
+    // Extract optional parameters
+    let session = args.get("session").and_then(|v| v.as_str());
+
+    debug!("Listing locks, session: {:?}", session);
+
+    let all_locks = match locks.list() {
+        Ok(all_locks) => all_locks,
+        Err(e) => {
+            return Ok(ToolCallResult {
+                content: vec![ToolContent::Text {
+                    text: format!("Failed to list locks: {}", e),
+                }],
+                is_error: Some(true),
+            });
+        }
+    };
+
+    // Only report locks inside the allowed directories
+    let entries: Vec<Value> = all_locks
+        .iter()
+        .filter(|info| allowed_paths.root_for(&info.path).is_some())
+        .filter(|info| session.is_none_or(|s| info.session == s))
+        .map(|info| {
+            json!({
+                "path": allowed_paths.closest_relative_path(&info.path),
+                "session": info.session,
+                "acquired": info.acquired.to_rfc3339(),
+                "expires": info.expires.to_rfc3339(),
+                "pid": info.pid
+            })
+        })
+        .collect();
+
+    let result = json!({
+        "count": entries.len(),
+        "locks": entries
+    });
+
+    Ok(ToolCallResult {
+        content: vec![ToolContent::Text { text: result.to_string() }],
+        is_error: Some(false),
+    })
+}
@@ -0,0 +1,95 @@
+use anyhow::Result;
+use mcp_protocol::types::tool::{ToolCallResult, ToolContent};
+use serde_json::{json, Value};
+use tracing::debug;
+
+use crate::utils::path::{AllowedPaths, PathError};
+use crate::utils::trash;
+
+// Define the schema for the tool
+pub fn schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "root": {
+                "type": "string",
+                "description": "Only list the trash of the allowed directory containing this path (default: all allowed directories)"
+            }
+        },
+        "required": []
+    })
+}
+
+// Execute the list_trash tool
+pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallResult> {
+    // Extract optional parameters
+    let root_str = args.get("root").and_then(|v| v.as_str());
+
+    debug!("Listing trash, root: {:?}", root_str);
+
+    // Determine which roots to inspect
+    let roots = match trash::selected_roots(allowed_paths, root_str) {
+        Ok(roots) => roots,
+        Err(e) => {
+            let error_message = match e {
+                PathError::OutsideAllowedPaths =>
+                    "Root is outside of all allowed directories".to_string(),
+                PathError::NotFound =>
+                    format!("Root not found: '{}'", root_str.unwrap_or_default()),
+                PathError::IoError(io_err) =>
+                    format!("IO error: {}", io_err),
+            };
+
+            return Ok(ToolCallResult {
+                content: vec![ToolContent::Text { text: error_message }],
+                is_error: Some(true),
+            });
+        }
+    };
+
+    let mut text = String::new();
+    let mut total_entries = 0;
+
+    for root in &roots {
+        let entries = match trash::list_entries(root) {
+            Ok(entries) => entries,
+            Err(e) => {
+                return Ok(ToolCallResult {
+                    content: vec![ToolContent::Text {
+                        text: format!("Failed to read trash for '{}': {}", root.display(), e),
+                    }],
+                    is_error: Some(true),
+                });
+            }
+        };
+
+        if entries.is_empty() {
+            continue;
+        }
+
+        total_entries += entries.len();
+        text.push_str(&format!("Trash for {} ({} items)\n", root.display(), entries.len()));
+
+        for entry in &entries {
+            let type_str = if entry.is_dir { "[DIR]" } else { "[FILE]" };
+            text.push_str(&format!(
+                "  {} {} ({} bytes, deleted {})\n    original: {}\n",
+                type_str,
+                entry.name,
+                entry.size,
+                entry.deletion_date.format("%Y-%m-%d %H:%M:%S"),
+                entry.original_path.display()
+            ));
+        }
+        text.push('\n');
+    }
+
+    if total_entries == 0 {
+        text.push_str("Trash is empty\n");
+    }
+
+    Ok(ToolCallResult {
+        content: vec![ToolContent::Text { text: text.trim_end().to_string() }],
+        is_error: Some(false),
+    })
+}
@@ -0,0 +1,88 @@
+use anyhow::{anyhow, Result};
+use chrono::Duration;
+use mcp_protocol::types::tool::{ToolCallResult, ToolContent};
+use serde_json::{json, Value};
+use std::path::Path;
+use tracing::debug;
+
+use crate::utils::lock::{self, LockManager};
+use crate::utils::path::{AllowedPaths, PathError};
+
+// Define the schema for the tool
+pub fn schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "path": {
+                "type": "string",
+                "description": "Full path to the file or directory to lock (it does not need to exist yet)"
+            },
+            "session": {
+                "type": "string",
+                "description": "Identifier of the caller; pass the same value to write, edit and delete"
+            },
+            "ttl_secs": {
+                "type": "integer",
+                "description": "Lease duration in seconds; locking again renews the lease",
+                "default": 300
+            }
+        },
+        "required": ["path", "session"]
+    })
+}
+
+// Execute the lock tool
+pub fn execute(args: &Value, allowed_paths: &AllowedPaths, locks: &LockManager) -> Result<ToolCallResult> {
+    // Extract parameters
+    let path_str = args.get("path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("Missing path parameter"))?;
+
+    let session = args.get("session")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("Missing session parameter"))?;
+
+    let ttl_secs = args.get("ttl_secs")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(300);
+
+    debug!("Locking '{}' for session '{}', ttl: {}s", path_str, session, ttl_secs);
+
+    // Validate the path
+    let validated_path = match allowed_paths.validate_path(Path::new(path_str)) {
+        Ok(p) => p,
+        Err(e) => {
+            let error_message = match e {
+                PathError::OutsideAllowedPaths =>
+                    "Path is outside of all allowed directories".to_string(),
+                PathError::NotFound =>
+                    format!("Parent directory not found: '{}'", path_str),
+                PathError::IoError(io_err) =>
+                    format!("IO error: {}", io_err),
+            };
+
+            return Ok(ToolCallResult {
+                content: vec![ToolContent::Text { text: error_message }],
+                is_error: Some(true),
+            });
+        }
+    };
+
+    match locks.acquire(&validated_path, session, Duration::seconds(ttl_secs as i64)) {
+        Ok(info) => {
+            let result = json!({
+                "success": true,
+                "path": allowed_paths.closest_relative_path(&info.path),
+                "session": info.session,
+                "acquired": info.acquired.to_rfc3339(),
+                "expires": info.expires.to_rfc3339()
+            });
+
+            Ok(ToolCallResult {
+                content: vec![ToolContent::Text { text: result.to_string() }],
+                is_error: Some(false),
+            })
+        }
+        Err(e) => Ok(lock::error_result(e)),
+    }
+}
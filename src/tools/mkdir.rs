@@ -8,6 +8,7 @@ use std::{
 use tracing::debug;
 
 use crate::utils::path::{AllowedPaths, PathError};
+use crate::utils::plan::{self, ActionKind, Plan};
 
 // Define the schema for the tool
 pub fn schema() -> Value {
@@ -22,6 +23,11 @@ pub fn schema() -> Value {
                 "type": "boolean",
                 "description": "Create parent directories if they don't exist",
                 "default": true
+            },
+            "dry_run": {
+                "type": "boolean",
+                "description": "Validate and report the planned changes without modifying the filesystem",
+                "default": false
             }
         },
         "required": ["path"]
@@ -40,9 +46,13 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
         .and_then(|v| v.as_bool())
         .unwrap_or(true);
     
+    let dry_run = args.get("dry_run")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    
     debug!(
-        "Creating directory: '{}', recursive: {}",
-        path_str, recursive
+        "Creating directory: '{}', recursive: {}, dry_run: {}",
+        path_str, recursive, dry_run
     );
     
     // Create Path object
@@ -101,6 +111,19 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
         }
     }
     
+    // Report the directories that would be created
+    if dry_run {
+        let mut plan = Plan::new("mkdir");
+        if recursive {
+            plan::add_missing_dirs(&mut plan, &validated_path);
+        } else if validated_path.parent().is_some_and(|parent| !parent.exists()) {
+            plan.add_conflict(ActionKind::CreateDir, &validated_path, "Parent directory does not exist");
+        } else {
+            plan.add(ActionKind::CreateDir, &validated_path, None);
+        }
+        return Ok(plan.into_result(allowed_paths));
+    }
+    
     // Create the directory
     let result = if recursive {
         fs::create_dir_all(&validated_path)
@@ -7,5 +7,18 @@ pub mod delete;
 pub mod copy;
 pub mod move_file;
 pub mod info;
+pub mod outline;
 pub mod list_allowed_dirs;
 pub mod edit;
+pub mod list_trash;
+pub mod restore;
+pub mod empty_trash;
+pub mod lock;
+pub mod unlock;
+pub mod list_locks;
+pub mod patch;
+pub mod batch_edit;
+pub mod edit_structured;
+pub mod history;
+pub mod undo;
+pub mod redo;
@@ -3,11 +3,15 @@ use mcp_protocol::types::tool::{ToolCallResult, ToolContent};
 use serde_json::{json, Value};
 use std::{
     fs,
-    path::Path,
+    path::{Path, PathBuf},
 };
 use tracing::debug;
 
+use crate::utils::journal::Journal;
+use crate::utils::lock::{self, LockManager};
 use crate::utils::path::{AllowedPaths, PathError};
+use crate::utils::plan::{ActionKind, Plan};
+use crate::utils::precondition::{self, Preconditions};
 
 // Define the schema for the tool
 pub fn schema() -> Value {
@@ -26,6 +30,23 @@ pub fn schema() -> Value {
                 "type": "boolean",
                 "description": "Whether to overwrite existing files",
                 "default": false
+            },
+            "dry_run": {
+                "type": "boolean",
+                "description": "Validate and report the planned changes without modifying the filesystem",
+                "default": false
+            },
+            "if_match_hash": {
+                "type": "string",
+                "description": "Only proceed if the source file's current SHA-256 hash (as returned by read or info) matches"
+            },
+            "if_unmodified_since": {
+                "type": "string",
+                "description": "Only proceed if the source has not been modified after this RFC 3339 timestamp (as returned by read or info)"
+            },
+            "session": {
+                "type": "string",
+                "description": "Session identifier used with the lock tool; paths locked by other sessions are refused"
             }
         },
         "required": ["source", "destination"]
@@ -33,7 +54,7 @@ pub fn schema() -> Value {
 }
 
 // Execute the move_file tool
-pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallResult> {
+pub fn execute(args: &Value, allowed_paths: &AllowedPaths, locks: &LockManager, journal: &Journal) -> Result<ToolCallResult> {
     // Extract required parameters
     let source_str = args.get("source")
         .and_then(|v| v.as_str())
@@ -48,9 +69,25 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
         .and_then(|v| v.as_bool())
         .unwrap_or(false);
     
+    let dry_run = args.get("dry_run")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    
+    let session = args.get("session").and_then(|v| v.as_str());
+    
+    let preconditions = match Preconditions::from_args(args) {
+        Ok(p) => p,
+        Err(e) => {
+            return Ok(ToolCallResult {
+                content: vec![ToolContent::Text { text: e.to_string() }],
+                is_error: Some(true),
+            });
+        }
+    };
+    
     debug!(
-        "Moving from '{}' to '{}', overwrite: {}",
-        source_str, destination_str, overwrite
+        "Moving from '{}' to '{}', overwrite: {}, dry_run: {}",
+        source_str, destination_str, overwrite, dry_run
     );
     
     // Create Path objects
@@ -150,6 +187,28 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
         });
     }
     
+    // Refuse to touch a path that changed since the caller last saw it
+    if let Err(e) = preconditions.check(&validated_source) {
+        return Ok(precondition::error_result(e, &validated_source));
+    }
+    
+    // Refuse to move a path locked by another session, or onto one
+    let target = move_target(&validated_source, &validated_destination);
+    for path in std::iter::once(&validated_source).chain(target.as_ref()) {
+        if let Err(e) = locks.check(path, session) {
+            return Ok(lock::error_result(e));
+        }
+    }
+    
+    if dry_run {
+        return Ok(plan_move(&validated_source, &validated_destination, overwrite).into_result(allowed_paths));
+    }
+    
+    // Keep the contents of a moved file for undo, at both ends of the move
+    let source_before = if validated_source.is_file() { journal.capture(&validated_source) } else { None };
+    
+    let destination_before = journal.capture(&validated_destination);
+    
     // Check if the destination exists
     if validated_destination.exists() {
         // Handle directory-to-directory move
@@ -224,8 +283,10 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
             }
             
             // Move file into destination directory
+            let destination_before = journal.capture(&new_dest);
             match fs::rename(&validated_source, &new_dest) {
                 Ok(_) => {
+                    record_move(journal, &validated_source, &new_dest, source_before, destination_before);
                     let src_rel = allowed_paths.closest_relative_path(&validated_source);
                     let dest_rel = allowed_paths.closest_relative_path(&new_dest);
                     
@@ -274,6 +335,8 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
     // Perform the move
     match fs::rename(&validated_source, &validated_destination) {
         Ok(_) => {
+            record_move(journal, &validated_source, &validated_destination, source_before, destination_before);
+
             let src_rel = allowed_paths.closest_relative_path(&validated_source);
             let dest_rel = allowed_paths.closest_relative_path(&validated_destination);
             
@@ -298,3 +361,52 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
         }
     }
 }
+
+// Record a moved file in the journal: removed at the source and written at the destination
+fn record_move(
+    journal: &Journal,
+    source: &Path,
+    destination: &Path,
+    source_before: Option<Option<Vec<u8>>>,
+    destination_before: Option<Option<Vec<u8>>>,
+) {
+    let contents = source_before.as_ref().and_then(|before| before.clone());
+    if contents.is_some() {
+        journal.record(destination, "move", destination_before, contents.as_deref());
+        journal.record(source, "move", source_before, None);
+    }
+}
+
+// The path a move ends at: inside the destination when that is a directory
+fn move_target(source: &Path, destination: &Path) -> Option<PathBuf> {
+    if destination.is_dir() && (source.is_dir() || source.is_file()) {
+        source.file_name().map(|name| destination.join(name))
+    } else {
+        Some(destination.to_path_buf())
+    }
+}
+
+// Build the plan for a move, mirroring the destination rules of execute
+fn plan_move(source: &Path, destination: &Path, overwrite: bool) -> Plan {
+    let mut plan = Plan::new("move");
+    let bytes = fs::metadata(source).ok().filter(|m| m.is_file()).map(|m| m.len());
+    
+    let Some(target) = move_target(source, destination) else {
+        plan.add_error("Invalid source name");
+        return plan;
+    };
+    
+    let target_exists = target.exists();
+    if target_exists && !overwrite {
+        plan.add_conflict(ActionKind::Move, &target, "Destination already exists and overwrite is false");
+        return plan;
+    }
+    
+    // A direct overwrite removes the existing destination first
+    if target_exists && target == destination {
+        plan.add(ActionKind::Remove, &target, None);
+    }
+    
+    plan.add(ActionKind::Move, &target, bytes).source = Some(source.display().to_string());
+    plan
+}
@@ -0,0 +1,173 @@
+use anyhow::{anyhow, Context, Result};
+use mcp_protocol::types::tool::{ToolCallResult, ToolContent};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+use tracing::debug;
+
+use crate::utils::path::{is_text_file, AllowedPaths, PathError};
+use crate::utils::result::error_result;
+use crate::utils::syntax::{self, SourceLanguage, SyntaxItem};
+
+// Define the schema for the tool
+pub fn schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "path": {
+                "type": "string",
+                "description": "Path to the source or Markdown file to outline (full path or relative to one of the allowed directories)"
+            },
+            "language": {
+                "type": "string",
+                "enum": ["rust", "python", "typescript", "tsx", "javascript", "go", "markdown"],
+                "description": "Language of the file; detected from the extension if omitted"
+            },
+            "max_depth": {
+                "type": "integer",
+                "description": "Only list items nested at most this deep (0 lists top-level items only)"
+            }
+        },
+        "required": ["path"]
+    })
+}
+
+// Language of the outlined file
+enum OutlineLanguage {
+    Source(SourceLanguage),
+    Markdown,
+}
+
+impl OutlineLanguage {
+    fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "markdown" | "md" => Some(OutlineLanguage::Markdown),
+            other => SourceLanguage::parse(other).map(OutlineLanguage::Source),
+        }
+    }
+
+    fn from_path(path: &Path) -> Option<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("md" | "markdown" | "mdx") => Some(OutlineLanguage::Markdown),
+            _ => SourceLanguage::from_path(path).map(OutlineLanguage::Source),
+        }
+    }
+
+    fn name(&self) -> String {
+        match self {
+            OutlineLanguage::Source(language) => language.to_string(),
+            OutlineLanguage::Markdown => "Markdown".to_string(),
+        }
+    }
+}
+
+// Execute the outline tool
+pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallResult> {
+    // Extract parameters
+    let path_str = args.get("path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("Missing path parameter"))?;
+
+    let max_depth = args.get("max_depth")
+        .and_then(|v| v.as_u64())
+        .map(|v| v as usize);
+
+    debug!("Outlining '{}', max_depth: {:?}", path_str, max_depth);
+
+    // Validate the path
+    let validated_path = match allowed_paths.validate_path(Path::new(path_str)) {
+        Ok(p) => p,
+        Err(e) => {
+            let error_message = match e {
+                PathError::OutsideAllowedPaths =>
+                    "Path is outside of all allowed directories".to_string(),
+                PathError::NotFound =>
+                    format!("File not found: '{}'", path_str),
+                PathError::IoError(io_err) =>
+                    format!("IO error: {}", io_err),
+            };
+            return Ok(error_result(error_message));
+        }
+    };
+
+    if validated_path.is_dir() {
+        return Ok(error_result(format!("Path is a directory, not a file: '{}'", path_str)));
+    }
+
+    // Use the explicit language, or detect it from the extension
+    let language = match args.get("language").and_then(|v| v.as_str()) {
+        Some(name) => match OutlineLanguage::parse(name) {
+            Some(language) => language,
+            None => return Ok(error_result(format!(
+                "Invalid language '{}' (expected rust, python, typescript, tsx, javascript, go or markdown)",
+                name
+            ))),
+        },
+        None => match OutlineLanguage::from_path(&validated_path) {
+            Some(language) => language,
+            None => return Ok(error_result(format!(
+                "Cannot detect the language of '{}' from its extension; pass language (rust, python, typescript, tsx, javascript, go or markdown)",
+                path_str
+            ))),
+        },
+    };
+
+    if !is_text_file(&validated_path)? {
+        return Ok(error_result(format!("File appears to be binary, outline not supported: '{}'", path_str)));
+    }
+
+    let content = fs::read_to_string(&validated_path).context("Failed to read file")?;
+
+    let items = match &language {
+        OutlineLanguage::Source(source_language) => match syntax::items(&content, *source_language) {
+            Ok(items) => items,
+            Err(e) => return Ok(error_result(e.to_string())),
+        },
+        OutlineLanguage::Markdown => syntax::markdown_headings(&content),
+    };
+
+    let total = items.len();
+    let items: Vec<SyntaxItem> = items.into_iter()
+        .filter(|item| max_depth.is_none_or(|max_depth| item.depth <= max_depth))
+        .collect();
+
+    let mut text = format!(
+        "Outline of {} ({}, {} lines)\n",
+        allowed_paths.closest_relative_path(&validated_path),
+        language.name(),
+        content.lines().count()
+    );
+    if items.is_empty() {
+        text.push_str("No items found\n");
+    } else {
+        text.push_str("Items (0-based inclusive line ranges, usable as read's start_line and end_line):\n");
+        for item in &items {
+            text.push_str(&format!(
+                "{}- {} (lines {}-{})\n",
+                "  ".repeat(item.depth),
+                label(item),
+                item.start_line,
+                item.end_line
+            ));
+        }
+    }
+    if items.len() < total {
+        text.push_str(&format!("{} nested items deeper than max_depth not shown\n", total - items.len()));
+    }
+
+    Ok(ToolCallResult {
+        content: vec![ToolContent::Text { text: text.trim_end().to_string() }],
+        is_error: Some(false),
+    })
+}
+
+// "fn main", "class Api" or "## Install"; impl blocks are already named "impl Foo"
+fn label(item: &SyntaxItem) -> String {
+    if let Some(level) = item.kind.strip_prefix('h').and_then(|level| level.parse::<usize>().ok()) {
+        return format!("{} {}", "#".repeat(level), item.name);
+    }
+    if item.name.starts_with(&format!("{} ", item.kind)) {
+        return item.name.clone();
+    }
+    format!("{} {}", item.kind, item.name)
+}
@@ -0,0 +1,481 @@
+use anyhow::{anyhow, Result};
+use mcp_protocol::types::tool::{ToolCallResult, ToolContent};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+use crate::utils::atomic;
+use crate::utils::encoding::Charset;
+use crate::utils::journal::Journal;
+use crate::utils::lock::LockManager;
+use crate::utils::patch::{self, ApplyOptions, FilePatch, HunkReport, HunkStatus};
+use crate::utils::path::{AllowedPaths, PathError};
+use crate::utils::plan::{ActionKind, Plan};
+use crate::utils::result::error_result;
+use crate::utils::trash::{self, TrashConfig};
+
+// Define the schema for the tool
+pub fn schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "patch": {
+                "type": "string",
+                "description": "Unified diff to apply (diff -u or git diff output); may span several files, with /dev/null marking created or deleted files"
+            },
+            "root": {
+                "type": "string",
+                "description": "Directory that relative paths in the patch are resolved against (default: the first allowed directory)"
+            },
+            "strip": {
+                "type": "integer",
+                "description": "Number of leading path components to remove from patch paths, like patch -p (default: 1 for a/ and b/ prefixes, otherwise 0)"
+            },
+            "fuzz": {
+                "type": "integer",
+                "description": "Number of leading and trailing context lines that may be ignored when a hunk does not match exactly",
+                "default": 2
+            },
+            "max_offset": {
+                "type": "integer",
+                "description": "How many lines away from its stated position a hunk may be applied",
+                "default": 1000
+            },
+            "allow_partial": {
+                "type": "boolean",
+                "description": "Apply the hunks that match even if others are rejected (by default nothing is written if any hunk is rejected)",
+                "default": false
+            },
+            "dry_run": {
+                "type": "boolean",
+                "description": "Validate and report the planned changes without modifying the filesystem",
+                "default": false
+            },
+            "session": {
+                "type": "string",
+                "description": "Session identifier used with the lock tool; paths locked by other sessions are refused"
+            }
+        },
+        "required": ["patch"]
+    })
+}
+
+// What a file patch does to the filesystem
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ChangeKind {
+    Create,
+    Modify,
+    Rename,
+    Delete,
+}
+
+impl ChangeKind {
+    fn describe(&self) -> &'static str {
+        match self {
+            ChangeKind::Create => "created",
+            ChangeKind::Modify => "modified",
+            ChangeKind::Rename => "renamed",
+            ChangeKind::Delete => "deleted",
+        }
+    }
+}
+
+// The outcome of applying one file patch in memory
+struct FileChange {
+    kind: ChangeKind,
+    display: String,
+    // Existing file the patch reads from (none for creations)
+    source: Option<PathBuf>,
+    // Path written (or removed, for deletions)
+    target: PathBuf,
+    // The patched text, and the same encoded in the source file's charset
+    content: String,
+    output: Vec<u8>,
+    reports: Vec<HunkReport>,
+    warnings: Vec<String>,
+    // A problem that prevents the file from being patched at all
+    error: Option<String>,
+}
+
+impl FileChange {
+    fn rejected(&self) -> usize {
+        self.reports.iter().filter(|r| r.status == HunkStatus::Rejected).count()
+    }
+
+    fn applied(&self) -> usize {
+        self.reports.iter().filter(|r| r.status == HunkStatus::Applied).count()
+    }
+}
+
+// Execute the patch tool
+pub fn execute(
+    args: &Value,
+    allowed_paths: &AllowedPaths,
+    trash_config: &TrashConfig,
+    locks: &LockManager,
+    journal: &Journal,
+) -> Result<ToolCallResult> {
+    // Extract parameters
+    let patch_text = args.get("patch")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("Missing patch parameter"))?;
+
+    let root_str = args.get("root").and_then(|v| v.as_str());
+    let strip = args.get("strip").and_then(|v| v.as_u64()).map(|v| v as usize);
+
+    let options = ApplyOptions {
+        fuzz: args.get("fuzz").and_then(|v| v.as_u64()).unwrap_or(2) as usize,
+        max_offset: args.get("max_offset").and_then(|v| v.as_u64()).unwrap_or(1000) as usize,
+    };
+
+    let allow_partial = args.get("allow_partial")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let dry_run = args.get("dry_run")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let session = args.get("session").and_then(|v| v.as_str());
+
+    debug!(
+        "Applying patch, root: {:?}, strip: {:?}, options: {:?}, allow_partial: {}, dry_run: {}",
+        root_str, strip, options, allow_partial, dry_run
+    );
+
+    // Parse the patch
+    let file_patches = match patch::parse(patch_text) {
+        Ok(p) => p,
+        Err(e) => return Ok(error_result(format!("Failed to parse patch: {}", e))),
+    };
+    let strip = strip.unwrap_or_else(|| patch::default_strip(&file_patches));
+
+    // Determine the base directory for relative paths
+    let base = match root_str {
+        Some(root) => match allowed_paths.validate_path(Path::new(root)) {
+            Ok(p) if p.is_dir() => p,
+            Ok(_) => return Ok(error_result(format!("Root is not a directory: '{}'", root))),
+            Err(e) => return Ok(error_result(path_error_message(e, root))),
+        },
+        None => match allowed_paths.all_paths().first() {
+            Some(p) => p.clone(),
+            None => return Ok(error_result("No allowed directories configured".to_string())),
+        },
+    };
+
+    // Apply every file patch in memory first
+    let changes: Vec<FileChange> = file_patches
+        .iter()
+        .map(|file_patch| prepare_change(file_patch, strip, &base, allowed_paths, locks, session, &options))
+        .collect();
+
+    let hunks_applied: usize = changes.iter().map(|c| c.applied()).sum();
+    let hunks_rejected: usize = changes.iter().map(|c| c.rejected()).sum();
+    let files_failed = changes.iter().filter(|c| c.error.is_some()).count();
+
+    // Report what would be written
+    if dry_run {
+        let mut plan = Plan::new("patch");
+        for change in &changes {
+            add_to_plan(&mut plan, change, allowed_paths, trash_config);
+        }
+        return Ok(plan.into_result(allowed_paths));
+    }
+
+    // All-or-nothing unless partial application was requested
+    if !allow_partial && (hunks_rejected > 0 || files_failed > 0) {
+        let mut text = format!(
+            "Patch not applied: {} hunks rejected, {} files failed. No files were changed.\n",
+            hunks_rejected, files_failed
+        );
+        for change in &changes {
+            append_report(&mut text, change, None);
+        }
+        text.push_str("Use allow_partial=true to apply the matching hunks anyway.");
+        return Ok(error_result(text));
+    }
+
+    // Write the patched files
+    let mut text = String::new();
+    let mut files_changed = 0;
+    let mut write_failures = 0;
+
+    for change in &changes {
+        if change.error.is_some() || (change.applied() == 0 && !change.reports.is_empty()) {
+            append_report(&mut text, change, Some("skipped"));
+            continue;
+        }
+
+        // Keep the previous contents for undo
+        let target_before = journal.capture(&change.target);
+        let source_before = match (change.kind, &change.source) {
+            (ChangeKind::Rename, Some(source)) => journal.capture(source),
+            _ => None,
+        };
+
+        match commit_change(change, allowed_paths, trash_config) {
+            Ok(note) => {
+                let after = (change.kind != ChangeKind::Delete).then_some(change.output.as_slice());
+                journal.record(&change.target, "patch", target_before, after);
+                if let Some(source) = &change.source {
+                    journal.record(source, "patch", source_before, None);
+                }
+                files_changed += 1;
+                append_report(&mut text, change, note.as_deref());
+            }
+            Err(e) => {
+                write_failures += 1;
+                append_report(&mut text, change, Some(&format!("failed: {}", e)));
+            }
+        }
+    }
+
+    let summary = format!(
+        "Patch applied to {} of {} files ({} hunks applied, {} rejected)\n",
+        files_changed,
+        changes.len(),
+        hunks_applied,
+        hunks_rejected
+    );
+    text.insert_str(0, &summary);
+
+    Ok(ToolCallResult {
+        content: vec![ToolContent::Text { text: text.trim_end().to_string() }],
+        is_error: Some(hunks_rejected > 0 || files_failed > 0 || write_failures > 0),
+    })
+}
+
+// Resolve the paths of a file patch and apply its hunks in memory
+fn prepare_change(
+    file_patch: &FilePatch,
+    strip: usize,
+    base: &Path,
+    allowed_paths: &AllowedPaths,
+    locks: &LockManager,
+    session: Option<&str>,
+    options: &ApplyOptions,
+) -> FileChange {
+    let kind = if file_patch.is_creation() {
+        ChangeKind::Create
+    } else if file_patch.is_deletion() {
+        ChangeKind::Delete
+    } else if file_patch.old_path.as_deref().and_then(|p| patch::strip_path(p, strip))
+        != file_patch.new_path.as_deref().and_then(|p| patch::strip_path(p, strip))
+    {
+        ChangeKind::Rename
+    } else {
+        ChangeKind::Modify
+    };
+
+    let mut change = FileChange {
+        kind,
+        display: file_patch.target_path().unwrap_or_default().to_string(),
+        source: None,
+        target: PathBuf::new(),
+        content: String::new(),
+        output: Vec::new(),
+        reports: Vec::new(),
+        warnings: Vec::new(),
+        error: None,
+    };
+
+    let resolved = (|| -> Result<(), String> {
+        let target_raw = file_patch.target_path().unwrap_or_default();
+        change.target = resolve_path(target_raw, strip, base, allowed_paths)?;
+        change.display = allowed_paths.closest_relative_path(&change.target);
+
+        if kind != ChangeKind::Create {
+            let source_raw = file_patch.old_path.as_deref().unwrap_or_default();
+            let source = resolve_path(source_raw, strip, base, allowed_paths)?;
+            if !source.is_file() {
+                return Err(format!("File not found: '{}'", allowed_paths.closest_relative_path(&source)));
+            }
+            change.source = Some(source);
+        }
+
+        if matches!(kind, ChangeKind::Create | ChangeKind::Rename) && change.target.exists() {
+            return Err(format!("File already exists: '{}'", change.display));
+        }
+
+        for path in change.source.iter().chain(std::iter::once(&change.target)) {
+            locks.check(path, session).map_err(|e| e.to_string())?;
+        }
+
+        // Patch the text as UTF-8 whatever the file's charset, and save it back in that charset
+        let (charset, original) = match &change.source {
+            Some(source) => {
+                let bytes = fs::read(source).map_err(|e| format!("Failed to read '{}': {}", change.display, e))?;
+                let (charset, warning) = Charset::detect_for_edit(&bytes);
+                change.warnings.extend(warning);
+                (charset, charset.decode(&bytes))
+            }
+            None => (Charset::UTF8, String::new()),
+        };
+
+        let applied = patch::apply(&original, &file_patch.hunks, options);
+        change.content = applied.content;
+        change.reports = applied.reports;
+        change.output = charset.encode(&change.content).map_err(|e| e.to_string())?;
+
+        if kind == ChangeKind::Delete && change.rejected() == 0 && !change.content.is_empty() {
+            return Err("File still has content after applying the deletion hunks".to_string());
+        }
+        Ok(())
+    })();
+
+    if let Err(e) = resolved {
+        change.error = Some(e);
+    }
+    change
+}
+
+// Resolve a patch path against the base directory and check it is allowed.
+// Missing parent directories are allowed for new files.
+fn resolve_path(raw: &str, strip: usize, base: &Path, allowed_paths: &AllowedPaths) -> Result<PathBuf, String> {
+    let stripped = patch::strip_path(raw, strip)
+        .ok_or_else(|| format!("Invalid path in patch: '{}'", raw))?;
+    let full = base.join(stripped);
+
+    let mut existing = full.as_path();
+    while !existing.exists() {
+        existing = existing
+            .parent()
+            .ok_or_else(|| format!("Path not found: '{}'", full.display()))?;
+    }
+
+    let validated = allowed_paths
+        .validate_path(existing)
+        .map_err(|e| path_error_message(e, &full.display().to_string()))?;
+    let remainder = full.strip_prefix(existing).unwrap_or(Path::new(""));
+
+    if remainder.as_os_str().is_empty() {
+        Ok(validated)
+    } else {
+        Ok(validated.join(remainder))
+    }
+}
+
+// Carry out an in-memory change, returning a note about how it was done
+fn commit_change(
+    change: &FileChange,
+    allowed_paths: &AllowedPaths,
+    trash_config: &TrashConfig,
+) -> Result<Option<String>, String> {
+    let mut notes = Vec::new();
+
+    match change.kind {
+        ChangeKind::Delete => {
+            let trash_root = allowed_paths
+                .root_for(&change.target)
+                .filter(|root| trash_config.enabled && !trash::is_in_trash(&change.target, root));
+
+            match trash_root {
+                Some(root) => {
+                    let entry = trash::move_to_trash(&change.target, root)
+                        .map_err(|e| format!("Failed to move file to trash: {}", e))?;
+                    if let Err(e) = trash::enforce_retention(root, trash_config) {
+                        warn!("Failed to enforce trash retention: {}", e);
+                    }
+                    notes.push(format!("moved to trash as '{}'", entry.name));
+                }
+                None => fs::remove_file(&change.target).map_err(|e| format!("Failed to delete file: {}", e))?,
+            }
+        }
+        ChangeKind::Create | ChangeKind::Modify | ChangeKind::Rename => {
+            if let Some(parent) = change.target.parent() {
+                fs::create_dir_all(parent).map_err(|e| format!("Failed to create parent directories: {}", e))?;
+            }
+
+            let outcome = if change.kind == ChangeKind::Modify {
+                atomic::write_atomic(&change.target, &change.output)
+            } else {
+                atomic::write_atomic_new(&change.target, &change.output)
+            }
+            .map_err(|e| format!("Failed to write file: {}", e))?;
+            notes.extend(outcome.warnings.into_iter().map(|w| format!("warning: {}", w)));
+
+            if change.kind == ChangeKind::Rename {
+                if let Some(source) = &change.source {
+                    fs::remove_file(source).map_err(|e| format!("Wrote new file but failed to remove the old one: {}", e))?;
+                    notes.push(format!("from '{}'", allowed_paths.closest_relative_path(source)));
+                }
+            }
+        }
+    }
+
+    Ok(if notes.is_empty() { None } else { Some(notes.join(", ")) })
+}
+
+fn add_to_plan(plan: &mut Plan, change: &FileChange, allowed_paths: &AllowedPaths, trash_config: &TrashConfig) {
+    let action = match change.kind {
+        ChangeKind::Create => ActionKind::CreateFile,
+        ChangeKind::Modify => ActionKind::OverwriteFile,
+        ChangeKind::Rename => ActionKind::Move,
+        ChangeKind::Delete => {
+            let trashed = trash_config.enabled
+                && allowed_paths
+                    .root_for(&change.target)
+                    .is_some_and(|root| !trash::is_in_trash(&change.target, root));
+            if trashed { ActionKind::Trash } else { ActionKind::Remove }
+        }
+    };
+
+    if let Some(error) = &change.error {
+        plan.add_conflict(action, &change.target, error.clone());
+        return;
+    }
+
+    let bytes = (change.kind != ChangeKind::Delete).then_some(change.output.len() as u64);
+    let planned = plan.add(action, &change.target, bytes);
+    if change.kind == ChangeKind::Rename {
+        planned.source = change.source.as_ref().map(|s| s.display().to_string());
+    }
+
+    for warning in &change.warnings {
+        plan.add_note(format!("{}: Warning: {}", change.display, warning));
+    }
+    for report in &change.reports {
+        let line = format!("{}: {}", change.display, report.describe());
+        if report.status == HunkStatus::Rejected {
+            plan.add_error(line);
+        } else {
+            plan.add_note(line);
+        }
+    }
+}
+
+fn append_report(text: &mut String, change: &FileChange, note: Option<&str>) {
+    let status = if change.error.is_some() {
+        "failed"
+    } else {
+        change.kind.describe()
+    };
+
+    text.push_str(&format!("{} {}", status, change.display));
+    if let Some(note) = note {
+        text.push_str(&format!(" ({})", note));
+    }
+    text.push('\n');
+
+    if let Some(error) = &change.error {
+        text.push_str(&format!("  Error: {}\n", error));
+    }
+    for warning in &change.warnings {
+        text.push_str(&format!("  Warning: {}\n", warning));
+    }
+    for report in &change.reports {
+        text.push_str(&format!("  {}\n", report.describe()));
+    }
+}
+
+fn path_error_message(error: PathError, path_str: &str) -> String {
+    match error {
+        PathError::OutsideAllowedPaths =>
+            "Path is outside of all allowed directories".to_string(),
+        PathError::NotFound =>
+            format!("Path not found: '{}'", path_str),
+        PathError::IoError(io_err) =>
+            format!("IO error: {}", io_err),
+    }
+}
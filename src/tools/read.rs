@@ -1,23 +1,36 @@
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
 use mcp_protocol::types::tool::{ToolCallResult, ToolContent};
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
+use glob::MatchOptions;
 use std::{
-    fs::File,
-    io::{BufRead, BufReader, Read},
-    path::Path,
+    fs::{self, File},
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
 };
 use tracing::{debug, warn};
 use base64;
 
+use crate::utils::encoding::{self, Charset};
+use crate::utils::media::{self, ImageType};
 use crate::utils::path::{AllowedPaths, is_text_file, PathError};
+use crate::utils::precondition;
+use crate::utils::result::error_result;
+use crate::utils::scan::{self, LineReader};
+use crate::utils::tail::{self, FollowToken, Restart};
+use crate::utils::text;
 
 // Struct representing file metadata
 #[derive(Debug, Serialize, Deserialize)]
 struct FileMetadata {
     path: String,
     modified: Option<String>,
+    hash: Option<String>,
+    format: Option<String>,
+    #[serde(skip)]
+    charset: Option<Charset>,
     size: u64,
+    lines: Option<usize>,
 }
 
 // Define the schema for the tool
@@ -29,37 +42,142 @@ pub fn schema() -> Value {
                 "type": "string",
                 "description": "Path to the file to read (full path or relative to one of the allowed directories)"
             },
+            "paths": {
+                "type": "array",
+                "items": { "type": "string" },
+                "description": "Read several files in one call instead of path: file paths and/or glob patterns (\"src/**/*.rs\"; relative patterns are matched in each allowed directory). Each file is returned as its own content block"
+            },
+            "max_total_size": {
+                "type": "integer",
+                "description": "Combined byte budget for a paths read; files after the budget is reached are skipped and listed",
+                "default": 1048576
+            },
+            "max_files": {
+                "type": "integer",
+                "description": "Maximum number of files read by a paths read",
+                "default": 100
+            },
             "encoding": {
                 "type": "string",
-                "description": "File encoding",
+                "description": "File encoding. With the default, binary files are returned as MCP image content (PNG, JPEG, GIF, WebP, BMP) or as an embedded resource blob; base64 returns them as text instead",
                 "enum": ["utf8", "base64", "binary"],
                 "default": "utf8"
             },
+            "charset": {
+                "type": "string",
+                "description": "Character encoding of the text, e.g. \"utf-8\", \"utf-16le\", \"windows-1252\" or \"shift_jis\"; detected from a byte order mark or the content if omitted. Text is always returned as UTF-8"
+            },
             "start_line": {
                 "type": "integer",
-                "description": "Start line for partial read (0-indexed)"
+                "description": "Start line for partial read (0-based, as used by edit and reported by search and outline)"
             },
             "end_line": {
                 "type": "integer",
-                "description": "End line for partial read (inclusive)"
+                "description": "End line for partial read (0-based, inclusive)"
+            },
+            "line_numbers": {
+                "type": "boolean",
+                "description": "Prefix each line with its 0-based line number and a tab (cat -n style), ready for replace_lines",
+                "default": false
             },
             "max_size": {
                 "type": "integer",
-                "description": "Maximum number of bytes to read",
+                "description": "Maximum number of bytes to read; larger images are scaled down to fit",
                 "default": 1048576
+            },
+            "max_image_dimension": {
+                "type": "integer",
+                "description": "Scale images down, keeping their aspect ratio, so neither side exceeds this many pixels"
+            },
+            "tail_lines": {
+                "type": "integer",
+                "description": "Read only the last N lines, seeking from the end of the file instead of scanning it"
+            },
+            "tail_bytes": {
+                "type": "integer",
+                "description": "Read only the last N bytes of the file"
+            },
+            "follow": {
+                "type": "string",
+                "description": "Follow token returned by a previous tail or follow read: return only the content appended since then (from the start if the file was truncated or rotated)"
+            },
+            "cursor": {
+                "type": "string",
+                "description": "Continue a truncated read: the cursor returned by the previous chunk. Fails if the file changed since"
             }
         },
-        "required": ["path"]
+        "anyOf": [
+            { "required": ["path"] },
+            { "required": ["paths"] }
+        ]
     })
 }
 
+// Options applied to each file read
+#[derive(Clone, Copy)]
+struct ReadOptions<'a> {
+    encoding: &'a str,
+    start_line: Option<usize>,
+    end_line: Option<usize>,
+    line_numbers: bool,
+    max_size: u64,
+    // The server's limit, which images must fit in to be decoded
+    max_file_size: u64,
+    cursor: Option<&'a str>,
+    tail: Option<Tail<'a>>,
+    charset: Option<Charset>,
+    max_image_dimension: Option<u32>,
+}
+
+// Read from the end of a file instead of its start
+#[derive(Clone, Copy)]
+enum Tail<'a> {
+    Lines(usize),
+    Bytes(u64),
+    Follow(&'a str),
+}
+
+// Where a truncated read continues: a byte offset into the file, the 0-based line that
+// offset falls in, and a fingerprint of the file the cursor was issued for
+#[derive(Debug, PartialEq)]
+struct Cursor {
+    offset: usize,
+    line: usize,
+    fingerprint: String,
+}
+
+impl Cursor {
+    // The fingerprint is a prefix of the file's SHA-256 hash
+    fn new(offset: usize, line: usize, hash: Option<&str>) -> Self {
+        Cursor {
+            offset,
+            line,
+            fingerprint: hash.unwrap_or_default().chars().take(16).collect(),
+        }
+    }
+    
+    fn encode(&self) -> String {
+        let raw = format!("{}:{}:{}", self.offset, self.line, self.fingerprint);
+        base64::encode_config(raw, base64::URL_SAFE_NO_PAD)
+    }
+    
+    fn decode(value: &str) -> Option<Self> {
+        let raw = String::from_utf8(base64::decode_config(value.trim(), base64::URL_SAFE_NO_PAD).ok()?).ok()?;
+        let mut parts = raw.splitn(3, ':');
+        Some(Cursor {
+            offset: parts.next()?.parse().ok()?,
+            line: parts.next()?.parse().ok()?,
+            fingerprint: parts.next()?.to_string(),
+        })
+    }
+    
+    fn matches(&self, hash: Option<&str>) -> bool {
+        hash.is_some_and(|hash| hash.starts_with(&self.fingerprint))
+    }
+}
+
 // Execute the read tool
 pub fn execute(args: &Value, allowed_paths: &AllowedPaths, max_file_size: u64) -> Result<ToolCallResult> {
-    // Extract path parameter (required)
-    let path_str = args.get("path")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| anyhow!("Missing path parameter"))?;
-    
     // Extract optional parameters
     let encoding = args.get("encoding")
         .and_then(|v| v.as_str())
@@ -73,6 +191,10 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths, max_file_size: u64) -
         .and_then(|v| v.as_u64())
         .map(|v| v as usize);
     
+    let line_numbers = args.get("line_numbers")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    
     let user_max_size = args.get("max_size")
         .and_then(|v| v.as_u64())
         .unwrap_or(1048576); // Default to 1MB
@@ -80,6 +202,77 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths, max_file_size: u64) -
     // Use the smaller of user-specified and server-configured max size
     let max_size = std::cmp::min(user_max_size, max_file_size);
     
+    let options = ReadOptions {
+        encoding,
+        start_line,
+        end_line,
+        line_numbers,
+        max_size,
+        max_file_size,
+        cursor: args.get("cursor").and_then(|v| v.as_str()),
+        tail: None,
+        charset: match args.get("charset").and_then(|v| v.as_str()).map(Charset::parse) {
+            Some(Ok(charset)) => Some(charset),
+            Some(Err(e)) => return Ok(error_result(e.to_string())),
+            None => None,
+        },
+        max_image_dimension: args.get("max_image_dimension")
+            .and_then(|v| v.as_u64())
+            .map(|v| v.min(u32::MAX as u64) as u32),
+    };
+    
+    // At most one of the tail modes, which read from the end rather than by line or cursor
+    let tails = [
+        args.get("tail_lines").and_then(|v| v.as_u64()).map(|n| Tail::Lines(n as usize)),
+        args.get("tail_bytes").and_then(|v| v.as_u64()).map(Tail::Bytes),
+        args.get("follow").and_then(|v| v.as_str()).map(Tail::Follow),
+    ];
+    let options = match tails.into_iter().flatten().collect::<Vec<_>>().as_slice() {
+        [] => options,
+        [tail] if start_line.is_none() && end_line.is_none() && options.cursor.is_none() && !line_numbers => {
+            ReadOptions { tail: Some(*tail), ..options }
+        }
+        [_] => {
+            return Ok(error_result(
+                "tail_lines, tail_bytes and follow cannot be combined with start_line, end_line, cursor or line_numbers".to_string(),
+            ));
+        }
+        _ => return Ok(error_result("Use only one of tail_lines, tail_bytes and follow".to_string())),
+    };
+    
+    // Several files and globs, each in its own content block
+    if let Some(paths) = args.get("paths") {
+        if options.cursor.is_some() || matches!(options.tail, Some(Tail::Follow(_))) {
+            return Ok(error_result("cursor and follow continue a single file: use them with path, not paths".to_string()));
+        }
+        
+        let Some(patterns) = paths.as_array().map(|items| items.iter().filter_map(|v| v.as_str()).collect::<Vec<_>>()) else {
+            return Ok(error_result("paths must be an array of strings".to_string()));
+        };
+        
+        let max_total_size = args.get("max_total_size")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(1048576);
+        
+        let max_files = args.get("max_files")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(100) as usize;
+        
+        return read_batch(&patterns, options, max_total_size, max_files, allowed_paths);
+    }
+    
+    // Extract path parameter
+    let path_str = args.get("path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("Missing path or paths parameter"))?;
+    
+    read_path(path_str, options, allowed_paths)
+}
+
+// Read a single file
+fn read_path(path_str: &str, options: ReadOptions, allowed_paths: &AllowedPaths) -> Result<ToolCallResult> {
+    let ReadOptions { encoding, start_line, end_line, line_numbers, max_size, max_file_size, cursor, tail, charset, max_image_dimension } = options;
+    
     debug!(
         "Reading file: '{}', encoding: '{}', start_line: {:?}, end_line: {:?}, max_size: {}",
         path_str, encoding, start_line, end_line, max_size
@@ -150,9 +343,10 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths, max_file_size: u64) -
         }
     };
     
-    // For binary files, enforce base64 encoding
-    let actual_encoding = if !is_text && encoding == "utf8" {
-        warn!("Binary file detected, forcing base64 encoding");
+    // Binary files are returned as images or resources, unless the caller named the text's charset
+    let as_media = !is_text && encoding == "utf8" && charset.is_none();
+    let actual_encoding = if as_media {
+        debug!("Binary file detected, returning it as image or resource content");
         "base64"
     } else {
         encoding
@@ -165,37 +359,100 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths, max_file_size: u64) -
             .into()
     });
     
-    // Prepare metadata structure
+    // Tails only read the end of the file, so the whole-file hash and line count are left out
+    if let Some(tail) = tail {
+        if !is_text && charset.is_none() {
+            return Ok(error_result(format!(
+                "tail_lines, tail_bytes and follow only support text files: '{}'",
+                path_str
+            )));
+        }
+        return read_tail(&validated_path, path_str, tail, charset, max_size, modified_time);
+    }
+    
+    // Text is scanned once in fixed-size blocks for the hash, charset, format and line count;
+    // only the requested part of it is then read into memory
+    let text_scan = if actual_encoding == "utf8" {
+        Some(scan::scan_text(&validated_path, charset).context("Failed to read file")?)
+    } else {
+        None
+    };
+    
+    // Hash the full content so callers can pass it back as a write precondition
+    let content_hash = match &text_scan {
+        Some(text_scan) => Some(text_scan.hash.clone()),
+        None => match precondition::hash_file(&validated_path) {
+            Ok(hash) => Some(hash),
+            Err(e) => {
+                warn!("Failed to hash '{}': {}", validated_path.display(), e);
+                None
+            }
+        },
+    };
+    
+    // Prepare metadata structure, with the line endings, BOM and trailing newline so edits can keep them
     let file_metadata = FileMetadata {
         path: path_str.to_string(),
         modified: modified_time,
+        hash: content_hash,
+        format: text_scan.as_ref().map(|text_scan| text_scan.format.describe()),
+        charset: text_scan.as_ref().map(|text_scan| text_scan.charset),
         size: file_size,
+        lines: text_scan.as_ref().map(|text_scan| text_scan.lines),
+    };
+    
+    // A cursor continues a read of the same file contents
+    let cursor = match cursor.map(Cursor::decode) {
+        Some(Some(cursor)) if !cursor.matches(file_metadata.hash.as_deref()) => {
+            return Ok(error_result(format!(
+                "File '{}' changed since the cursor was issued; read it again from the start",
+                path_str
+            )));
+        }
+        Some(Some(cursor)) => Some(cursor),
+        Some(None) => return Ok(error_result("Invalid cursor".to_string())),
+        None => None,
     };
     
     // Handle different read modes
-    match actual_encoding {
-        "utf8" => {
+    match (actual_encoding, file_metadata.charset) {
+        ("utf8", Some(text_charset)) => {
+            // Continue from the cursor if given, transcoding the text to UTF-8 from its charset
+            let (offset, first_line) = cursor.as_ref().map_or((0, 0), |cursor| (cursor.offset as u64, cursor.line));
+            if offset > file_size {
+                return Ok(error_result("Invalid cursor".to_string()));
+            }
+            let mut file = File::open(&validated_path).context("Failed to read file")?;
+            file.seek(SeekFrom::Start(offset))?;
+            let window = TextWindow { file, charset: text_charset, offset, first_line };
+            
             // If line range is specified, use line-based reading
             if start_line.is_some() || end_line.is_some() {
                 read_text_lines(
-                    &validated_path,
-                    start_line,
+                    window,
+                    cursor.map_or(start_line, |cursor| Some(cursor.line)),
                     end_line,
                     max_size,
+                    line_numbers,
                     file_metadata,
                 )
             } else {
                 // Otherwise read the entire file (up to max_size)
-                read_text_file(&validated_path, max_size, file_metadata)
+                read_text_file(window, max_size, line_numbers, file_metadata)
             }
         }
-        "base64" | "binary" => {
-            read_binary_file(&validated_path, max_size, file_metadata)
+        ("base64" | "binary", _) => {
+            let offset = cursor.map_or(0, |cursor| cursor.offset);
+            if as_media {
+                read_media(&validated_path, offset as u64, max_size, max_file_size, max_image_dimension, file_metadata)
+            } else {
+                read_binary_file(&validated_path, offset as u64, max_size, file_metadata)
+            }
         }
         _ => {
             Ok(ToolCallResult {
                 content: vec![ToolContent::Text {
-                    text: format!("Unsupported encoding: '{}'", actual_encoding),
+                    text: format!("Unsupported encoding: '{}'", encoding),
                 }],
                 is_error: Some(true),
             })
@@ -203,82 +460,280 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths, max_file_size: u64) -
     }
 }
 
-// Read a text file line by line
+// Read the files named or matched by `patterns` until the byte budget is used up
+fn read_batch(
+    patterns: &[&str],
+    options: ReadOptions,
+    max_total_size: u64,
+    max_files: usize,
+    allowed_paths: &AllowedPaths,
+) -> Result<ToolCallResult> {
+    debug!(
+        "Batch read of {} paths, max_total_size: {}, max_files: {}",
+        patterns.len(), max_total_size, max_files
+    );
+    
+    // Expand globs, keeping the order of the patterns and dropping duplicates
+    let mut files: Vec<String> = Vec::new();
+    let mut blocks = Vec::new();
+    for pattern in patterns {
+        if !pattern.contains(['*', '?', '[']) {
+            for path in resolve_literal(pattern, allowed_paths) {
+                if !files.contains(&path) {
+                    files.push(path);
+                }
+            }
+            continue;
+        }
+        match expand_glob(pattern, allowed_paths) {
+            Ok(matches) if matches.is_empty() => blocks.push(ToolContent::Text {
+                text: format!("Error: no files match '{}'", pattern),
+            }),
+            Ok(matches) => {
+                for path in matches {
+                    if !files.contains(&path) {
+                        files.push(path);
+                    }
+                }
+            }
+            Err(e) => blocks.push(ToolContent::Text {
+                text: format!("Error: {}", e),
+            }),
+        }
+    }
+    
+    let not_read = files.split_off(files.len().min(max_files));
+    
+    let mut used: u64 = 0;
+    let mut read_count = 0;
+    let mut error_count = blocks.len();
+    let mut skipped = Vec::new();
+    for (index, path_str) in files.iter().enumerate() {
+        // Stop at the budget rather than truncate a file, unless nothing was read yet
+        let remaining = max_total_size.saturating_sub(used);
+        let size = allowed_paths.validate_path(Path::new(path_str))
+            .ok()
+            .and_then(|path| fs::metadata(path).ok())
+            .map_or(0, |m| m.len());
+        let whole_file = options.start_line.is_none() && options.end_line.is_none() && options.tail.is_none();
+        if remaining == 0 || (read_count > 0 && whole_file && size > remaining.min(options.max_size)) {
+            skipped.extend(files[index..].iter().cloned());
+            break;
+        }
+        
+        let file_options = ReadOptions {
+            max_size: options.max_size.min(remaining),
+            ..options
+        };
+        let result = read_path(path_str, file_options, allowed_paths)
+            .unwrap_or_else(|e| error_result(e.to_string()));
+        let failed = result.is_error == Some(true);
+        for content in result.content {
+            match content {
+                ToolContent::Text { text } => {
+                    used += text.len() as u64;
+                    let text = if failed { format!("Error reading '{}': {}", path_str, text) } else { text };
+                    blocks.push(ToolContent::Text { text });
+                }
+                ToolContent::Image { data, mime_type } => {
+                    used += data.len() as u64;
+                    blocks.push(ToolContent::Image { data, mime_type });
+                }
+                ToolContent::Resource { resource } => {
+                    used += resource.get("blob").and_then(|v| v.as_str()).map_or(0, |blob| blob.len() as u64);
+                    blocks.push(ToolContent::Resource { resource });
+                }
+                _ => {}
+            }
+        }
+        if failed {
+            error_count += 1;
+        } else {
+            read_count += 1;
+        }
+    }
+    
+    // Summary first, then one block per file
+    let mut summary = format!(
+        "Batch read: {} files read, {} errors, {} bytes of {} budget used\n",
+        read_count, error_count, used, max_total_size
+    );
+    if !skipped.is_empty() {
+        summary.push_str(&format!("Skipped (byte budget reached): {}\n", skipped.len()));
+        for path in &skipped {
+            summary.push_str(&format!("  - {}\n", path));
+        }
+    }
+    if !not_read.is_empty() {
+        summary.push_str(&format!("Not read (more than max_files = {}): {}\n", max_files, not_read.len()));
+        for path in &not_read {
+            summary.push_str(&format!("  - {}\n", path));
+        }
+    }
+    blocks.insert(0, ToolContent::Text { text: summary.trim_end().to_string() });
+    
+    Ok(ToolCallResult {
+        content: blocks,
+        is_error: Some(read_count == 0),
+    })
+}
+
+// The paths a literal path names: a relative path is looked up in every allowed directory,
+// like a glob, and left under the first one if it exists in none (so reading it reports why)
+fn resolve_literal(path_str: &str, allowed_paths: &AllowedPaths) -> Vec<String> {
+    let path = Path::new(path_str);
+    if path.is_absolute() {
+        return vec![path_str.to_string()];
+    }
+    let candidates: Vec<PathBuf> = allowed_paths.all_paths().iter().map(|root| root.join(path)).collect();
+    let existing: Vec<String> = candidates.iter()
+        .filter(|candidate| candidate.exists())
+        .map(|candidate| candidate.to_string_lossy().to_string())
+        .collect();
+    if !existing.is_empty() {
+        return existing;
+    }
+    candidates.first().map_or_else(|| vec![path_str.to_string()], |first| vec![first.to_string_lossy().to_string()])
+}
+
+// Files matching a glob pattern, sorted; a relative pattern is matched in every allowed directory
+fn expand_glob(pattern: &str, allowed_paths: &AllowedPaths) -> Result<Vec<String>> {
+    let full_patterns: Vec<PathBuf> = if Path::new(pattern).is_absolute() {
+        // Only walk from a directory that is allowed
+        let literal_prefix: PathBuf = Path::new(pattern)
+            .components()
+            .take_while(|c| !c.as_os_str().to_string_lossy().contains(['*', '?', '[']))
+            .collect();
+        allowed_paths
+            .validate_path(&literal_prefix)
+            .map_err(|e| anyhow!("Cannot expand '{}': {}", pattern, e))?;
+        vec![PathBuf::from(pattern)]
+    } else {
+        allowed_paths.all_paths().iter().map(|root| root.join(pattern)).collect()
+    };
+    
+    // Hidden files and directories, including the trash, only match a literal leading dot
+    let match_options = MatchOptions {
+        require_literal_leading_dot: true,
+        ..MatchOptions::new()
+    };
+    
+    let mut matches = Vec::new();
+    for full_pattern in full_patterns {
+        let entries = glob::glob_with(&full_pattern.to_string_lossy(), match_options)
+            .map_err(|e| anyhow!("Invalid glob pattern '{}': {}", pattern, e))?;
+        for path in entries.flatten() {
+            if path.is_file() && allowed_paths.validate_path(&path).is_ok() {
+                matches.push(path.to_string_lossy().to_string());
+            }
+        }
+    }
+    matches.sort();
+    matches.dedup();
+    Ok(matches)
+}
+
+// An open text file positioned at a line start
+struct TextWindow {
+    file: File,
+    charset: Charset,
+    // Byte offset of the file position, and the 0-based line starting there
+    offset: u64,
+    first_line: usize,
+}
+
+// Read a range of lines of a text file, holding at most max_size bytes of it in memory
 fn read_text_lines(
-    path: &Path,
+    window: TextWindow,
     start_line: Option<usize>,
     end_line: Option<usize>,
     max_size: u64,
+    line_numbers: bool,
     metadata: FileMetadata,
 ) -> Result<ToolCallResult> {
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
-    
     let start = start_line.unwrap_or(0);
     let end = end_line.unwrap_or(usize::MAX);
+    let total_lines = metadata.lines.unwrap_or(0);
     
-    let mut content = String::new();
-    let mut line_count = 0;
-    let mut byte_count = 0;
-    let mut truncated = false;
+    let TextWindow { file, charset, mut offset, first_line } = window;
+    let mut reader = LineReader::new(file, charset);
+    let mut index = first_line;
+    let mut line = Vec::new();
     
-    // Read lines and filter based on range
-    for (i, line_result) in reader.lines().enumerate() {
-        line_count += 1;
-        
-        // Skip lines before start
-        if i < start {
-            continue;
+    // Skip lines before start without keeping them
+    while index < start {
+        let len = reader.next_line(&mut line, 0)?;
+        if len == 0 {
+            break;
         }
-        
-        // Stop after end line
-        if i > end {
+        offset += len;
+        index += 1;
+    }
+    
+    let mut selected = Vec::new();
+    let mut last_line = None;
+    let mut byte_count = 0;
+    // Line and offset the next chunk starts at, if the range was cut short
+    let mut next = None;
+    
+    // Keep the lines in range, up to max_size
+    while index <= end {
+        line.clear();
+        let remaining = max_size - byte_count;
+        let len = reader.next_line(&mut line, usize::try_from(remaining).unwrap_or(usize::MAX))?;
+        if len == 0 {
             break;
         }
         
-        // Read the line
-        let line = line_result?;
-        
         // Check if adding this line would exceed max_size
-        let line_bytes = line.len() + 1; // +1 for newline
-        if byte_count + line_bytes as u64 > max_size {
-            truncated = true;
+        if byte_count + len > max_size {
+            next = Some((index, offset));
             break;
         }
         
-        // Add line to content
-        content.push_str(&line);
-        content.push('\n');
-        byte_count += line_bytes as u64;
+        selected.extend_from_slice(&line);
+        byte_count += len;
+        last_line = Some(index);
+        offset += len;
+        index += 1;
     }
     
-    // Format result text
-    let mut result = format!("File: {}\n", metadata.path);
-    
-    if let Some(modified) = metadata.modified {
-        result.push_str(&format!("Modified: {}\n", modified));
+    // Each line ends with a single "\n" in the output
+    let mut content = String::new();
+    for raw_line in charset.decode(&selected).split_inclusive('\n') {
+        let line = raw_line.strip_suffix('\n').unwrap_or(raw_line);
+        content.push_str(line.strip_suffix('\r').unwrap_or(line));
+        content.push('\n');
     }
     
-    result.push_str(&format!("Size: {} bytes\n", metadata.size));
+    // Format result text
+    let mut result = header(&metadata);
     
-    if truncated {
-        result.push_str("Note: File was truncated due to size limit\n");
+    match last_line {
+        Some(last_line) => result.push_str(&format!(
+            "Lines: {}-{} of {} (0-based, inclusive)\n",
+            start, last_line, total_lines
+        )),
+        None => result.push_str(&format!(
+            "Lines: none in range (the file has {} lines, numbered from 0)\n",
+            total_lines
+        )),
     }
     
-    if let Some(start_line) = start_line {
-        if let Some(end_line) = end_line {
-            result.push_str(&format!("Lines: {}-{} of {}\n", start_line, end_line, line_count));
+    if let Some((next_line, next_offset)) = next {
+        let cursor = Cursor::new(next_offset as usize, next_line, metadata.hash.as_deref());
+        if last_line.is_none() {
+            result.push_str(&format!(
+                "Note: Line {} is longer than max_size; pass the cursor without a line range to read it in chunks\n",
+                next_line
+            ));
         } else {
-            result.push_str(&format!("Lines: {} to end of {} total\n", start_line, line_count));
+            result.push_str("Note: File was truncated due to size limit\n");
         }
-    } else if let Some(end_line) = end_line {
-        result.push_str(&format!("Lines: 0-{} of {}\n", end_line, line_count));
-    } else {
-        result.push_str(&format!("Total lines: {}\n", line_count));
+        result.push_str(&format!("Cursor: {} (pass as cursor, with the same end_line, to continue at line {})\n", cursor.encode(), next_line));
     }
     
-    result.push_str("\n----- File Content -----\n\n");
-    result.push_str(&content);
+    push_content(&mut result, &content, start, line_numbers);
     
     Ok(ToolCallResult {
         content: vec![ToolContent::Text {
@@ -288,42 +743,132 @@ fn read_text_lines(
     })
 }
 
-// Read a text file up to max_size
+// Read a text file from the window's offset up to max_size
 fn read_text_file(
-    path: &Path,
+    window: TextWindow,
     max_size: u64,
+    line_numbers: bool,
     metadata: FileMetadata,
 ) -> Result<ToolCallResult> {
-    let file = File::open(path)?;
+    let TextWindow { file, charset, offset, first_line } = window;
     
-    // Determine how much to read
-    let file_size = metadata.size;
-    let bytes_to_read = std::cmp::min(file_size, max_size) as usize;
-    let truncated = file_size > max_size;
+    // Read up to max_size bytes, and a few more to finish a character if no line fits
+    let mut bytes = Vec::new();
+    file.take(max_size.saturating_add(4)).read_to_end(&mut bytes)?;
     
-    // Read file content
-    let mut content = String::with_capacity(bytes_to_read);
-    file.take(max_size).read_to_string(&mut content)?;
+    // Cut the content at max_size: after the last whole line, or on a character boundary if no line fits
+    let truncated = bytes.len() as u64 > max_size;
+    let mut cut = bytes.len();
+    if truncated {
+        let newline = scan::newline(charset);
+        cut = scan::char_boundary(&bytes[..max_size as usize], charset);
+        if let Some(eol) = bytes[..cut].chunks_exact(newline.len()).rposition(|unit| unit == newline) {
+            cut = (eol + 1) * newline.len();
+        }
+        if cut == 0 {
+            cut = (1..=bytes.len())
+                .find(|&len| scan::char_boundary(&bytes[..len], charset) > 0)
+                .unwrap_or(bytes.len());
+        }
+    }
+    let chunk = charset.decode(&bytes[..cut]);
+    let chunk = chunk.as_str();
+    
+    // Format result text
+    let mut result = header(&metadata);
+    
+    result.push_str(&format!("Total lines: {}\n", metadata.lines.unwrap_or(0)));
+    
+    if offset > 0 {
+        result.push_str(&format!("Continuing at line {} (offset {})\n", first_line, offset));
+    }
+    
+    if truncated {
+        let cursor = Cursor::new(offset as usize + cut, first_line + chunk.matches('\n').count(), metadata.hash.as_deref());
+        result.push_str(&format!(
+            "Note: File was truncated due to size limit; the content ends in line {}\n",
+            first_line + chunk.lines().count().saturating_sub(1)
+        ));
+        result.push_str(&format!("Cursor: {} (pass as cursor to read the next chunk)\n", cursor.encode()));
+    }
     
-    // Count lines
-    let line_count = content.lines().count();
+    push_content(&mut result, chunk, first_line, line_numbers);
+    
+    Ok(ToolCallResult {
+        content: vec![ToolContent::Text {
+            text: result,
+        }],
+        is_error: Some(false),
+    })
+}
+
+// Read the end of a file, or the content appended since a follow token, seeking past the rest
+fn read_tail(
+    path: &Path,
+    path_str: &str,
+    mode: Tail,
+    charset: Option<Charset>,
+    max_size: u64,
+    modified: Option<String>,
+) -> Result<ToolCallResult> {
+    let mut file = File::open(path)?;
+    let metadata = file.metadata()?;
+    let size = metadata.len();
+    
+    // Seeking to line starts needs an ASCII-compatible charset, detected once from the start
+    let mut sample = Vec::new();
+    (&mut file).take(encoding::SAMPLE_LEN as u64).read_to_end(&mut sample)?;
+    let charset = charset.unwrap_or_else(|| Charset::detect(&sample));
+    if charset.is_utf16() {
+        return Ok(error_result(format!(
+            "tail_lines, tail_bytes and follow do not support UTF-16 files: '{}'",
+            path_str
+        )));
+    }
+    
+    let (chunk, restart) = match mode {
+        Tail::Lines(lines) => (tail::tail_lines(&mut file, size, lines, max_size, charset.is_utf8())?, None),
+        Tail::Bytes(bytes) => (tail::tail_bytes(&mut file, size, bytes, max_size, charset.is_utf8())?, None),
+        Tail::Follow(token) => match FollowToken::decode(token) {
+            Some(token) => tail::follow(&mut file, &metadata, &token, max_size, charset.is_utf8())?,
+            None => return Ok(error_result("Invalid follow token".to_string())),
+        },
+    };
+    let next = FollowToken::new(&mut file, &metadata, chunk.end)?;
     
     // Format result text
-    let mut result = format!("File: {}\n", metadata.path);
+    let mut result = format!("File: {}\n", path_str);
     
-    if let Some(modified) = metadata.modified {
+    if let Some(modified) = modified {
         result.push_str(&format!("Modified: {}\n", modified));
     }
     
-    result.push_str(&format!("Size: {} bytes\n", metadata.size));
-    result.push_str(&format!("Total lines: {}\n", line_count));
+    result.push_str(&format!("Size: {} bytes\n", size));
+    result.push_str(&format!("Range: bytes {}-{} of {}\n", chunk.start, chunk.end, size));
     
-    if truncated {
-        result.push_str("Note: File was truncated due to size limit\n");
+    match restart {
+        Some(Restart::Truncated) => result.push_str("Note: The file was truncated since the last read; reading from the start\n"),
+        Some(Restart::Rotated) => result.push_str("Note: The file was rotated or replaced since the last read; reading the new file from the start\n"),
+        None => {}
+    }
+    
+    if chunk.truncated {
+        match mode {
+            Tail::Follow(_) => result.push_str("Note: More content follows than fits in max_size; follow again to continue\n"),
+            _ => result.push_str("Note: Only the end of the requested range fits in max_size\n"),
+        }
+    } else if matches!(mode, Tail::Follow(_)) && chunk.data.is_empty() {
+        result.push_str("No new content\n");
+    }
+    
+    result.push_str(&format!("Follow: {} (pass as follow to read content appended after this point)\n", next.encode()));
+    
+    if !charset.is_utf8() {
+        result.push_str(&format!("Charset: {} (transcoded to UTF-8)\n", charset));
     }
     
     result.push_str("\n----- File Content -----\n\n");
-    result.push_str(&content);
+    result.push_str(&charset.decode(&chunk.data));
     
     Ok(ToolCallResult {
         content: vec![ToolContent::Text {
@@ -333,23 +878,61 @@ fn read_text_file(
     })
 }
 
+// File, modification time, hash, size and format lines shared by the text and media read modes
+fn header(metadata: &FileMetadata) -> String {
+    let mut result = format!("File: {}\n", metadata.path);
+    
+    if let Some(modified) = &metadata.modified {
+        result.push_str(&format!("Modified: {}\n", modified));
+    }
+    
+    if let Some(hash) = &metadata.hash {
+        result.push_str(&format!("Hash: {}\n", hash));
+    }
+    
+    result.push_str(&format!("Size: {} bytes\n", metadata.size));
+    
+    if let Some(format) = &metadata.format {
+        result.push_str(&format!("{}\n", format));
+    }
+    
+    match &metadata.charset {
+        Some(charset) if charset.is_utf8() => result.push_str("Charset: UTF-8\n"),
+        Some(charset) => result.push_str(&format!("Charset: {} (transcoded to UTF-8)\n", charset)),
+        None => {}
+    }
+    
+    result
+}
+
+// Append the content after its banner, numbering the lines from `first_line` if requested
+fn push_content(result: &mut String, content: &str, first_line: usize, line_numbers: bool) {
+    if line_numbers {
+        result.push_str("\n----- File Content (0-based line numbers) -----\n\n");
+        result.push_str(&text::number_lines(content, first_line));
+    } else {
+        result.push_str("\n----- File Content -----\n\n");
+        result.push_str(content);
+    }
+}
+
 // Read a binary file up to max_size and encode as base64
 fn read_binary_file(
     path: &Path,
+    offset: u64,
     max_size: u64,
     metadata: FileMetadata,
 ) -> Result<ToolCallResult> {
     let mut file = File::open(path)?;
-    
-    // Determine how much to read
-    let file_size = metadata.size;
-    let bytes_to_read = std::cmp::min(file_size, max_size) as usize;
-    let truncated = file_size > max_size;
+    if offset > metadata.size {
+        return Ok(error_result("Invalid cursor".to_string()));
+    }
+    file.seek(SeekFrom::Start(offset))?;
     
     // Read file content
-    let mut buffer = vec![0; bytes_to_read];
-    let bytes_read = file.read(&mut buffer)?;
-    buffer.truncate(bytes_read);
+    let mut buffer = Vec::new();
+    let bytes_read = file.take(max_size).read_to_end(&mut buffer)?;
+    let truncated = offset + (bytes_read as u64) < metadata.size;
     
     // Encode as base64
     let content = base64::encode(&buffer);
@@ -357,16 +940,25 @@ fn read_binary_file(
     // Format result text
     let mut result = format!("File: {}\n", metadata.path);
     
-    if let Some(modified) = metadata.modified {
+    if let Some(modified) = &metadata.modified {
         result.push_str(&format!("Modified: {}\n", modified));
     }
     
+    if let Some(hash) = &metadata.hash {
+        result.push_str(&format!("Hash: {}\n", hash));
+    }
+    
     result.push_str(&format!("Size: {} bytes\n", metadata.size));
+    if offset > 0 {
+        result.push_str(&format!("Offset: {}\n", offset));
+    }
     result.push_str(&format!("Bytes read: {}\n", bytes_read));
     result.push_str("Encoding: base64\n");
     
     if truncated {
+        let cursor = Cursor::new(offset as usize + bytes_read, 0, metadata.hash.as_deref());
         result.push_str("Note: File was truncated due to size limit\n");
+        result.push_str(&format!("Cursor: {} (pass as cursor to read the next chunk)\n", cursor.encode()));
     }
     
     result.push_str("\n----- Base64 Encoded Content -----\n\n");
@@ -379,3 +971,269 @@ fn read_binary_file(
         is_error: Some(false),
     })
 }
+
+// Return an image as image content, scaled down if it does not fit, and any other binary
+// file as an embedded resource blob with the MIME type detected from its magic bytes
+fn read_media(
+    path: &Path,
+    offset: u64,
+    max_size: u64,
+    max_file_size: u64,
+    max_image_dimension: Option<u32>,
+    metadata: FileMetadata,
+) -> Result<ToolCallResult> {
+    let mut file = File::open(path)?;
+    if offset > metadata.size {
+        return Ok(error_result("Invalid cursor".to_string()));
+    }
+    
+    let mut magic = Vec::new();
+    (&mut file).take(16).read_to_end(&mut magic)?;
+    
+    // Images are returned whole, so a cursor only continues a resource read. An image is only
+    // loaded to be decoded if it fits the server's limit; a larger one is read in chunks
+    let image_type = ImageType::detect(&magic).filter(|_| offset == 0);
+    let too_large = image_type.is_some() && metadata.size > max_file_size;
+    if let Some(image_type) = image_type.filter(|_| !too_large) {
+        let bytes = fs::read(path)?;
+        match media::fit_image(&bytes, image_type, max_image_dimension, max_size) {
+            Ok(image) => {
+                let mut result = header(&metadata);
+                result.push_str(&format!(
+                    "Image: {}, {}x{}\n",
+                    image_type.mime_type(), image.original_width, image.original_height
+                ));
+                if image.scaled() {
+                    result.push_str(&format!(
+                        "Scaled to: {}x{} ({}, {} bytes) to fit max_size or max_image_dimension\n",
+                        image.width, image.height, image.image_type.mime_type(), image.data.len()
+                    ));
+                }
+                if image.data.len() as u64 > max_size {
+                    result.push_str(&format!(
+                        "Note: The image is {} bytes even at its smallest size, more than max_size ({} bytes)\n",
+                        image.data.len(), max_size
+                    ));
+                }
+                return Ok(ToolCallResult {
+                    content: vec![
+                        ToolContent::Text { text: result },
+                        ToolContent::Image {
+                            data: base64::encode(&image.data),
+                            mime_type: image.image_type.mime_type().to_string(),
+                        },
+                    ],
+                    is_error: Some(false),
+                });
+            }
+            Err(e) => warn!("Failed to decode image '{}', returning it as a resource: {}", path.display(), e),
+        }
+    }
+    
+    // Read file content
+    file.seek(SeekFrom::Start(offset))?;
+    let mut buffer = Vec::new();
+    let bytes_read = file.take(max_size).read_to_end(&mut buffer)?;
+    let truncated = offset + (bytes_read as u64) < metadata.size;
+    let mime_type = media::mime_type(&magic);
+    
+    let mut result = header(&metadata);
+    if offset > 0 {
+        result.push_str(&format!("Offset: {}\n", offset));
+    }
+    result.push_str(&format!("Bytes read: {}\n", bytes_read));
+    result.push_str(&format!("MIME type: {}\n", mime_type));
+    if too_large {
+        result.push_str(&format!(
+            "Note: The image is larger than the server's max file size ({} bytes), so it is returned as a resource\n",
+            max_file_size
+        ));
+    }
+    
+    if truncated {
+        let cursor = Cursor::new(offset as usize + bytes_read, 0, metadata.hash.as_deref());
+        result.push_str("Note: File was truncated due to size limit\n");
+        result.push_str(&format!("Cursor: {} (pass as cursor to read the next chunk)\n", cursor.encode()));
+    }
+    
+    Ok(ToolCallResult {
+        content: vec![
+            ToolContent::Text { text: result },
+            ToolContent::Resource {
+                resource: json!({
+                    "uri": format!("file://{}", path.display()),
+                    "mimeType": mime_type,
+                    "blob": base64::encode(&buffer),
+                }),
+            },
+        ],
+        is_error: Some(false),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    fn texts(result: &ToolCallResult) -> Vec<&str> {
+        result.content.iter().filter_map(|c| match c {
+            ToolContent::Text { text } => Some(text.as_str()),
+            _ => None,
+        }).collect()
+    }
+
+    // The cursor and content of a text read
+    fn page(result: &ToolCallResult) -> (Option<String>, String) {
+        let text = texts(result)[0];
+        let cursor = text.lines()
+            .find_map(|line| line.strip_prefix("Cursor: "))
+            .map(|rest| rest.split(' ').next().unwrap().to_string());
+        let content = text.split_once("----- File Content -----\n\n").unwrap().1.to_string();
+        (cursor, content)
+    }
+
+    #[test]
+    fn test_cursor_pagination() {
+        let temp_dir = tempdir().unwrap();
+        let allowed_paths = AllowedPaths::new(vec![temp_dir.path().to_path_buf()]).unwrap();
+        let path = temp_dir.path().join("long.txt");
+        let content: String = (0..50).map(|i| format!("line {}\n", i)).collect();
+        fs::write(&path, &content).unwrap();
+
+        // Chunks end at line boundaries and add up to the whole file
+        let mut pages = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let args = json!({ "path": path, "max_size": 64, "cursor": cursor });
+            let result = execute(&args, &allowed_paths, 1 << 20).unwrap();
+            assert_eq!(result.is_error, Some(false));
+            let (next, chunk) = page(&result);
+            assert!(chunk.ends_with('\n'));
+            pages.push(chunk);
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
+        assert!(pages.len() > 1);
+        assert_eq!(pages.concat(), content);
+
+        // A cursor is refused once the file has changed
+        let args = json!({ "path": path, "max_size": 64 });
+        let (cursor, _) = page(&execute(&args, &allowed_paths, 1 << 20).unwrap());
+        fs::write(&path, "changed\n").unwrap();
+        let args = json!({ "path": path, "max_size": 64, "cursor": cursor });
+        let result = execute(&args, &allowed_paths, 1 << 20).unwrap();
+        assert_eq!(result.is_error, Some(true));
+        assert!(texts(&result)[0].contains("changed since the cursor was issued"));
+    }
+
+    #[test]
+    fn test_batch_read_with_globs_and_budget() {
+        let temp_dir = tempdir().unwrap();
+        let allowed_paths = AllowedPaths::new(vec![temp_dir.path().to_path_buf()]).unwrap();
+        let root = allowed_paths.all_paths()[0].clone();
+
+        fs::create_dir(root.join("src")).unwrap();
+        fs::write(root.join("src/a.rs"), "fn a() {}\n").unwrap();
+        fs::write(root.join("src/b.rs"), "fn b() {}\n").unwrap();
+        fs::write(root.join("big.txt"), "x".repeat(4000)).unwrap();
+
+        // Globs expand in order, and a missing file is reported in its own block
+        let args = json!({ "paths": ["src/*.rs", root.join("missing.txt")] });
+        let result = execute(&args, &allowed_paths, 1 << 20).unwrap();
+        let blocks = texts(&result);
+        assert_eq!(result.is_error, Some(false));
+        assert_eq!(blocks.len(), 4);
+        assert!(blocks[0].starts_with("Batch read: 2 files read, 1 errors"));
+        assert!(blocks[1].contains("fn a() {}"));
+        assert!(blocks[2].contains("fn b() {}"));
+        assert!(blocks[3].starts_with("Error reading"));
+
+        // Files that no longer fit in the budget are skipped and listed
+        let paths = ["src/a.rs", "big.txt", "src/b.rs"].map(|p| root.join(p));
+        let args = json!({ "paths": paths, "max_total_size": 1000 });
+        let result = execute(&args, &allowed_paths, 1 << 20).unwrap();
+        let blocks = texts(&result);
+        assert_eq!(blocks.len(), 2);
+        assert!(blocks[0].contains("Skipped (byte budget reached): 2"));
+        assert!(blocks[0].contains("big.txt"));
+    }
+
+    #[test]
+    fn test_batch_read_relative_literal_paths() {
+        let temp_dir = tempdir().unwrap();
+        let allowed_paths = AllowedPaths::new(vec![temp_dir.path().to_path_buf()]).unwrap();
+        let root = allowed_paths.all_paths()[0].clone();
+
+        fs::create_dir(root.join("src")).unwrap();
+        fs::write(root.join("src/a.rs"), "fn a() {}\n").unwrap();
+        fs::write(root.join("big.txt"), "x".repeat(4000)).unwrap();
+
+        // Relative paths are found under the allowed directory, not the working directory
+        let args = json!({ "paths": ["src/a.rs", "src/a.rs", "missing.txt"] });
+        let result = execute(&args, &allowed_paths, 1 << 20).unwrap();
+        let blocks = texts(&result);
+        assert_eq!(blocks.len(), 3);
+        assert!(blocks[0].starts_with("Batch read: 1 files read, 1 errors"), "{}", blocks[0]);
+        assert!(blocks[1].contains("fn a() {}"));
+        assert!(blocks[2].starts_with("Error reading"));
+
+        // and their size counts against the budget
+        let args = json!({ "paths": ["src/a.rs", "big.txt"], "max_total_size": 1000 });
+        let result = execute(&args, &allowed_paths, 1 << 20).unwrap();
+        let blocks = texts(&result);
+        assert_eq!(blocks.len(), 2);
+        assert!(blocks[0].contains("Skipped (byte budget reached): 1"), "{}", blocks[0]);
+    }
+
+    #[test]
+    fn test_read_transcodes_to_utf8() {
+        let temp_dir = tempdir().unwrap();
+        let allowed_paths = AllowedPaths::new(vec![temp_dir.path().to_path_buf()]).unwrap();
+
+        let latin1 = temp_dir.path().join("latin1.txt");
+        fs::write(&latin1, b"caf\xe9\n").unwrap();
+        let result = execute(&json!({ "path": latin1 }), &allowed_paths, 1 << 20).unwrap();
+        assert!(texts(&result)[0].contains("Charset: windows-1252 (transcoded to UTF-8)"));
+        assert_eq!(page(&result).1, "café\n");
+
+        // UTF-16 is text, and an explicit charset overrides detection
+        let utf16 = temp_dir.path().join("utf16.txt");
+        fs::write(&utf16, b"\xff\xfeh\x00\xe9\x00\n\x00").unwrap();
+        let result = execute(&json!({ "path": utf16 }), &allowed_paths, 1 << 20).unwrap();
+        assert_eq!(page(&result).1, "hé\n");
+        let result = execute(&json!({ "path": latin1, "charset": "iso-8859-7" }), &allowed_paths, 1 << 20).unwrap();
+        assert_eq!(page(&result).1, "cafι\n");
+    }
+
+    #[test]
+    fn test_binary_files_as_image_and_resource_content() {
+        let temp_dir = tempdir().unwrap();
+        let allowed_paths = AllowedPaths::new(vec![temp_dir.path().to_path_buf()]).unwrap();
+
+        let picture = temp_dir.path().join("picture.png");
+        image::RgbImage::from_pixel(300, 150, image::Rgb([200, 30, 30])).save(&picture).unwrap();
+        let result = execute(&json!({ "path": picture, "max_image_dimension": 60 }), &allowed_paths, 1 << 20).unwrap();
+        assert!(texts(&result)[0].contains("Scaled to: 60x30"));
+        assert!(matches!(&result.content[1], ToolContent::Image { mime_type, .. } if mime_type == "image/png"));
+
+        // No image fits in 10 bytes, which is reported rather than returned silently
+        let result = execute(&json!({ "path": picture, "max_size": 10 }), &allowed_paths, 1 << 20).unwrap();
+        assert!(texts(&result)[0].contains("more than max_size (10 bytes)"));
+
+        // An image larger than the server's limit is not loaded, but read in chunks
+        let result = execute(&json!({ "path": picture }), &allowed_paths, 64).unwrap();
+        assert!(texts(&result)[0].contains("larger than the server's max file size"));
+        assert!(texts(&result)[0].contains("Cursor:"));
+        assert!(matches!(&result.content[1], ToolContent::Resource { .. }));
+
+        let archive = temp_dir.path().join("data.zip");
+        fs::write(&archive, b"PK\x03\x04\0\0\xff\xfe").unwrap();
+        let result = execute(&json!({ "path": archive }), &allowed_paths, 1 << 20).unwrap();
+        let ToolContent::Resource { resource } = &result.content[1] else { panic!("expected a resource") };
+        assert_eq!(resource["mimeType"], "application/zip");
+        assert_eq!(resource["blob"], base64::encode(b"PK\x03\x04\0\0\xff\xfe"));
+    }
+}
@@ -0,0 +1,40 @@
+use anyhow::Result;
+use mcp_protocol::types::tool::ToolCallResult;
+use serde_json::{json, Value};
+
+use crate::tools::undo::{self, Direction};
+use crate::utils::journal::Journal;
+use crate::utils::lock::LockManager;
+use crate::utils::path::AllowedPaths;
+
+// Define the schema for the tool
+pub fn schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "path": {
+                "type": "string",
+                "description": "Full path to the file to restore"
+            },
+            "revision": {
+                "type": "integer",
+                "description": "Undone revision to go forward to, as listed by history; redoes one revision if omitted"
+            },
+            "dry_run": {
+                "type": "boolean",
+                "description": "Validate and report the planned changes without modifying the filesystem",
+                "default": false
+            },
+            "session": {
+                "type": "string",
+                "description": "Session identifier used with the lock tool; paths locked by other sessions are refused"
+            }
+        },
+        "required": ["path"]
+    })
+}
+
+// Execute the redo tool
+pub fn execute(args: &Value, allowed_paths: &AllowedPaths, journal: &Journal, locks: &LockManager) -> Result<ToolCallResult> {
+    undo::restore_revision(args, allowed_paths, journal, locks, Direction::Redo)
+}
@@ -0,0 +1,187 @@
+use anyhow::{anyhow, Result};
+use mcp_protocol::types::tool::{ToolCallResult, ToolContent};
+use serde_json::{json, Value};
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+use crate::utils::lock::{self, LockManager};
+use crate::utils::path::{AllowedPaths, PathError};
+use crate::utils::result::error_result;
+use crate::utils::trash::{self, TrashEntry};
+
+// Define the schema for the tool
+pub fn schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "name": {
+                "type": "string",
+                "description": "Name of the trash entry to restore (as shown by list_trash)"
+            },
+            "path": {
+                "type": "string",
+                "description": "Original path of the item to restore; the most recently deleted match is used"
+            },
+            "root": {
+                "type": "string",
+                "description": "Only look in the trash of the allowed directory containing this path"
+            },
+            "destination": {
+                "type": "string",
+                "description": "Restore to this path instead of the original location"
+            },
+            "overwrite": {
+                "type": "boolean",
+                "description": "Whether to replace an existing item at the restore location",
+                "default": false
+            },
+            "session": {
+                "type": "string",
+                "description": "Session identifier used with the lock tool; paths locked by other sessions are refused"
+            }
+        },
+        "required": []
+    })
+}
+
+// Execute the restore tool
+pub fn execute(args: &Value, allowed_paths: &AllowedPaths, locks: &LockManager) -> Result<ToolCallResult> {
+    // Extract parameters (one of name or path is required)
+    let name = args.get("name").and_then(|v| v.as_str());
+    let original_path = args.get("path").and_then(|v| v.as_str());
+
+    if name.is_none() && original_path.is_none() {
+        return Err(anyhow!("Missing name or path parameter"));
+    }
+
+    let root_str = args.get("root").and_then(|v| v.as_str());
+    let destination_str = args.get("destination").and_then(|v| v.as_str());
+
+    let overwrite = args.get("overwrite")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let session = args.get("session").and_then(|v| v.as_str());
+
+    debug!(
+        "Restoring from trash, name: {:?}, path: {:?}, destination: {:?}, overwrite: {}",
+        name, original_path, destination_str, overwrite
+    );
+
+    let roots = match trash::selected_roots(allowed_paths, root_str) {
+        Ok(roots) => roots,
+        Err(e) => return Ok(error_result(path_error_message(e, root_str.unwrap_or_default()))),
+    };
+
+    // Find the matching entry across the selected roots
+    let mut candidates: Vec<(PathBuf, TrashEntry)> = Vec::new();
+    for root in roots {
+        let entries = match trash::list_entries(&root) {
+            Ok(entries) => entries,
+            Err(e) => {
+                return Ok(error_result(format!(
+                    "Failed to read trash for '{}': {}",
+                    root.display(),
+                    e
+                )));
+            }
+        };
+
+        for entry in entries {
+            let matches = match (name, original_path) {
+                (Some(name), _) => entry.name == name,
+                (None, Some(path)) => entry.original_path == Path::new(path),
+                (None, None) => false,
+            };
+            if matches {
+                candidates.push((root.clone(), entry));
+            }
+        }
+    }
+
+    if name.is_some() && candidates.len() > 1 {
+        let roots = candidates
+            .iter()
+            .map(|(root, _)| root.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        return Ok(error_result(format!(
+            "Trash entry '{}' exists in several allowed directories ({}). Use root to select one",
+            name.unwrap_or_default(),
+            roots
+        )));
+    }
+
+    // For lookups by original path, prefer the most recently deleted item
+    let (root, entry) = match candidates
+        .into_iter()
+        .max_by(|(_, a), (_, b)| a.deletion_date.cmp(&b.deletion_date))
+    {
+        Some(found) => found,
+        None => {
+            return Ok(error_result(format!(
+                "No trash entry found for '{}'",
+                name.or(original_path).unwrap_or_default()
+            )));
+        }
+    };
+
+    // Validate the restore location
+    let destination = match destination_str {
+        Some(dest) => match allowed_paths.validate_path(Path::new(dest)) {
+            Ok(p) => Some(p),
+            Err(e) => return Ok(error_result(path_error_message(e, dest))),
+        },
+        None => None,
+    };
+    let target = destination.clone().unwrap_or_else(|| entry.original_path.clone());
+    if let Err(e) = validate_nearest_ancestor(allowed_paths, &target) {
+        return Ok(error_result(path_error_message(e, &target.display().to_string())));
+    }
+
+    // Refuse to restore onto a path locked by another session
+    if let Err(e) = locks.check(&target, session) {
+        return Ok(lock::error_result(e));
+    }
+
+    match trash::restore(&root, &entry.name, destination.as_deref(), overwrite) {
+        Ok(restored) => {
+            let relative_path = allowed_paths.closest_relative_path(&restored);
+            Ok(ToolCallResult {
+                content: vec![ToolContent::Text {
+                    text: format!(
+                        "Restored {} '{}' to '{}'",
+                        if entry.is_dir { "directory" } else { "file" },
+                        entry.name,
+                        relative_path
+                    ),
+                }],
+                is_error: Some(false),
+            })
+        }
+        Err(e) => Ok(error_result(format!("Failed to restore: {}", e))),
+    }
+}
+
+// The original parent may have been deleted as well, so validate the closest existing ancestor
+fn validate_nearest_ancestor(allowed_paths: &AllowedPaths, target: &Path) -> Result<(), PathError> {
+    let mut current = Some(target);
+    while let Some(path) = current {
+        if path.exists() {
+            return allowed_paths.validate_path(path).map(|_| ());
+        }
+        current = path.parent();
+    }
+    Err(PathError::NotFound)
+}
+
+fn path_error_message(error: PathError, path_str: &str) -> String {
+    match error {
+        PathError::OutsideAllowedPaths =>
+            "Path is outside of all allowed directories".to_string(),
+        PathError::NotFound =>
+            format!("Path not found: '{}'", path_str),
+        PathError::IoError(io_err) =>
+            format!("IO error: {}", io_err),
+    }
+}
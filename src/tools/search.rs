@@ -16,6 +16,7 @@ use tracing::{debug, warn};
 use walkdir::{DirEntry, WalkDir};
 
 use crate::utils::path::{AllowedPaths, is_text_file, PathError};
+use crate::utils::trash;
 
 // Struct representing a search match
 #[derive(Debug, Serialize, Deserialize)]
@@ -338,7 +339,7 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
     writeln!(&mut text, "Time: {:.2} seconds", elapsed.as_secs_f64())?;
     
     if results.total_matches > 0 {
-        writeln!(&mut text, "\nMatches:")?;
+        writeln!(&mut text, "\nMatches (0-based line numbers, as used by read and edit):")?;
         
         for file_match in &results.matches {
             writeln!(&mut text, "\nFile: {}", file_match.file)?;
@@ -377,9 +378,9 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
 
 // Determine if an entry should be processed (directory or matching file)
 fn should_process_entry(entry: &DirEntry, pattern: &Pattern) -> bool {
-    // Always process directories
+    // Always process directories, except trash directories
     if entry.file_type().is_dir() {
-        return true;
+        return !trash::is_trash_dir_name(&entry.file_name().to_string_lossy());
     }
     
     // Skip hidden files
@@ -424,7 +425,7 @@ fn search_file(path: &Path, regex: &Regex, context_lines: usize) -> Result<Vec<M
             let start_context = if line_num > context_lines { line_num - context_lines } else { 0 };
             for i in start_context..line_num {
                 match_context.push(Context {
-                    line_number: i + 1,
+                    line_number: i,
                     content: lines[i].clone(),
                 });
             }
@@ -433,13 +434,13 @@ fn search_file(path: &Path, regex: &Regex, context_lines: usize) -> Result<Vec<M
             let end_context = std::cmp::min(line_num + context_lines + 1, lines.len());
             for i in line_num+1..end_context {
                 match_context.push(Context {
-                    line_number: i + 1,
+                    line_number: i,
                     content: lines[i].clone(),
                 });
             }
             
             matches.push(Match {
-                line_number: line_num + 1,
+                line_number: line_num,
                 line: line.clone(),
                 context: match_context,
             });
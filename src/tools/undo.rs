@@ -0,0 +1,161 @@
+use anyhow::{anyhow, Result};
+use mcp_protocol::types::tool::{ToolCallResult, ToolContent};
+use serde_json::{json, Value};
+use std::path::Path;
+use tracing::debug;
+
+use crate::tools::history;
+use crate::utils::journal::Journal;
+use crate::utils::lock::{self, LockManager};
+use crate::utils::path::{AllowedPaths, PathError};
+use crate::utils::plan::{ActionKind, Plan};
+use crate::utils::result::error_result;
+
+// Whether revisions are undone or redone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Direction {
+    Undo,
+    Redo,
+}
+
+// Define the schema for the tool
+pub fn schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "path": {
+                "type": "string",
+                "description": "Full path to the file to restore"
+            },
+            "revision": {
+                "type": "integer",
+                "description": "Revision to go back to, as listed by history (0 for the state before the first revision); undoes one revision if omitted"
+            },
+            "dry_run": {
+                "type": "boolean",
+                "description": "Validate and report the planned changes without modifying the filesystem",
+                "default": false
+            },
+            "session": {
+                "type": "string",
+                "description": "Session identifier used with the lock tool; paths locked by other sessions are refused"
+            }
+        },
+        "required": ["path"]
+    })
+}
+
+// Execute the undo tool
+pub fn execute(args: &Value, allowed_paths: &AllowedPaths, journal: &Journal, locks: &LockManager) -> Result<ToolCallResult> {
+    restore_revision(args, allowed_paths, journal, locks, Direction::Undo)
+}
+
+// Undo or redo revisions of a file, shared by the undo and redo tools
+pub(crate) fn restore_revision(
+    args: &Value,
+    allowed_paths: &AllowedPaths,
+    journal: &Journal,
+    locks: &LockManager,
+    direction: Direction,
+) -> Result<ToolCallResult> {
+    let tool = match direction {
+        Direction::Undo => "undo",
+        Direction::Redo => "redo",
+    };
+
+    // Extract parameters
+    let path_str = args.get("path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("Missing path parameter"))?;
+
+    let revision = args.get("revision").and_then(|v| v.as_u64());
+
+    let dry_run = args.get("dry_run")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    let session = args.get("session").and_then(|v| v.as_str());
+
+    debug!("{} '{}' to revision {:?}, dry_run: {}", tool, path_str, revision, dry_run);
+
+    // Validate the path (the file itself may have been deleted)
+    let validated_path = match allowed_paths.validate_path(Path::new(path_str)) {
+        Ok(p) => p,
+        Err(e) => {
+            let error_message = match e {
+                PathError::OutsideAllowedPaths =>
+                    "Path is outside of all allowed directories".to_string(),
+                PathError::NotFound =>
+                    format!("Parent directory not found: '{}'", path_str),
+                PathError::IoError(io_err) =>
+                    format!("IO error: {}", io_err),
+            };
+
+            return Ok(error_result(error_message));
+        }
+    };
+
+    // Refuse to touch a path locked by another session
+    if let Err(e) = locks.check(&validated_path, session) {
+        return Ok(lock::error_result(e));
+    }
+
+    // Report what would be restored
+    if dry_run {
+        let planned = match direction {
+            Direction::Undo => journal.plan_undo(&validated_path, revision),
+            Direction::Redo => journal.plan_redo(&validated_path, revision),
+        };
+        let mut plan = Plan::new(tool);
+        match planned {
+            Ok((revisions, hash)) => {
+                match hash {
+                    Some(_) if validated_path.exists() => plan.add(ActionKind::OverwriteFile, &validated_path, None),
+                    Some(_) => plan.add(ActionKind::CreateFile, &validated_path, None),
+                    None => plan.add(ActionKind::Remove, &validated_path, None),
+                };
+                for revision in &revisions {
+                    plan.add_note(format!("{} {}", tool, history::describe(revision)));
+                }
+            }
+            Err(e) => plan.add_error(e.to_string()),
+        }
+        return Ok(plan.into_result(allowed_paths));
+    }
+
+    let result = match direction {
+        Direction::Undo => journal.undo(&validated_path, revision),
+        Direction::Redo => journal.redo(&validated_path, revision),
+    };
+    let restored = match result {
+        Ok(restored) => restored,
+        Err(e) => return Ok(error_result(e.to_string())),
+    };
+
+    let verb = match direction {
+        Direction::Undo => "Undid",
+        Direction::Redo => "Redid",
+    };
+    let mut text = format!(
+        "{} {} revision(s) of {}\n",
+        verb,
+        restored.revisions.len(),
+        allowed_paths.closest_relative_path(&validated_path)
+    );
+    for revision in &restored.revisions {
+        text.push_str(&format!("  - {}\n", history::describe(revision)));
+    }
+    text.push_str(&format!("Current revision: {}\n", restored.current_revision));
+    match &restored.hash {
+        Some(hash) => text.push_str(&format!("Hash: {}\n", hash)),
+        None => text.push_str("The file does not exist at this revision and was removed\n"),
+    }
+    for warning in &restored.warnings {
+        text.push_str(&format!("Warning: {}\n", warning));
+    }
+
+    Ok(ToolCallResult {
+        content: vec![ToolContent::Text { text: text.trim_end().to_string() }],
+        is_error: Some(false),
+    })
+}
@@ -0,0 +1,88 @@
+use anyhow::{anyhow, Result};
+use mcp_protocol::types::tool::{ToolCallResult, ToolContent};
+use serde_json::{json, Value};
+use std::path::Path;
+use tracing::debug;
+
+use crate::utils::lock::LockManager;
+use crate::utils::path::{AllowedPaths, PathError};
+
+// Define the schema for the tool
+pub fn schema() -> Value {
+    json!({
+        "type": "object",
+        "properties": {
+            "path": {
+                "type": "string",
+                "description": "Full path to the locked file or directory"
+            },
+            "session": {
+                "type": "string",
+                "description": "Identifier of the session holding the lock"
+            },
+            "force": {
+                "type": "boolean",
+                "description": "Release the lock even if it is held by another session of this server",
+                "default": false
+            }
+        },
+        "required": ["path", "session"]
+    })
+}
+
+// Execute the unlock tool
+pub fn execute(args: &Value, allowed_paths: &AllowedPaths, locks: &LockManager) -> Result<ToolCallResult> {
+    // Extract parameters
+    let path_str = args.get("path")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("Missing path parameter"))?;
+
+    let session = args.get("session")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| anyhow!("Missing session parameter"))?;
+
+    let force = args.get("force")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+
+    debug!("Unlocking '{}' for session '{}', force: {}", path_str, session, force);
+
+    // Validate the path
+    let validated_path = match allowed_paths.validate_path(Path::new(path_str)) {
+        Ok(p) => p,
+        Err(e) => {
+            let error_message = match e {
+                PathError::OutsideAllowedPaths =>
+                    "Path is outside of all allowed directories".to_string(),
+                PathError::NotFound =>
+                    format!("Path not found: '{}'", path_str),
+                PathError::IoError(io_err) =>
+                    format!("IO error: {}", io_err),
+            };
+
+            return Ok(ToolCallResult {
+                content: vec![ToolContent::Text { text: error_message }],
+                is_error: Some(true),
+            });
+        }
+    };
+
+    match locks.release(&validated_path, session, force) {
+        Ok(info) => {
+            let result = json!({
+                "success": true,
+                "path": allowed_paths.closest_relative_path(&info.path),
+                "session": info.session
+            });
+
+            Ok(ToolCallResult {
+                content: vec![ToolContent::Text { text: result.to_string() }],
+                is_error: Some(false),
+            })
+        }
+        Err(e) => Ok(ToolCallResult {
+            content: vec![ToolContent::Text { text: e.to_string() }],
+            is_error: Some(true),
+        }),
+    }
+}
@@ -2,14 +2,24 @@ use anyhow::{anyhow, Result};
 use mcp_protocol::types::tool::{ToolCallResult, ToolContent};
 use serde_json::{json, Value};
 use std::{
-    fs::{self, OpenOptions},
-    io::Write as IoWrite,
+    fs,
+    io::Read,
     path::Path,
 };
 use tracing::{debug, warn};
 use base64;
 
-use crate::utils::path::{AllowedPaths, PathError};
+use crate::utils::atomic;
+use crate::utils::encoding::{self, Charset, EncodingError};
+use crate::utils::journal::Journal;
+use crate::utils::lock::{self, LockManager};
+use crate::utils::path::{is_text_file, AllowedPaths, PathError};
+use crate::utils::plan::{self, ActionKind, Plan};
+use crate::utils::precondition::{self, Preconditions};
+use crate::utils::result::error_result;
+use crate::utils::scan;
+use crate::utils::text::{self, LineEnding, TextFormat};
+use crate::utils::validate;
 
 // Define the schema for the tool
 pub fn schema() -> Value {
@@ -36,10 +46,51 @@ pub fn schema() -> Value {
                 "enum": ["create", "overwrite", "append", "create_new"],
                 "default": "overwrite"
             },
+            "line_ending": {
+                "type": "string",
+                "description": "Convert the content's line endings; preserve uses the existing file's line endings (utf8 encoding only)",
+                "enum": ["lf", "crlf", "preserve"]
+            },
+            "bom": {
+                "type": "string",
+                "description": "Add or remove a UTF-8 byte order mark at the start of the file; preserve keeps the existing file's (utf8 encoding only)",
+                "enum": ["add", "remove", "preserve"]
+            },
+            "charset": {
+                "type": "string",
+                "description": "Character encoding to store the text in, e.g. \"utf-8\", \"utf-16le\" or \"windows-1252\"; defaults to the existing file's charset when it can be detected reliably, otherwise UTF-8 (utf8 encoding only)"
+            },
             "make_dirs": {
                 "type": "boolean",
                 "description": "Create parent directories if they don't exist",
                 "default": false
+            },
+            "dry_run": {
+                "type": "boolean",
+                "description": "Validate and report the planned changes without modifying the filesystem",
+                "default": false
+            },
+            "validate": {
+                "type": "boolean",
+                "description": "Parse the resulting file (JSON, YAML, TOML, Rust, Python, TypeScript, Go; chosen by extension) and report its first syntax errors with 0-based line and column",
+                "default": false
+            },
+            "reject_invalid": {
+                "type": "boolean",
+                "description": "Refuse to write content that fails validation, even if the file it replaces was already invalid (implies validate; edit's reject_new_errors only refuses new errors)",
+                "default": false
+            },
+            "if_match_hash": {
+                "type": "string",
+                "description": "Only proceed if the file's current SHA-256 hash (as returned by read or info) matches"
+            },
+            "if_unmodified_since": {
+                "type": "string",
+                "description": "Only proceed if the file has not been modified after this RFC 3339 timestamp (as returned by read or info)"
+            },
+            "session": {
+                "type": "string",
+                "description": "Session identifier used with the lock tool; paths locked by other sessions are refused"
             }
         },
         "required": ["path", "content"]
@@ -47,7 +98,7 @@ pub fn schema() -> Value {
 }
 
 // Execute the write tool
-pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallResult> {
+pub fn execute(args: &Value, allowed_paths: &AllowedPaths, locks: &LockManager, journal: &Journal) -> Result<ToolCallResult> {
     // Extract required parameters
     let path_str = args.get("path")
         .and_then(|v| v.as_str())
@@ -66,17 +117,64 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
         .and_then(|v| v.as_str())
         .unwrap_or("overwrite");
     
+    let line_ending = args.get("line_ending").and_then(|v| v.as_str());
+    let bom = args.get("bom").and_then(|v| v.as_str());
+    
+    let charset = match args.get("charset").and_then(|v| v.as_str()).map(Charset::parse) {
+        Some(Ok(charset)) => Some(charset),
+        Some(Err(e)) => {
+            return Ok(ToolCallResult {
+                content: vec![ToolContent::Text { text: e.to_string() }],
+                is_error: Some(true),
+            });
+        }
+        None => None,
+    };
+    
     let make_dirs = args.get("make_dirs")
         .and_then(|v| v.as_bool())
         .unwrap_or(false);
     
+    let dry_run = args.get("dry_run")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    
+    let reject_invalid = args.get("reject_invalid")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    
+    let validate = reject_invalid || args.get("validate")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    
+    let session = args.get("session").and_then(|v| v.as_str());
+    
+    let preconditions = match Preconditions::from_args(args) {
+        Ok(p) => p,
+        Err(e) => {
+            return Ok(ToolCallResult {
+                content: vec![ToolContent::Text { text: e.to_string() }],
+                is_error: Some(true),
+            });
+        }
+    };
+    
     debug!(
-        "Writing to path: '{}', encoding: '{}', mode: '{}', make_dirs: {}",
-        path_str, encoding, mode, make_dirs
+        "Writing to path: '{}', encoding: '{}', mode: '{}', make_dirs: {}, dry_run: {}",
+        path_str, encoding, mode, make_dirs, dry_run
     );
     
+    if encoding != "utf8" && (line_ending.is_some() || bom.is_some() || charset.is_some()) {
+        return Ok(ToolCallResult {
+            content: vec![ToolContent::Text {
+                text: "The line_ending, bom and charset options require utf8 encoding".to_string(),
+            }],
+            is_error: Some(true),
+        });
+    }
+    
     // Decode content if needed
-    let decoded_content = match encoding {
+    let mut decoded_content = match encoding {
         "utf8" => content.as_bytes().to_vec(),
         "base64" => {
             match base64::decode(content) {
@@ -144,6 +242,166 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
         }
     };
     
+    // Refuse to touch a file that changed since the caller last saw it
+    if let Err(e) = preconditions.check(&validated_path) {
+        return Ok(precondition::error_result(e, &validated_path));
+    }
+    
+    // Refuse to touch a path locked by another session
+    if let Err(e) = locks.check(&validated_path, session) {
+        return Ok(lock::error_result(e));
+    }
+    
+    // Text content is stored in the existing file's charset when it can be told reliably,
+    // unless another one is given
+    let existing_charset = if encoding == "utf8" && validated_path.is_file() {
+        match detect_existing_charset(&validated_path) {
+            Ok(charset) => charset,
+            Err(e) => return Ok(error_result(format!("Failed to read existing file: {}", e))),
+        }
+    } else {
+        None
+    };
+    let mut target_charset = match (existing_charset, charset) {
+        (Some(existing_charset), Some(charset)) if mode == "append" && charset.encoding != existing_charset.encoding => {
+            return Ok(error_result(format!(
+                "Cannot append {} text to '{}', which is {}; append in its charset or rewrite the whole file",
+                charset, validated_path.display(), existing_charset
+            )));
+        }
+        (_, Some(charset)) => charset,
+        (Some(existing_charset), None) => existing_charset,
+        (None, None) => Charset::UTF8,
+    };
+    let appending = mode == "append" && validated_path.is_file();
+    
+    let mut warnings = Vec::new();
+    
+    // The text of the file as it will be after the write (appends include the existing content)
+    let mut resulting_text = None;
+    if encoding == "utf8" {
+        // An append is validated with the text before it, which is read in full for the write anyway
+        let existing_text = if appending {
+            match fs::read(&validated_path) {
+                Ok(bytes) => Some(target_charset.decode(&bytes)),
+                Err(e) => return Ok(error_result(format!("Failed to read existing file: {}", e))),
+            }
+        } else {
+            None
+        };
+        
+        // Only preserving the line endings or byte order mark needs the format of a file being replaced
+        let preserve = line_ending == Some("preserve") || bom == Some("preserve");
+        let existing_format = match &existing_text {
+            Some(text) => Some(TextFormat::detect(text)),
+            None if preserve && validated_path.is_file() => match scan::scan_text(&validated_path, existing_charset) {
+                Ok(text_scan) => Some(text_scan.format),
+                Err(e) => return Ok(error_result(format!("Failed to read existing file: {}", e))),
+            },
+            None => None,
+        };
+        
+        // Apply the requested line endings and byte order mark
+        let text = match apply_text_format(content, existing_format, mode, line_ending, bom) {
+            Ok(formatted) => formatted,
+            Err(e) => return Ok(error_result(e.to_string())),
+        };
+        
+        // An appended tail never repeats the byte order mark
+        let tail_charset = if appending { target_charset.without_bom() } else { target_charset };
+        decoded_content = match tail_charset.encode(&text) {
+            Ok(bytes) => bytes,
+            // A replaced file only inherited its charset, so content it cannot hold is kept as UTF-8
+            Err(EncodingError::Unmappable { character, line, .. }) if charset.is_none() && !appending => {
+                warnings.push(format!(
+                    "'{}' (line {}) has no representation in {}, the file's previous charset; it was written as UTF-8",
+                    character, line, target_charset
+                ));
+                target_charset = Charset::UTF8;
+                text.as_bytes().to_vec()
+            }
+            Err(e) => return Ok(error_result(e.to_string())),
+        };
+        resulting_text = Some(match existing_text {
+            Some(existing_text) => format!("{}{}", existing_text, text),
+            None => text,
+        });
+    }
+    
+    // Parse the file as it will be after the write
+    let validation = if validate {
+        let resulting = match &resulting_text {
+            Some(text) => text.clone(),
+            None => String::from_utf8_lossy(&decoded_content).into_owned(),
+        };
+        validate::validate(&resulting, &validated_path, None)
+    } else {
+        None
+    };
+    let rejected = reject_invalid && validation.as_ref().is_some_and(|v| !v.valid);
+    
+    // Report what would be written
+    if dry_run {
+        let mut plan = Plan::new("write");
+        match &validation {
+            Some(validation) if rejected => plan.add_error(format!("Content would be refused: {}", validation.describe())),
+            Some(validation) => plan.add_note(validation.describe()),
+            None if validate => plan.add_note("No syntax validation is available for this file type"),
+            None => {}
+        }
+        for warning in &warnings {
+            plan.add_note(warning.clone());
+        }
+        if make_dirs {
+            if let Some(parent) = validated_path.parent() {
+                plan::add_missing_dirs(&mut plan, parent);
+            }
+        }
+        
+        let exists = validated_path.exists();
+        let bytes = Some(decoded_content.len() as u64);
+        if validated_path.is_dir() {
+            plan.add_conflict(ActionKind::OverwriteFile, &validated_path, "Path is a directory");
+        } else {
+            match mode {
+                "create" | "overwrite" => {
+                    let action = if exists { ActionKind::OverwriteFile } else { ActionKind::CreateFile };
+                    plan.add(action, &validated_path, bytes);
+                }
+                "append" => {
+                    let action = if exists { ActionKind::AppendFile } else { ActionKind::CreateFile };
+                    plan.add(action, &validated_path, bytes);
+                }
+                "create_new" if exists => {
+                    plan.add_conflict(ActionKind::CreateFile, &validated_path, "File already exists and mode is create_new");
+                }
+                "create_new" => {
+                    plan.add(ActionKind::CreateFile, &validated_path, bytes);
+                }
+                _ => {
+                    return Ok(ToolCallResult {
+                        content: vec![ToolContent::Text {
+                            text: format!("Invalid mode: '{}'", mode),
+                        }],
+                        is_error: Some(true),
+                    });
+                }
+            }
+        }
+        
+        return Ok(plan.into_result(allowed_paths));
+    }
+    
+    if rejected {
+        let report = validation.as_ref().map(|v| v.describe()).unwrap_or_default();
+        return Ok(ToolCallResult {
+            content: vec![ToolContent::Text {
+                text: format!("Refusing to write '{}': the content has syntax errors\n{}", validated_path.display(), report),
+            }],
+            is_error: Some(true),
+        });
+    }
+    
     // Create parent directories if needed
     if make_dirs {
         if let Some(parent) = validated_path.parent() {
@@ -165,35 +423,30 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
         }
     }
     
-    // Determine file open mode and handle existing files
-    let file_result = match mode {
-        "create" => {
-            OpenOptions::new()
-                .write(true)
-                .create(true)
-                .truncate(true)
-                .open(&validated_path)
-        }
-        "overwrite" => {
-            OpenOptions::new()
-                .write(true)
-                .create(true)
-                .truncate(true)
-                .open(&validated_path)
-        }
+    // Determine the final file contents for the requested mode
+    let (final_content, create_new) = match mode {
+        "create" | "overwrite" => (decoded_content.clone(), false),
         "append" => {
-            OpenOptions::new()
-                .write(true)
-                .create(true)
-                .append(true)
-                .open(&validated_path)
-        }
-        "create_new" => {
-            OpenOptions::new()
-                .write(true)
-                .create_new(true)
-                .open(&validated_path)
+            // Appends are staged like any other write so a crash never leaves a partial tail
+            let mut existing = if validated_path.exists() {
+                match fs::read(&validated_path) {
+                    Ok(bytes) => bytes,
+                    Err(e) => {
+                        return Ok(ToolCallResult {
+                            content: vec![ToolContent::Text {
+                                text: format!("Failed to read existing file for append: {}", e),
+                            }],
+                            is_error: Some(true),
+                        });
+                    }
+                }
+            } else {
+                Vec::new()
+            };
+            existing.extend_from_slice(&decoded_content);
+            (existing, false)
         }
+        "create_new" => (decoded_content.clone(), true),
         _ => {
             return Ok(ToolCallResult {
                 content: vec![ToolContent::Text {
@@ -204,30 +457,22 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
         }
     };
     
-    // Handle file open result
-    let mut file = match file_result {
-        Ok(f) => f,
-        Err(e) => {
-            let error_msg = if mode == "create_new" && e.kind() == std::io::ErrorKind::AlreadyExists {
-                format!("File already exists: '{}' and mode is create_new", validated_path.display())
-            } else {
-                format!("Failed to open file: {}", e)
-            };
-            
-            return Ok(ToolCallResult {
-                content: vec![ToolContent::Text {
-                    text: error_msg,
-                }],
-                is_error: Some(true),
-            });
-        }
+    // Keep the previous contents for undo
+    let before = journal.capture(&validated_path);
+    
+    // Write content via a temp file and atomic rename
+    let write_result = if create_new {
+        atomic::write_atomic_new(&validated_path, &final_content)
+    } else {
+        atomic::write_atomic(&validated_path, &final_content)
     };
     
-    // Write content to file
-    match file.write_all(&decoded_content) {
-        Ok(_) => {
+    match write_result {
+        Ok(outcome) => {
+            journal.record(&validated_path, "write", before, Some(&final_content));
+            
             // Get file metadata
-            let metadata = match file.metadata() {
+            let metadata = match fs::metadata(&validated_path) {
                 Ok(m) => m,
                 Err(e) => {
                     warn!("Failed to get file metadata: {}", e);
@@ -251,15 +496,27 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
             // Format success response
             let relative_path = allowed_paths.closest_relative_path(&validated_path);
             
-            let response = json!({
+            let mut response = json!({
                 "success": true,
                 "path": relative_path,
                 "bytes_written": decoded_content.len(),
                 "metadata": {
                     "size": metadata.len(),
-                    "modified": modified_time
+                    "modified": modified_time,
+                    "hash": precondition::hash_bytes(&final_content)
                 }
             });
+            if let Some(text) = &resulting_text {
+                response["metadata"]["format"] = json!(TextFormat::detect(text));
+                response["metadata"]["charset"] = json!(target_charset.to_string());
+            }
+            if let Some(validation) = &validation {
+                response["validation"] = json!(validation);
+            }
+            warnings.extend(outcome.warnings);
+            if !warnings.is_empty() {
+                response["warnings"] = json!(warnings);
+            }
             
             Ok(ToolCallResult {
                 content: vec![ToolContent::Text {
@@ -269,12 +526,62 @@ pub fn execute(args: &Value, allowed_paths: &AllowedPaths) -> Result<ToolCallRes
             })
         }
         Err(e) => {
+            let error_msg = if create_new && e.kind() == std::io::ErrorKind::AlreadyExists {
+                format!("File already exists: '{}' and mode is create_new", validated_path.display())
+            } else {
+                format!("Failed to write to file: {}", e)
+            };
+            
             Ok(ToolCallResult {
                 content: vec![ToolContent::Text {
-                    text: format!("Failed to write to file: {}", e),
+                    text: error_msg,
                 }],
                 is_error: Some(true),
             })
         }
     }
 }
+
+// Convert text content to the requested line endings and byte order mark; "preserve" takes
+// them from the existing file's text, which is left as given if it has none or mixes line endings
+fn apply_text_format(content: &str, existing: Option<TextFormat>, mode: &str, line_ending: Option<&str>, bom: Option<&str>) -> Result<String> {
+    let mut formatted = content.to_string();
+    
+    let eol = match line_ending {
+        None => None,
+        Some("preserve") => existing.and_then(|format| format.line_ending.as_str()),
+        Some(value) => Some(
+            LineEnding::parse(value)
+                .and_then(|ending| ending.as_str())
+                .ok_or_else(|| anyhow!("Invalid line_ending: '{}'", value))?,
+        ),
+    };
+    if let Some(eol) = eol {
+        formatted = text::convert_line_endings(&formatted, eol);
+    }
+    
+    // An appended tail never carries a byte order mark; only the start of a new file can
+    let at_file_start = mode != "append" || existing.is_none();
+    let add_bom = match bom {
+        None => None,
+        Some("add") => Some(at_file_start),
+        Some("remove") => Some(false),
+        Some("preserve") => Some(at_file_start && existing.is_some_and(|format| format.bom)),
+        Some(value) => return Err(anyhow!("Invalid bom: '{}'", value)),
+    };
+    if let Some(add_bom) = add_bom {
+        formatted = text::set_bom(&formatted, add_bom);
+    }
+    
+    Ok(formatted)
+}
+
+// The charset of an existing text file, from its first bytes; None for binary files and guesses
+fn detect_existing_charset(path: &Path) -> std::io::Result<Option<Charset>> {
+    if !is_text_file(path)? {
+        return Ok(None);
+    }
+    let mut prefix = Vec::new();
+    fs::File::open(path)?.take(encoding::SAMPLE_LEN as u64).read_to_end(&mut prefix)?;
+    Ok(Charset::detect_confident(&prefix))
+}
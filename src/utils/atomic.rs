@@ -0,0 +1,354 @@
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+use tracing::{debug, warn};
+
+/// Outcome of an atomic write
+#[derive(Debug, Default)]
+pub struct AtomicWrite {
+    /// Problems that did not stop the write, e.g. falling back to an in-place write
+    pub warnings: Vec<String>,
+}
+
+/// Atomically replace (or create) a file with `contents`
+///
+/// The data is written to a sibling temp file, fsynced, given the original
+/// file's permissions and ownership, and renamed over the target; the parent
+/// directory is fsynced afterwards so the rename itself is durable. If the
+/// temp file cannot be created or renamed, the file is written in place and
+/// a warning is returned. Errors writing or syncing the temp file (e.g. a
+/// full disk) are returned with the target untouched.
+///
+/// # Arguments
+///
+/// * `path` - The file to write
+/// * `contents` - The complete new contents of the file
+///
+/// # Returns
+///
+/// * `io::Result<AtomicWrite>` - Warnings produced while writing
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<AtomicWrite> {
+    write_with(path, contents, false)
+}
+
+/// Atomically create a new file, failing with `AlreadyExists` if it exists
+///
+/// Like [`write_atomic`], but the final step never replaces an existing file.
+pub fn write_atomic_new(path: &Path, contents: &[u8]) -> io::Result<AtomicWrite> {
+    write_with(path, contents, true)
+}
+
+/// Atomically replace (or create) a file readable and writable only by its owner (mode 0600)
+///
+/// Like [`write_atomic`], for the server's own state; the file's previous
+/// permissions are not kept.
+pub fn write_private(path: &Path, contents: &[u8]) -> io::Result<AtomicWrite> {
+    let mut outcome = AtomicWrite::default();
+    let (mut temp, _) = create_temp(path, true)?;
+    fill_temp(&mut temp, path, None, &mut outcome, |file| file.write_all(contents))?;
+    temp.persist(path).map_err(|e| e.error)?;
+    sync_parent_dir(path);
+    Ok(outcome)
+}
+
+/// A file whose new contents are written and synced but not yet in place
+///
+/// Staging several files before committing any of them lets a batch of
+/// writes fail without touching the targets. Dropping a staged file removes
+/// its temp file.
+pub struct StagedFile {
+    path: PathBuf,
+    temp: NamedTempFile,
+    outcome: AtomicWrite,
+}
+
+/// Write `contents` to a temp file next to `path`, ready to replace it
+///
+/// Unlike [`write_atomic`] there is no in-place fallback: if the temp file
+/// cannot be created the error is returned.
+pub fn stage_file(path: &Path, contents: &[u8]) -> io::Result<StagedFile> {
+    let mut outcome = AtomicWrite::default();
+    let (mut temp, existing) = create_temp(path, false)?;
+    fill_temp(&mut temp, path, existing, &mut outcome, |file| file.write_all(contents))?;
+    Ok(StagedFile {
+        path: path.to_path_buf(),
+        temp,
+        outcome,
+    })
+}
+
+impl StagedFile {
+    /// Rename the staged contents over the target
+    pub fn commit(self) -> io::Result<AtomicWrite> {
+        self.temp.persist(&self.path).map_err(|e| e.error)?;
+        sync_parent_dir(&self.path);
+        debug!("Committed staged write to '{}'", self.path.display());
+        Ok(self.outcome)
+    }
+}
+
+fn write_with(path: &Path, contents: &[u8], create_new: bool) -> io::Result<AtomicWrite> {
+    write_staged(path, contents, create_new, |file| file.write_all(contents))
+}
+
+// Only an impossible temp file or rename falls back to writing in place; a failed write to
+// the temp file would fail the same way in place, after truncating the target
+fn write_staged<F>(path: &Path, contents: &[u8], create_new: bool, write: F) -> io::Result<AtomicWrite>
+where
+    F: FnOnce(&mut NamedTempFile) -> io::Result<()>,
+{
+    let mut outcome = AtomicWrite::default();
+
+    let (mut temp, existing) = match create_temp(path, false) {
+        Ok(created) => created,
+        Err(e) => {
+            let warning = format!("Could not create temp file for atomic write ({}); wrote file in place", e);
+            return write_in_place(path, contents, create_new, outcome, warning);
+        }
+    };
+    fill_temp(&mut temp, path, existing, &mut outcome, write)?;
+
+    let persisted = if create_new {
+        temp.persist_noclobber(path)
+    } else {
+        temp.persist(path)
+    };
+
+    if let Err(e) = persisted {
+        if e.error.kind() == io::ErrorKind::AlreadyExists {
+            return Err(e.error);
+        }
+        let warning = format!("Atomic rename failed ({}); wrote file in place", e.error);
+        drop(e.file);
+        return write_in_place(path, contents, create_new, outcome, warning);
+    }
+
+    sync_parent_dir(path);
+    debug!("Atomically wrote {} bytes to '{}'", contents.len(), path.display());
+    Ok(outcome)
+}
+
+// Create an empty temp file next to `path`, returning it with the target's current metadata;
+// a private one keeps the temp file's 0600 mode
+fn create_temp(path: &Path, private: bool) -> io::Result<(NamedTempFile, Option<fs::Metadata>)> {
+    let parent = parent_dir(path);
+    let file_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let existing = fs::metadata(path).ok();
+
+    let prefix = format!(".{}.", file_name);
+    let mut builder = tempfile::Builder::new();
+    builder.prefix(&prefix).suffix(".tmp");
+
+    // New files get the usual 0666 & !umask instead of the temp file's private mode
+    #[cfg(unix)]
+    if existing.is_none() && !private {
+        use std::os::unix::fs::PermissionsExt;
+        builder.permissions(fs::Permissions::from_mode(0o666));
+    }
+
+    let temp = builder.tempfile_in(parent)?;
+    Ok((temp, existing))
+}
+
+// Write the contents into the temp file, sync it and give it the target's permissions
+fn fill_temp<F>(
+    temp: &mut NamedTempFile,
+    path: &Path,
+    existing: Option<fs::Metadata>,
+    outcome: &mut AtomicWrite,
+    write: F,
+) -> io::Result<()>
+where
+    F: FnOnce(&mut NamedTempFile) -> io::Result<()>,
+{
+    write(temp)?;
+    temp.as_file().sync_all()?;
+
+    if let Some(metadata) = existing {
+        fs::set_permissions(temp.path(), metadata.permissions())?;
+        if let Err(e) = copy_ownership(&metadata, temp.path()) {
+            warn!("Failed to preserve ownership of '{}': {}", path.display(), e);
+            outcome
+                .warnings
+                .push(format!("Could not preserve file ownership: {}", e));
+        }
+    }
+
+    Ok(())
+}
+
+fn write_in_place(
+    path: &Path,
+    contents: &[u8],
+    create_new: bool,
+    mut outcome: AtomicWrite,
+    warning: String,
+) -> io::Result<AtomicWrite> {
+    warn!("{}: '{}'", warning, path.display());
+
+    let mut options = OpenOptions::new();
+    options.write(true);
+    if create_new {
+        options.create_new(true);
+    } else {
+        options.create(true).truncate(true);
+    }
+
+    let mut file = options.open(path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+
+    outcome.warnings.push(warning);
+    Ok(outcome)
+}
+
+#[cfg(unix)]
+fn copy_ownership(metadata: &fs::Metadata, target: &Path) -> io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+
+    let target_metadata = fs::metadata(target)?;
+    if target_metadata.uid() == metadata.uid() && target_metadata.gid() == metadata.gid() {
+        return Ok(());
+    }
+    std::os::unix::fs::chown(target, Some(metadata.uid()), Some(metadata.gid()))
+}
+
+#[cfg(not(unix))]
+fn copy_ownership(_metadata: &fs::Metadata, _target: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+fn parent_dir(path: &Path) -> &Path {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    }
+}
+
+// Make a completed rename durable; failures are only logged since the data is already in place
+fn sync_parent_dir(path: &Path) {
+    #[cfg(unix)]
+    {
+        if let Err(e) = fs::File::open(parent_dir(path)).and_then(|dir| dir.sync_all()) {
+            warn!("Failed to fsync directory of '{}': {}", path.display(), e);
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = path;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_write_atomic_replaces_content() {
+        let temp_dir = tempdir().unwrap();
+        let file = temp_dir.path().join("data.txt");
+        fs::write(&file, "old content that is longer").unwrap();
+
+        let outcome = write_atomic(&file, b"new").unwrap();
+        assert!(outcome.warnings.is_empty());
+        assert_eq!(fs::read_to_string(&file).unwrap(), "new");
+
+        // No temp files are left behind
+        let entries: Vec<_> = fs::read_dir(temp_dir.path()).unwrap().collect();
+        assert_eq!(entries.len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_preserves_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempdir().unwrap();
+        let file = temp_dir.path().join("script.sh");
+        fs::write(&file, "#!/bin/sh\n").unwrap();
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o750)).unwrap();
+
+        write_atomic(&file, b"#!/bin/sh\necho hi\n").unwrap();
+        let mode = fs::metadata(&file).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o750);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_atomic_new_file_is_not_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempdir().unwrap();
+        let file = temp_dir.path().join("new.txt");
+
+        write_atomic(&file, b"content").unwrap();
+        let mode = fs::metadata(&file).unwrap().permissions().mode();
+        assert_ne!(mode & 0o777, 0o600);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_private() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempdir().unwrap();
+        let file = temp_dir.path().join("blob");
+        fs::write(&file, "old").unwrap();
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_private(&file, b"new").unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "new");
+        assert_eq!(fs::metadata(&file).unwrap().permissions().mode() & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_staged_file_commit_and_drop() {
+        let temp_dir = tempdir().unwrap();
+        let file = temp_dir.path().join("data.txt");
+        fs::write(&file, "old").unwrap();
+
+        // Dropping a staged file leaves the target untouched and cleans up
+        drop(stage_file(&file, b"discarded").unwrap());
+        assert_eq!(fs::read_to_string(&file).unwrap(), "old");
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+
+        let staged = stage_file(&file, b"new").unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "old");
+        staged.commit().unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "new");
+    }
+
+    #[test]
+    fn test_failed_temp_write_leaves_target_untouched() {
+        let temp_dir = tempdir().unwrap();
+        let file = temp_dir.path().join("data.txt");
+        fs::write(&file, "original").unwrap();
+
+        // A full disk while writing the temp file must not fall back to truncating the target
+        let err = write_staged(&file, b"new", false, |temp| {
+            temp.write_all(b"ne")?;
+            Err(io::Error::new(io::ErrorKind::StorageFull, "no space left on device"))
+        })
+        .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::StorageFull);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "original");
+        assert_eq!(fs::read_dir(temp_dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_write_atomic_new_refuses_existing() {
+        let temp_dir = tempdir().unwrap();
+        let file = temp_dir.path().join("data.txt");
+
+        write_atomic_new(&file, b"first").unwrap();
+        let err = write_atomic_new(&file, b"second").unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "first");
+    }
+}
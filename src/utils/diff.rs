@@ -0,0 +1,45 @@
+use similar::TextDiff;
+
+/// Produce a unified diff between two versions of a file
+///
+/// # Arguments
+///
+/// * `old` - The original content
+/// * `new` - The modified content
+/// * `path` - Path shown in the `---`/`+++` headers (as `a/path` and `b/path`)
+/// * `context_lines` - Number of unchanged lines shown around each change
+///
+/// # Returns
+///
+/// * `String` - The diff, empty if the contents are identical
+pub fn unified_diff(old: &str, new: &str, path: &str, context_lines: usize) -> String {
+    if old == new {
+        return String::new();
+    }
+
+    let path = path.trim_start_matches('/');
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(context_lines)
+        .header(&format!("a/{}", path), &format!("b/{}", path))
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unified_diff() {
+        let old = "one\ntwo\nthree\nfour\nfive\n";
+        let new = "one\ntwo\nTHREE\nfour\nfive\n";
+
+        let diff = unified_diff(old, new, "/src/file.txt", 1);
+        assert_eq!(
+            diff,
+            "--- a/src/file.txt\n+++ b/src/file.txt\n@@ -2,3 +2,3 @@\n two\n-three\n+THREE\n four\n"
+        );
+
+        assert!(unified_diff(old, old, "file.txt", 3).is_empty());
+    }
+}
@@ -0,0 +1,278 @@
+use encoding_rs::{Encoding, UTF_16BE, UTF_16LE, UTF_8, WINDOWS_1252};
+use std::fmt;
+use thiserror::Error;
+
+/// Number of leading bytes examined when guessing whether BOM-less content is UTF-16
+const UTF16_SAMPLE_LEN: usize = 4096;
+
+/// Number of leading bytes of a file its charset is detected from before it is rewritten
+pub const SAMPLE_LEN: usize = 64 * 1024;
+
+#[derive(Error, Debug)]
+pub enum EncodingError {
+    #[error("Unknown charset '{0}' (expected a name such as utf-8, utf-16le, utf-16be, windows-1252, iso-8859-15 or shift_jis)")]
+    UnknownCharset(String),
+
+    #[error("The content cannot be written as {charset}: '{character}' (line {line}) has no representation in it; pass charset \"utf-8\" to convert the file")]
+    Unmappable { charset: String, character: char, line: usize },
+}
+
+/// Character encoding of a text file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Charset {
+    pub encoding: &'static Encoding,
+    /// The file starts with a UTF-16 byte order mark; a UTF-8 one is kept in the decoded text instead
+    pub bom: bool,
+}
+
+impl Charset {
+    pub const UTF8: Charset = Charset { encoding: UTF_8, bom: false };
+
+    /// Parse a charset name or alias, e.g. "utf-16le", "latin1" or "cp1252"
+    ///
+    /// UTF-16 files written with an explicit charset get a byte order mark, without which they
+    /// could not be detected again.
+    pub fn parse(label: &str) -> Result<Self, EncodingError> {
+        let encoding = Encoding::for_label(label.trim().as_bytes())
+            .ok_or_else(|| EncodingError::UnknownCharset(label.to_string()))?;
+        Ok(Charset {
+            encoding,
+            bom: is_utf16(encoding),
+        })
+    }
+
+    /// Detect the encoding of a file's content
+    ///
+    /// A byte order mark decides first; otherwise the content is UTF-16 if every other byte
+    /// is mostly zero, UTF-8 if it decodes as UTF-8 (a character cut off at the end is
+    /// allowed, so a sample can be checked), and windows-1252 (a superset of Latin-1) otherwise.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The content, or a leading sample of it
+    ///
+    /// # Returns
+    ///
+    /// * `Charset` - The detected encoding
+    pub fn detect(bytes: &[u8]) -> Self {
+        if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+            return Charset {
+                encoding,
+                bom: is_utf16(encoding),
+            };
+        }
+
+        // Zero bytes are valid UTF-8, so UTF-16 is ruled out first
+        if let Some(encoding) = utf16_without_bom(bytes) {
+            return Charset { encoding, bom: false };
+        }
+
+        match std::str::from_utf8(bytes) {
+            Ok(_) => Charset::UTF8,
+            Err(e) if e.error_len().is_none() => Charset::UTF8,
+            Err(_) => Charset { encoding: WINDOWS_1252, bom: false },
+        }
+    }
+
+    /// Detect the encoding of a file's content, if the content leaves no real doubt about it
+    ///
+    /// Like `detect`, except that windows-1252 is only returned for content with no valid
+    /// multi-byte UTF-8 sequence in it: UTF-8 text with a damaged byte is not a legacy file.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The content, or a leading sample of it
+    ///
+    /// # Returns
+    ///
+    /// * `Option<Charset>` - The detected encoding, or None if it is only a guess
+    pub fn detect_confident(bytes: &[u8]) -> Option<Self> {
+        let charset = Charset::detect(bytes);
+        if charset.encoding == WINDOWS_1252 && has_utf8_sequence(bytes) {
+            return None;
+        }
+        Some(charset)
+    }
+
+    /// Charset to edit a file's content in, detected from its first `SAMPLE_LEN` bytes
+    ///
+    /// Content whose charset cannot be told reliably is edited as UTF-8, as is UTF-8 that only
+    /// goes bad past the sample; the warning then says that its malformed bytes get replaced.
+    ///
+    /// # Arguments
+    ///
+    /// * `bytes` - The whole content of the file
+    ///
+    /// # Returns
+    ///
+    /// * `(Charset, Option<String>)` - The charset, and a warning if decoding in it loses bytes
+    pub fn detect_for_edit(bytes: &[u8]) -> (Self, Option<String>) {
+        let charset = Charset::detect_confident(&bytes[..bytes.len().min(SAMPLE_LEN)]).unwrap_or(Charset::UTF8);
+        if !charset.is_utf8() || std::str::from_utf8(bytes).is_ok() {
+            return (charset, None);
+        }
+        let malformed = bytes.utf8_chunks().filter(|chunk| !chunk.invalid().is_empty()).count();
+        let warning = format!(
+            "The file is not valid UTF-8 and its charset could not be detected reliably; \
+             {} malformed byte sequence(s) were replaced with U+FFFD",
+            malformed
+        );
+        (charset, Some(warning))
+    }
+
+    pub fn is_utf8(&self) -> bool {
+        self.encoding == UTF_8
+    }
+
+    pub fn is_utf16(&self) -> bool {
+        is_utf16(self.encoding)
+    }
+
+    /// Decode content to UTF-8, replacing malformed sequences
+    pub fn decode(&self, bytes: &[u8]) -> String {
+        if self.is_utf8() {
+            return String::from_utf8_lossy(bytes).into_owned();
+        }
+        let body = match (self.bom, Encoding::for_bom(bytes)) {
+            (true, Some((_, bom_len))) => &bytes[bom_len..],
+            _ => bytes,
+        };
+        self.encoding.decode_without_bom_handling(body).0.into_owned()
+    }
+
+    /// Encode UTF-8 text in this charset, failing on characters it cannot represent
+    pub fn encode(&self, text: &str) -> Result<Vec<u8>, EncodingError> {
+        if self.is_utf8() {
+            return Ok(text.as_bytes().to_vec());
+        }
+
+        // encoding_rs only decodes UTF-16, so it is encoded here
+        if is_utf16(self.encoding) {
+            let big_endian = self.encoding == UTF_16BE;
+            let mut bytes = Vec::with_capacity(text.len() * 2 + 2);
+            let units = self.bom.then_some(0xFEFF).into_iter().chain(text.encode_utf16());
+            for unit in units {
+                bytes.extend_from_slice(&if big_endian { unit.to_be_bytes() } else { unit.to_le_bytes() });
+            }
+            return Ok(bytes);
+        }
+
+        let (bytes, _, had_errors) = self.encoding.encode(text);
+        if had_errors {
+            let mut buffer = [0; 4];
+            let (index, character) = text.char_indices()
+                .find(|(_, c)| self.encoding.encode(c.encode_utf8(&mut buffer)).2)
+                .unwrap_or((0, '\u{fffd}'));
+            return Err(EncodingError::Unmappable {
+                charset: self.to_string(),
+                character,
+                line: text[..index].matches('\n').count(),
+            });
+        }
+        Ok(bytes.into_owned())
+    }
+
+    /// The same charset without a byte order mark, for content that does not start the file
+    pub fn without_bom(&self) -> Self {
+        Charset { encoding: self.encoding, bom: false }
+    }
+}
+
+impl fmt::Display for Charset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.encoding.name())?;
+        if self.bom {
+            f.write_str(" with BOM")?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether content without a byte order mark looks like UTF-16 text, mostly ASCII characters
+/// whose high bytes are zero
+pub fn looks_like_utf16(bytes: &[u8]) -> bool {
+    Encoding::for_bom(bytes).is_some_and(|(encoding, _)| is_utf16(encoding)) || utf16_without_bom(bytes).is_some()
+}
+
+// Whether any non-ASCII character in the content is valid UTF-8
+fn has_utf8_sequence(bytes: &[u8]) -> bool {
+    bytes.utf8_chunks().any(|chunk| !chunk.valid().is_ascii())
+}
+
+fn is_utf16(encoding: &'static Encoding) -> bool {
+    encoding == UTF_16LE || encoding == UTF_16BE
+}
+
+fn utf16_without_bom(bytes: &[u8]) -> Option<&'static Encoding> {
+    let sample = &bytes[..bytes.len().min(UTF16_SAMPLE_LEN) & !1];
+    if sample.len() < 4 {
+        return None;
+    }
+    let units = sample.len() / 2;
+    let even_zeros = sample.iter().step_by(2).filter(|&&b| b == 0).count();
+    let odd_zeros = sample.iter().skip(1).step_by(2).filter(|&&b| b == 0).count();
+
+    // Nearly all high bytes zero, nearly no low bytes zero
+    if odd_zeros * 10 >= units * 7 && even_zeros * 20 <= units {
+        Some(UTF_16LE)
+    } else if even_zeros * 10 >= units * 7 && odd_zeros * 20 <= units {
+        Some(UTF_16BE)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_and_decode() {
+        assert_eq!(Charset::detect("héllo".as_bytes()), Charset::UTF8);
+        assert_eq!(Charset::detect(b"\xef\xbb\xbfhi").encoding, UTF_8);
+
+        let latin1 = b"caf\xe9\n";
+        let charset = Charset::detect(latin1);
+        assert_eq!(charset.encoding, WINDOWS_1252);
+        assert_eq!(charset.decode(latin1), "café\n");
+
+        let utf16 = b"\xff\xfeh\x00i\x00";
+        let charset = Charset::detect(utf16);
+        assert_eq!(charset.to_string(), "UTF-16LE with BOM");
+        assert_eq!(charset.decode(utf16), "hi");
+
+        let utf16be = b"\x00h\x00i\x00!\x00\n";
+        let charset = Charset::detect(utf16be);
+        assert_eq!(charset, Charset { encoding: UTF_16BE, bom: false });
+        assert_eq!(charset.decode(utf16be), "hi!\n");
+    }
+
+    #[test]
+    fn test_detect_confident() {
+        assert_eq!(Charset::detect_confident(b"caf\xe9\n").map(|c| c.encoding), Some(WINDOWS_1252));
+        assert_eq!(Charset::detect_confident("héllo".as_bytes()), Some(Charset::UTF8));
+
+        // UTF-8 with a damaged byte is not taken for windows-1252
+        assert_eq!(Charset::detect_confident(b"h\xc3\xa9llo \xff"), None);
+
+        // Edited as UTF-8 with a warning instead of being re-encoded as windows-1252
+        let (charset, warning) = Charset::detect_for_edit(b"h\xc3\xa9llo \xff");
+        assert_eq!(charset, Charset::UTF8);
+        assert!(warning.is_some());
+        assert_eq!(Charset::detect_for_edit(b"caf\xe9\n").0.encoding, WINDOWS_1252);
+    }
+
+    #[test]
+    fn test_encode_round_trip() {
+        let charset = Charset::detect(b"\xff\xfeh\x00i\x00");
+        assert_eq!(charset.encode("hé").unwrap(), b"\xff\xfeh\x00\xe9\x00");
+        assert_eq!(charset.without_bom().encode("h").unwrap(), b"h\x00");
+
+        let latin1 = Charset::parse("latin1").unwrap();
+        assert_eq!(latin1.encode("café").unwrap(), b"caf\xe9");
+        let err = latin1.encode("a\n€ ok\n日本").unwrap_err();
+        assert!(matches!(err, EncodingError::Unmappable { character: '日', line: 2, .. }));
+
+        assert!(Charset::parse("no-such-charset").is_err());
+    }
+}
@@ -0,0 +1,574 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use thiserror::Error;
+use tracing::{debug, warn};
+
+use crate::utils::atomic;
+use crate::utils::path::path_key;
+use crate::utils::precondition;
+use crate::utils::state;
+
+const HISTORY_FILE: &str = "history.json";
+const BLOBS_DIR: &str = "blobs";
+
+/// Files larger than this are not recorded in the journal
+pub const MAX_JOURNALED_FILE_SIZE: u64 = 16 * 1024 * 1024;
+
+#[derive(Error, Debug)]
+pub enum JournalError {
+    #[error("No history recorded for '{0}'")]
+    NoHistory(String),
+
+    #[error("Nothing to undo for '{0}'")]
+    NothingToUndo(String),
+
+    #[error("Nothing to redo for '{0}'")]
+    NothingToRedo(String),
+
+    #[error("Revision {revision} cannot be reached by this operation (current revision: {current})")]
+    InvalidRevision { revision: u64, current: u64 },
+
+    #[error("Conflict: '{path}' changed outside the journal since revision {revision} (expected {expected}, current {actual}); refusing to overwrite it")]
+    Conflict {
+        path: String,
+        revision: u64,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("Journal data for '{0}' is missing or corrupt")]
+    Corrupt(String),
+
+    #[error("IO error: {0}")]
+    IoError(#[from] io::Error),
+}
+
+/// One recorded change to a file
+///
+/// `before` and `after` are the SHA-256 hashes of the file contents, or `None`
+/// when the file did not exist.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Revision {
+    pub id: u64,
+    pub timestamp: DateTime<Utc>,
+    pub tool: String,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+/// The recorded revisions of one path
+///
+/// Revisions before `position` are applied; the ones from `position` on were
+/// undone and can be redone until the next recorded change discards them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FileHistory {
+    pub path: PathBuf,
+    pub revisions: Vec<Revision>,
+    pub position: usize,
+    next_id: u64,
+}
+
+impl FileHistory {
+    fn new(path: PathBuf) -> Self {
+        FileHistory {
+            path,
+            revisions: Vec::new(),
+            position: 0,
+            next_id: 1,
+        }
+    }
+
+    /// Id of the last applied revision, or 0 if every revision was undone
+    pub fn current_revision(&self) -> u64 {
+        match self.position {
+            0 => 0,
+            position => self.revisions[position - 1].id,
+        }
+    }
+
+    // Number of revisions applied once the file is at `revision`
+    fn position_of(&self, revision: u64) -> usize {
+        self.revisions.iter().take_while(|r| r.id <= revision).count()
+    }
+}
+
+/// Result of an undo or redo
+#[derive(Debug)]
+pub struct Restored {
+    /// Revisions undone or redone, in the order they were applied
+    pub revisions: Vec<Revision>,
+    /// Hash of the restored content, or `None` if the file was removed
+    pub hash: Option<String>,
+    /// Revision the file is at afterwards
+    pub current_revision: u64,
+    pub warnings: Vec<String>,
+}
+
+/// Edit journal holding before- and after-images of files changed by the mutating tools
+///
+/// Each path gets a directory (named by the hash of the path) in the journal
+/// directory, holding `history.json` and the file contents as blobs named by
+/// their hash, so identical contents are stored once.
+pub struct Journal {
+    dir: PathBuf,
+    max_revisions: usize,
+    guard: Mutex<()>,
+}
+
+impl Journal {
+    /// Create a journal stored in `dir`, keeping at most `max_revisions` revisions per file
+    pub fn new(dir: PathBuf, max_revisions: usize) -> Self {
+        Journal {
+            dir,
+            max_revisions: max_revisions.max(1),
+            guard: Mutex::new(()),
+        }
+    }
+
+    /// Default journal directory, private to the user and outside of any workspace
+    pub fn default_dir() -> PathBuf {
+        state::state_dir("journal")
+    }
+
+    /// Read the current contents of a path before changing it
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path about to be changed
+    ///
+    /// # Returns
+    ///
+    /// * `Some(None)` - The path does not exist
+    /// * `Some(Some(bytes))` - The contents of the file
+    /// * `None` - The path cannot be journaled (a directory, too large or unreadable)
+    pub fn capture(&self, path: &Path) -> Option<Option<Vec<u8>>> {
+        let metadata = match fs::symlink_metadata(path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Some(None),
+            Err(e) => {
+                warn!("Not journaling '{}': {}", path.display(), e);
+                return None;
+            }
+        };
+        if !metadata.is_file() || metadata.len() > MAX_JOURNALED_FILE_SIZE {
+            debug!("Not journaling '{}': not a regular file or too large", path.display());
+            return None;
+        }
+        match fs::read(path) {
+            Ok(bytes) => Some(Some(bytes)),
+            Err(e) => {
+                warn!("Not journaling '{}': {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// Record a change to a file
+    ///
+    /// Failures are logged and otherwise ignored, so journaling never fails the change itself.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The changed path
+    /// * `tool` - Name of the tool that made the change
+    /// * `before` - The contents captured with `capture` before the change
+    /// * `after` - The new contents, or `None` if the path was removed
+    pub fn record(&self, path: &Path, tool: &str, before: Option<Option<Vec<u8>>>, after: Option<&[u8]>) {
+        let Some(before) = before else {
+            return;
+        };
+        if after.is_some_and(|bytes| bytes.len() as u64 > MAX_JOURNALED_FILE_SIZE) {
+            return;
+        }
+        if let Err(e) = self.try_record(path, tool, before.as_deref(), after) {
+            warn!("Failed to journal change to '{}': {}", path.display(), e);
+        }
+    }
+
+    fn try_record(&self, path: &Path, tool: &str, before: Option<&[u8]>, after: Option<&[u8]>) -> Result<(), JournalError> {
+        let _guard = self.lock();
+        let key = path_key(path);
+        let entry_dir = self.entry_dir(&key)?;
+
+        let before_hash = before.map(|bytes| self.store_blob(&entry_dir, bytes)).transpose()?;
+        let after_hash = after.map(|bytes| self.store_blob(&entry_dir, bytes)).transpose()?;
+        if before_hash == after_hash {
+            return Ok(());
+        }
+
+        let mut history = self.load(&entry_dir)?.unwrap_or_else(|| FileHistory::new(key));
+
+        // A new change discards the revisions that were undone
+        history.revisions.truncate(history.position);
+        history.revisions.push(Revision {
+            id: history.next_id,
+            timestamp: Utc::now(),
+            tool: tool.to_string(),
+            before: before_hash,
+            after: after_hash,
+        });
+        history.next_id += 1;
+
+        let excess = history.revisions.len().saturating_sub(self.max_revisions);
+        history.revisions.drain(..excess);
+        history.position = history.revisions.len();
+
+        self.save(&entry_dir, &history)?;
+        self.collect_garbage(&entry_dir, &history);
+        debug!("Journaled revision {} of '{}'", history.current_revision(), path.display());
+        Ok(())
+    }
+
+    /// Get the recorded history of a path
+    pub fn history(&self, path: &Path) -> Result<FileHistory, JournalError> {
+        let _guard = self.lock();
+        let entry_dir = self.entry_dir(&path_key(path))?;
+        self.load(&entry_dir)?
+            .ok_or_else(|| JournalError::NoHistory(path.display().to_string()))
+    }
+
+    /// Plan an undo without changing anything
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to undo
+    /// * `revision` - Revision to go back to (0 for the state before the first one); one step if `None`
+    ///
+    /// # Returns
+    ///
+    /// * `Ok((revisions, hash))` - Revisions that would be undone and the hash of the content restored
+    pub fn plan_undo(&self, path: &Path, revision: Option<u64>) -> Result<(Vec<Revision>, Option<String>), JournalError> {
+        let history = self.history(path)?;
+        let target = self.undo_target(path, &history, revision)?;
+        self.check_current(path, &history, history.position)?;
+        let mut undone = history.revisions[target..history.position].to_vec();
+        undone.reverse();
+        Ok((undone, history.revisions[target].before.clone()))
+    }
+
+    /// Plan a redo without changing anything
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to redo
+    /// * `revision` - Revision to go forward to; one step if `None`
+    ///
+    /// # Returns
+    ///
+    /// * `Ok((revisions, hash))` - Revisions that would be redone and the hash of the content restored
+    pub fn plan_redo(&self, path: &Path, revision: Option<u64>) -> Result<(Vec<Revision>, Option<String>), JournalError> {
+        let history = self.history(path)?;
+        let target = self.redo_target(path, &history, revision)?;
+        self.check_current(path, &history, history.position)?;
+        let redone = history.revisions[history.position..target].to_vec();
+        Ok((redone, history.revisions[target - 1].after.clone()))
+    }
+
+    /// Restore a path to the content it had before a revision
+    ///
+    /// Refuses with `JournalError::Conflict` if the file no longer matches the
+    /// current revision, so changes made outside the journal are never lost.
+    pub fn undo(&self, path: &Path, revision: Option<u64>) -> Result<Restored, JournalError> {
+        let _guard = self.lock();
+        let entry_dir = self.entry_dir(&path_key(path))?;
+        let mut history = self.load(&entry_dir)?
+            .ok_or_else(|| JournalError::NoHistory(path.display().to_string()))?;
+
+        let target = self.undo_target(path, &history, revision)?;
+        self.check_current(path, &history, history.position)?;
+
+        let hash = history.revisions[target].before.clone();
+        let warnings = self.restore(path, &entry_dir, hash.as_deref())?;
+        let mut undone = history.revisions[target..history.position].to_vec();
+        undone.reverse();
+
+        history.position = target;
+        self.save(&entry_dir, &history)?;
+        Ok(Restored {
+            revisions: undone,
+            hash,
+            current_revision: history.current_revision(),
+            warnings,
+        })
+    }
+
+    /// Restore a path to the content it had after an undone revision
+    ///
+    /// Refuses with `JournalError::Conflict` if the file no longer matches the
+    /// current revision.
+    pub fn redo(&self, path: &Path, revision: Option<u64>) -> Result<Restored, JournalError> {
+        let _guard = self.lock();
+        let entry_dir = self.entry_dir(&path_key(path))?;
+        let mut history = self.load(&entry_dir)?
+            .ok_or_else(|| JournalError::NoHistory(path.display().to_string()))?;
+
+        let target = self.redo_target(path, &history, revision)?;
+        self.check_current(path, &history, history.position)?;
+
+        let hash = history.revisions[target - 1].after.clone();
+        let warnings = self.restore(path, &entry_dir, hash.as_deref())?;
+        let redone = history.revisions[history.position..target].to_vec();
+
+        history.position = target;
+        self.save(&entry_dir, &history)?;
+        Ok(Restored {
+            revisions: redone,
+            hash,
+            current_revision: history.current_revision(),
+            warnings,
+        })
+    }
+
+    // Position to undo to; it must be before the current one
+    fn undo_target(&self, path: &Path, history: &FileHistory, revision: Option<u64>) -> Result<usize, JournalError> {
+        if history.position == 0 {
+            return Err(JournalError::NothingToUndo(path.display().to_string()));
+        }
+        let target = match revision {
+            None => history.position - 1,
+            Some(revision) => history.position_of(revision),
+        };
+        if target >= history.position {
+            return Err(JournalError::InvalidRevision {
+                revision: revision.unwrap_or_default(),
+                current: history.current_revision(),
+            });
+        }
+        Ok(target)
+    }
+
+    // Position to redo to; it must be after the current one
+    fn redo_target(&self, path: &Path, history: &FileHistory, revision: Option<u64>) -> Result<usize, JournalError> {
+        if history.position == history.revisions.len() {
+            return Err(JournalError::NothingToRedo(path.display().to_string()));
+        }
+        let target = match revision {
+            None => history.position + 1,
+            Some(revision) => history.position_of(revision),
+        };
+        if target <= history.position {
+            return Err(JournalError::InvalidRevision {
+                revision: revision.unwrap_or_default(),
+                current: history.current_revision(),
+            });
+        }
+        Ok(target)
+    }
+
+    // Make sure the file still holds the content of the revision at `position`
+    fn check_current(&self, path: &Path, history: &FileHistory, position: usize) -> Result<(), JournalError> {
+        let expected = match position {
+            0 => history.revisions[0].before.clone(),
+            position => history.revisions[position - 1].after.clone(),
+        };
+        let actual = match fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.is_file() => Some(precondition::hash_file(path)?),
+            Ok(_) => Some("(not a file)".to_string()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        if actual != expected {
+            let describe = |hash: Option<String>| hash.unwrap_or_else(|| "no file".to_string());
+            return Err(JournalError::Conflict {
+                path: path.display().to_string(),
+                revision: history.current_revision(),
+                expected: describe(expected),
+                actual: describe(actual),
+            });
+        }
+        Ok(())
+    }
+
+    // Put the content with the given hash at `path`, or remove the file for `None`
+    fn restore(&self, path: &Path, entry_dir: &Path, hash: Option<&str>) -> Result<Vec<String>, JournalError> {
+        match hash {
+            Some(hash) => {
+                let contents = fs::read(entry_dir.join(BLOBS_DIR).join(hash))
+                    .map_err(|_| JournalError::Corrupt(path.display().to_string()))?;
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                Ok(atomic::write_atomic(path, &contents)?.warnings)
+            }
+            None => {
+                match fs::remove_file(path) {
+                    Ok(()) => {}
+                    Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                    Err(e) => return Err(e.into()),
+                }
+                Ok(Vec::new())
+            }
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, ()> {
+        self.guard.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    // The journal directory is checked on every use, so a directory replaced by another user is never read
+    fn entry_dir(&self, key: &Path) -> io::Result<PathBuf> {
+        state::ensure_private_dir(&self.dir)?;
+        Ok(self.dir.join(precondition::hash_bytes(key.to_string_lossy().as_bytes())))
+    }
+
+    fn store_blob(&self, entry_dir: &Path, bytes: &[u8]) -> io::Result<String> {
+        let hash = precondition::hash_bytes(bytes);
+        let blobs = entry_dir.join(BLOBS_DIR);
+        let blob = blobs.join(&hash);
+        if !blob.exists() {
+            state::ensure_private_dir(&blobs)?;
+            atomic::write_private(&blob, bytes)?;
+        }
+        Ok(hash)
+    }
+
+    fn load(&self, entry_dir: &Path) -> Result<Option<FileHistory>, JournalError> {
+        let contents = match fs::read(entry_dir.join(HISTORY_FILE)) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        serde_json::from_slice(&contents)
+            .map(Some)
+            .map_err(|_| JournalError::Corrupt(entry_dir.display().to_string()))
+    }
+
+    fn save(&self, entry_dir: &Path, history: &FileHistory) -> io::Result<()> {
+        state::ensure_private_dir(entry_dir)?;
+        let json = serde_json::to_vec_pretty(history).map_err(io::Error::other)?;
+        atomic::write_private(&entry_dir.join(HISTORY_FILE), &json)?;
+        Ok(())
+    }
+
+    // Remove blobs no longer referenced by any revision
+    fn collect_garbage(&self, entry_dir: &Path, history: &FileHistory) {
+        let referenced: HashSet<&str> = history.revisions
+            .iter()
+            .flat_map(|r| [r.before.as_deref(), r.after.as_deref()])
+            .flatten()
+            .collect();
+
+        let Ok(entries) = fs::read_dir(entry_dir.join(BLOBS_DIR)) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let name = entry.file_name();
+            if !referenced.contains(name.to_string_lossy().as_ref()) {
+                let _ = fs::remove_file(entry.path());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_journal_is_private() {
+        use std::os::unix::fs::PermissionsExt;
+        use walkdir::WalkDir;
+
+        let state = tempdir().unwrap();
+        let workspace = tempdir().unwrap();
+        let journal = Journal::new(state.path().join("journal"), 10);
+        let file = workspace.path().join("secret.txt");
+        fs::write(&file, "before").unwrap();
+
+        let before = journal.capture(&file);
+        fs::write(&file, "after").unwrap();
+        journal.record(&file, "write", before, Some(b"after"));
+
+        for entry in WalkDir::new(state.path().join("journal")).into_iter().flatten() {
+            let mode = entry.metadata().unwrap().permissions().mode() & 0o777;
+            assert_eq!(mode, if entry.file_type().is_dir() { 0o700 } else { 0o600 }, "{}", entry.path().display());
+        }
+
+        // A journal directory that is not the user's own is never read
+        let link = state.path().join("link");
+        std::os::unix::fs::symlink(state.path().join("journal"), &link).unwrap();
+        assert!(Journal::new(link, 10).history(&file).is_err());
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let journal_dir = tempdir().unwrap();
+        let workspace = tempdir().unwrap();
+        let journal = Journal::new(journal_dir.path().to_path_buf(), 10);
+        let file = workspace.path().join("a.txt");
+
+        // Create, then edit twice
+        let before = journal.capture(&file);
+        fs::write(&file, "one").unwrap();
+        journal.record(&file, "write", before, Some(b"one"));
+        for content in ["two", "three"] {
+            let before = journal.capture(&file);
+            fs::write(&file, content).unwrap();
+            journal.record(&file, "edit", before, Some(content.as_bytes()));
+        }
+        assert_eq!(journal.history(&file).unwrap().current_revision(), 3);
+
+        let restored = journal.undo(&file, None).unwrap();
+        assert_eq!(restored.current_revision, 2);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "two");
+
+        // Undo to before the first revision removes the created file
+        journal.undo(&file, Some(0)).unwrap();
+        assert!(!file.exists());
+
+        let restored = journal.redo(&file, Some(3)).unwrap();
+        assert_eq!(restored.revisions.len(), 3);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "three");
+        assert!(journal.redo(&file, None).is_err());
+    }
+
+    #[test]
+    fn test_undo_refuses_external_changes() {
+        let journal_dir = tempdir().unwrap();
+        let workspace = tempdir().unwrap();
+        let journal = Journal::new(journal_dir.path().to_path_buf(), 10);
+        let file = workspace.path().join("a.txt");
+        fs::write(&file, "one").unwrap();
+
+        let before = journal.capture(&file);
+        fs::write(&file, "two").unwrap();
+        journal.record(&file, "edit", before, Some(b"two"));
+
+        fs::write(&file, "changed elsewhere").unwrap();
+        assert!(matches!(journal.undo(&file, None), Err(JournalError::Conflict { .. })));
+        assert_eq!(fs::read_to_string(&file).unwrap(), "changed elsewhere");
+    }
+
+    #[test]
+    fn test_new_change_discards_redo_and_prunes() {
+        let journal_dir = tempdir().unwrap();
+        let workspace = tempdir().unwrap();
+        let journal = Journal::new(journal_dir.path().to_path_buf(), 2);
+        let file = workspace.path().join("a.txt");
+
+        for content in ["1", "2", "3"] {
+            let before = journal.capture(&file);
+            fs::write(&file, content).unwrap();
+            journal.record(&file, "write", before, Some(content.as_bytes()));
+        }
+        let history = journal.history(&file).unwrap();
+        assert_eq!(history.revisions.iter().map(|r| r.id).collect::<Vec<_>>(), vec![2, 3]);
+
+        journal.undo(&file, None).unwrap();
+        let before = journal.capture(&file);
+        fs::write(&file, "4").unwrap();
+        journal.record(&file, "write", before, Some(b"4"));
+
+        let history = journal.history(&file).unwrap();
+        assert_eq!(history.revisions.iter().map(|r| r.id).collect::<Vec<_>>(), vec![2, 4]);
+        assert_eq!(history.position, 2);
+    }
+}
@@ -0,0 +1,455 @@
+use chrono::{DateTime, Duration, Utc};
+use fs2::FileExt;
+use mcp_protocol::types::tool::{ToolCallResult, ToolContent};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use thiserror::Error;
+use tracing::{debug, warn};
+
+use crate::utils::path::path_key;
+use crate::utils::precondition;
+use crate::utils::state;
+
+const LOCK_EXTENSION: &str = "lock";
+
+#[derive(Error, Debug)]
+pub enum LockError {
+    #[error("'{}' is locked by session '{}' until {}", .0.path.display(), .0.session, .0.expires.to_rfc3339())]
+    Locked(LockInfo),
+
+    #[error("'{0}' is not locked")]
+    NotLocked(String),
+
+    #[error("'{}' is locked by session '{}', not by this session", .0.path.display(), .0.session)]
+    NotOwner(LockInfo),
+
+    #[error("IO error: {0}")]
+    IoError(#[from] io::Error),
+}
+
+/// A lease on a path, as stored in its lock file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LockInfo {
+    pub path: PathBuf,
+    pub session: String,
+    pub acquired: DateTime<Utc>,
+    pub expires: DateTime<Utc>,
+    /// Process holding the underlying flock
+    pub pid: u32,
+}
+
+impl LockInfo {
+    pub fn is_expired(&self) -> bool {
+        self.expires <= Utc::now()
+    }
+}
+
+// A lease held by this server, together with the open lock file carrying the flock
+struct HeldLock {
+    info: LockInfo,
+    file: File,
+}
+
+/// Advisory locks on paths, shared by every tool of a server
+///
+/// Each lock is an exclusive `flock` on a lock file in a directory shared by
+/// the user's servers (one file per locked path), so separate server
+/// processes exclude each other as well. Leases expire after their TTL; an expired lease no longer
+/// blocks other sessions and is released the next time it is looked at.
+pub struct LockManager {
+    dir: PathBuf,
+    held: Mutex<HashMap<PathBuf, HeldLock>>,
+}
+
+impl LockManager {
+    /// Create a lock manager keeping its lock files in `dir`
+    pub fn new(dir: PathBuf) -> Self {
+        LockManager {
+            dir,
+            held: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Default directory for lock files, shared by all of the user's servers and private to them
+    pub fn default_dir() -> PathBuf {
+        state::state_dir("locks")
+    }
+
+    /// Acquire or renew a lease on `path` for `session`
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The path to lock (it does not need to exist yet)
+    /// * `session` - Identifier of the caller holding the lease
+    /// * `ttl` - How long the lease lasts before it expires
+    ///
+    /// # Returns
+    ///
+    /// * `Ok(LockInfo)` - The acquired or renewed lease
+    /// * `Err(LockError::Locked)` - If another session holds a live lease
+    pub fn acquire(&self, path: &Path, session: &str, ttl: Duration) -> Result<LockInfo, LockError> {
+        let key = path_key(path);
+        let now = Utc::now();
+        let mut held = self.held();
+
+        if let Some(existing) = held.get_mut(&key) {
+            if existing.info.session != session && !existing.info.is_expired() {
+                return Err(LockError::Locked(existing.info.clone()));
+            }
+
+            // Renew our own lease, or take over an expired one
+            existing.info.session = session.to_string();
+            if existing.info.is_expired() {
+                existing.info.acquired = now;
+            }
+            existing.info.expires = now + ttl;
+            write_info(&mut existing.file, &existing.info)?;
+            debug!("Renewed lock on '{}' for session '{}'", key.display(), session);
+            return Ok(existing.info.clone());
+        }
+
+        state::ensure_private_dir(&self.dir)?;
+        let lock_path = self.lock_file_path(&key);
+        let mut file = match open_locked(&lock_path)? {
+            Some(file) => file,
+            None => {
+                // Held by another process; only an expired lease may be taken over
+                match read_info_at(&lock_path) {
+                    Some(info) if !info.is_expired() => return Err(LockError::Locked(info)),
+                    _ => {
+                        warn!("Taking over expired lock file '{}'", lock_path.display());
+                        let _ = fs::remove_file(&lock_path);
+                        match open_locked(&lock_path)? {
+                            Some(file) => file,
+                            None => {
+                                let info = read_info_at(&lock_path).unwrap_or_else(|| LockInfo {
+                                    path: key.clone(),
+                                    session: "unknown".to_string(),
+                                    acquired: now,
+                                    expires: now,
+                                    pid: 0,
+                                });
+                                return Err(LockError::Locked(info));
+                            }
+                        }
+                    }
+                }
+            }
+        };
+
+        let info = LockInfo {
+            path: key.clone(),
+            session: session.to_string(),
+            acquired: now,
+            expires: now + ttl,
+            pid: std::process::id(),
+        };
+        write_info(&mut file, &info)?;
+        held.insert(key.clone(), HeldLock { info: info.clone(), file });
+
+        debug!("Locked '{}' for session '{}'", key.display(), session);
+        Ok(info)
+    }
+
+    /// Release the lease on `path`
+    ///
+    /// Only the owning session may release a live lease unless `force` is set.
+    pub fn release(&self, path: &Path, session: &str, force: bool) -> Result<LockInfo, LockError> {
+        let key = path_key(path);
+        let mut held = self.held();
+
+        let owner = match held.get(&key) {
+            Some(existing) => existing.info.clone(),
+            None => {
+                // A lease held by another process cannot be released from here
+                return match self.probe(&key) {
+                    Some(info) => Err(LockError::Locked(info)),
+                    None => Err(LockError::NotLocked(key.display().to_string())),
+                };
+            }
+        };
+
+        if owner.session != session && !owner.is_expired() && !force {
+            return Err(LockError::NotOwner(owner));
+        }
+
+        if let Some(existing) = held.remove(&key) {
+            self.unlock_file(&key, existing.file);
+        }
+        debug!("Unlocked '{}'", key.display());
+        Ok(owner)
+    }
+
+    /// List all live leases, including those held by other server processes
+    pub fn list(&self) -> Result<Vec<LockInfo>, LockError> {
+        self.release_expired();
+
+        let mut locks: Vec<LockInfo> = self.held().values().map(|h| h.info.clone()).collect();
+
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(locks),
+            Err(e) => return Err(e.into()),
+        };
+
+        for entry in entries.flatten() {
+            let lock_path = entry.path();
+            if lock_path.extension().and_then(|e| e.to_str()) != Some(LOCK_EXTENSION) {
+                continue;
+            }
+            if let Some(info) = read_info_at(&lock_path) {
+                if locks.iter().any(|l| l.path == info.path) {
+                    continue;
+                }
+                if let Some(info) = self.probe(&info.path) {
+                    locks.push(info);
+                }
+            }
+        }
+
+        locks.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(locks)
+    }
+
+    /// Check that `session` may modify `path`
+    ///
+    /// Fails if `path`, a directory containing it, or anything below it when
+    /// it is a directory, is locked by a different session. Callers without a
+    /// session are refused by any live lease.
+    pub fn check(&self, path: &Path, session: Option<&str>) -> Result<(), LockError> {
+        let key = path_key(path);
+        let blocked = |info: &LockInfo| Some(info.session.as_str()) != session;
+
+        // Only the lock files of the path and its ancestors need to be looked at
+        self.release_expired();
+        for ancestor in key.ancestors() {
+            if let Some(info) = self.lookup(ancestor).filter(|info| blocked(info)) {
+                return Err(LockError::Locked(info));
+            }
+        }
+
+        // Leases below a directory cannot be found by name
+        if key.is_dir() {
+            if let Some(info) = self.list()?.into_iter().find(|info| info.path.starts_with(&key) && blocked(info)) {
+                return Err(LockError::Locked(info));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn held(&self) -> std::sync::MutexGuard<'_, HashMap<PathBuf, HeldLock>> {
+        self.held.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn lock_file_path(&self, key: &Path) -> PathBuf {
+        let name = precondition::hash_bytes(key.to_string_lossy().as_bytes());
+        self.dir.join(format!("{}.{}", name, LOCK_EXTENSION))
+    }
+
+    // Look up the live lease on exactly `key`, held by this server or another process
+    fn lookup(&self, key: &Path) -> Option<LockInfo> {
+        if let Some(existing) = self.held().get(key) {
+            return Some(existing.info.clone());
+        }
+        if !self.lock_file_path(key).exists() {
+            return None;
+        }
+        self.probe(key)
+    }
+
+    // Look up a lease held by another process, cleaning up stale lock files
+    fn probe(&self, key: &Path) -> Option<LockInfo> {
+        let lock_path = self.lock_file_path(key);
+        match open_locked(&lock_path) {
+            Ok(Some(file)) => {
+                // Nobody holds the flock, so the file is left over
+                self.unlock_file(key, file);
+                None
+            }
+            Ok(None) => read_info_at(&lock_path).filter(|info| !info.is_expired()),
+            Err(_) => None,
+        }
+    }
+
+    fn release_expired(&self) {
+        let mut held = self.held();
+        let expired: Vec<PathBuf> = held
+            .iter()
+            .filter(|(_, h)| h.info.is_expired())
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        for key in expired {
+            if let Some(existing) = held.remove(&key) {
+                debug!("Lock on '{}' expired", key.display());
+                self.unlock_file(&key, existing.file);
+            }
+        }
+    }
+
+    // Remove the lock file while still holding the flock, then drop it. A file
+    // that was already replaced (an expired lease taken over) is left alone.
+    fn unlock_file(&self, key: &Path, file: File) {
+        let lock_path = self.lock_file_path(key);
+        if is_same_file(&file, &lock_path) {
+            if let Err(e) = fs::remove_file(&lock_path) {
+                warn!("Failed to remove lock file '{}': {}", lock_path.display(), e);
+            }
+        }
+        let _ = file.unlock();
+    }
+}
+
+/// Build the tool result for a path that is locked by another session
+pub fn error_result(error: LockError) -> ToolCallResult {
+    let text = match &error {
+        LockError::Locked(_) => format!("{}. Wait for the lock to be released or to expire, then retry.", error),
+        _ => error.to_string(),
+    };
+
+    ToolCallResult {
+        content: vec![ToolContent::Text { text }],
+        is_error: Some(true),
+    }
+}
+
+// Open a lock file and take its flock without blocking
+//
+// Returns `None` if another open file holds the lock. The file is reopened if
+// it was replaced between opening and locking, so the flock always belongs to
+// the file currently at `lock_path`.
+fn open_locked(lock_path: &Path) -> io::Result<Option<File>> {
+    loop {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(lock_path)?;
+
+        if file.try_lock_exclusive().is_err() {
+            return Ok(None);
+        }
+
+        if is_same_file(&file, lock_path) {
+            return Ok(Some(file));
+        }
+        let _ = file.unlock();
+    }
+}
+
+#[cfg(unix)]
+fn is_same_file(file: &File, path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+
+    match (file.metadata(), fs::metadata(path)) {
+        (Ok(a), Ok(b)) => a.dev() == b.dev() && a.ino() == b.ino(),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_same_file(_file: &File, path: &Path) -> bool {
+    path.exists()
+}
+
+fn write_info(file: &mut File, info: &LockInfo) -> io::Result<()> {
+    let json = serde_json::to_vec(info).map_err(io::Error::other)?;
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(&json)?;
+    file.sync_data()
+}
+
+fn read_info_at(lock_path: &Path) -> Option<LockInfo> {
+    let mut contents = String::new();
+    File::open(lock_path).ok()?.read_to_string(&mut contents).ok()?;
+    serde_json::from_str(&contents).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_lock_conflicts_between_sessions() {
+        let lock_dir = tempdir().unwrap();
+        let work_dir = tempdir().unwrap();
+        let file = work_dir.path().join("a.txt");
+        fs::write(&file, "content").unwrap();
+
+        let locks = LockManager::new(lock_dir.path().to_path_buf());
+        locks.acquire(&file, "alice", Duration::seconds(60)).unwrap();
+
+        assert!(matches!(
+            locks.acquire(&file, "bob", Duration::seconds(60)),
+            Err(LockError::Locked(_))
+        ));
+        assert!(locks.check(&file, Some("alice")).is_ok());
+        assert!(matches!(locks.check(&file, Some("bob")), Err(LockError::Locked(_))));
+        assert!(matches!(locks.check(&file, None), Err(LockError::Locked(_))));
+
+        // Locks below a directory block changes to the directory itself
+        assert!(matches!(locks.check(work_dir.path(), Some("bob")), Err(LockError::Locked(_))));
+
+        // And a lock on a directory blocks changes to anything in it
+        let nested = work_dir.path().join("sub").join("b.txt");
+        locks.acquire(work_dir.path(), "alice", Duration::seconds(60)).unwrap();
+        assert!(matches!(locks.check(&nested, Some("bob")), Err(LockError::Locked(_))));
+        assert!(locks.check(&nested, Some("alice")).is_ok());
+        locks.release(work_dir.path(), "alice", false).unwrap();
+
+        assert!(matches!(locks.release(&file, "bob", false), Err(LockError::NotOwner(_))));
+        locks.release(&file, "alice", false).unwrap();
+        assert!(locks.check(&file, Some("bob")).is_ok());
+        assert!(locks.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_lock_shared_between_managers() {
+        let lock_dir = tempdir().unwrap();
+        let work_dir = tempdir().unwrap();
+        let file = work_dir.path().join("new.txt");
+
+        // Two managers stand in for two server processes sharing a lock directory
+        let first = LockManager::new(lock_dir.path().to_path_buf());
+        let second = LockManager::new(lock_dir.path().to_path_buf());
+
+        first.acquire(&file, "alice", Duration::seconds(60)).unwrap();
+        let listed = second.list().unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].session, "alice");
+        assert!(matches!(
+            second.acquire(&file, "bob", Duration::seconds(60)),
+            Err(LockError::Locked(_))
+        ));
+
+        // A directory lock held by the other manager is found through the file's ancestors
+        first.acquire(work_dir.path(), "alice", Duration::seconds(60)).unwrap();
+        assert!(matches!(second.check(&file, Some("bob")), Err(LockError::Locked(_))));
+        first.release(work_dir.path(), "alice", false).unwrap();
+
+        first.release(&file, "alice", false).unwrap();
+        second.acquire(&file, "bob", Duration::seconds(60)).unwrap();
+    }
+
+    #[test]
+    fn test_expired_lease_does_not_block() {
+        let lock_dir = tempdir().unwrap();
+        let work_dir = tempdir().unwrap();
+        let file = work_dir.path().join("a.txt");
+
+        let locks = LockManager::new(lock_dir.path().to_path_buf());
+        locks.acquire(&file, "alice", Duration::seconds(-1)).unwrap();
+
+        assert!(locks.check(&file, Some("bob")).is_ok());
+        let info = locks.acquire(&file, "bob", Duration::seconds(60)).unwrap();
+        assert_eq!(info.session, "bob");
+    }
+}
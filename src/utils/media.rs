@@ -0,0 +1,210 @@
+use image::imageops::FilterType;
+use image::{DynamicImage, ImageFormat, ImageResult};
+use std::io::Cursor;
+
+/// Smallest longest side an image is shrunk to while trying to fit a byte limit
+const MIN_DIMENSION: u32 = 16;
+
+/// Image formats recognized by their magic bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageType {
+    Png,
+    Jpeg,
+    Gif,
+    Webp,
+    Bmp,
+}
+
+impl ImageType {
+    /// Detect an image format from the first bytes of a file
+    pub fn detect(bytes: &[u8]) -> Option<Self> {
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            Some(ImageType::Png)
+        } else if bytes.starts_with(b"\xff\xd8\xff") {
+            Some(ImageType::Jpeg)
+        } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+            Some(ImageType::Gif)
+        } else if bytes.len() >= 12 && bytes.starts_with(b"RIFF") && &bytes[8..12] == b"WEBP" {
+            Some(ImageType::Webp)
+        } else if bytes.starts_with(b"BM") && bytes.len() >= 14 {
+            Some(ImageType::Bmp)
+        } else {
+            None
+        }
+    }
+
+    pub fn mime_type(&self) -> &'static str {
+        match self {
+            ImageType::Png => "image/png",
+            ImageType::Jpeg => "image/jpeg",
+            ImageType::Gif => "image/gif",
+            ImageType::Webp => "image/webp",
+            ImageType::Bmp => "image/bmp",
+        }
+    }
+
+    fn format(&self) -> ImageFormat {
+        match self {
+            ImageType::Png => ImageFormat::Png,
+            ImageType::Jpeg => ImageFormat::Jpeg,
+            ImageType::Gif => ImageFormat::Gif,
+            ImageType::Webp => ImageFormat::WebP,
+            ImageType::Bmp => ImageFormat::Bmp,
+        }
+    }
+}
+
+/// MIME type of a binary file, from its magic bytes
+///
+/// # Arguments
+///
+/// * `bytes` - The file content, or its first bytes
+///
+/// # Returns
+///
+/// * `&'static str` - The MIME type, "application/octet-stream" if it is not recognized
+pub fn mime_type(bytes: &[u8]) -> &'static str {
+    if let Some(image_type) = ImageType::detect(bytes) {
+        return image_type.mime_type();
+    }
+    const SIGNATURES: &[(&[u8], &str)] = &[
+        (b"%PDF-", "application/pdf"),
+        (b"PK\x03\x04", "application/zip"),
+        (b"\x1f\x8b", "application/gzip"),
+        (b"\0asm", "application/wasm"),
+        (b"\x7fELF", "application/x-elf"),
+        (b"OggS", "audio/ogg"),
+        (b"fLaC", "audio/flac"),
+        (b"ID3", "audio/mpeg"),
+        (b"SQLite format 3\0", "application/vnd.sqlite3"),
+    ];
+    SIGNATURES.iter()
+        .find(|(magic, _)| bytes.starts_with(magic))
+        .map_or("application/octet-stream", |(_, mime)| mime)
+}
+
+/// An image, re-encoded at a smaller size if it had to be
+#[derive(Debug)]
+pub struct FittedImage {
+    pub data: Vec<u8>,
+    pub image_type: ImageType,
+    pub width: u32,
+    pub height: u32,
+    pub original_width: u32,
+    pub original_height: u32,
+}
+
+impl FittedImage {
+    pub fn scaled(&self) -> bool {
+        self.width != self.original_width || self.height != self.original_height
+    }
+}
+
+/// Fit an image within a maximum width and height and a maximum encoded size
+///
+/// An image that already fits is returned as is. Otherwise it is scaled down, keeping its
+/// aspect ratio, and re-encoded as JPEG if it was one and PNG otherwise (animations keep
+/// only their first frame); if that is still too large it is shrunk further. An image that
+/// does not fit `max_bytes` even at `MIN_DIMENSION` pixels is returned at that size anyway,
+/// larger than `max_bytes`.
+///
+/// # Arguments
+///
+/// * `bytes` - The encoded image
+/// * `image_type` - Its format
+/// * `max_dimension` - Maximum width and height in pixels, if any
+/// * `max_bytes` - Maximum size of the encoded result
+///
+/// # Returns
+///
+/// * `ImageResult<FittedImage>` - The image, or the error decoding it
+pub fn fit_image(bytes: &[u8], image_type: ImageType, max_dimension: Option<u32>, max_bytes: u64) -> ImageResult<FittedImage> {
+    let (width, height) = image::ImageReader::with_format(Cursor::new(bytes), image_type.format()).into_dimensions()?;
+    let longest = width.max(height);
+    let mut target = max_dimension.map_or(longest, |max| max.clamp(1, longest));
+
+    if target == longest && bytes.len() as u64 <= max_bytes {
+        return Ok(FittedImage {
+            data: bytes.to_vec(),
+            image_type,
+            width,
+            height,
+            original_width: width,
+            original_height: height,
+        });
+    }
+
+    let image = image::load_from_memory_with_format(bytes, image_type.format())?;
+    let output_type = if image_type == ImageType::Jpeg { ImageType::Jpeg } else { ImageType::Png };
+    loop {
+        let scaled = if target < longest {
+            image.resize(target, target, FilterType::Triangle)
+        } else {
+            image.clone()
+        };
+        let data = encode(&scaled, output_type)?;
+        if data.len() as u64 <= max_bytes || target <= MIN_DIMENSION {
+            return Ok(FittedImage {
+                data,
+                image_type: output_type,
+                width: scaled.width(),
+                height: scaled.height(),
+                original_width: width,
+                original_height: height,
+            });
+        }
+        target = (target * 3 / 4).max(MIN_DIMENSION);
+    }
+}
+
+fn encode(image: &DynamicImage, image_type: ImageType) -> ImageResult<Vec<u8>> {
+    let mut data = Vec::new();
+    match image_type {
+        // JPEG has no alpha channel
+        ImageType::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8()).write_to(&mut Cursor::new(&mut data), ImageFormat::Jpeg)?,
+        _ => image.write_to(&mut Cursor::new(&mut data), image_type.format())?,
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgb, RgbImage};
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let image = RgbImage::from_fn(width, height, |x, y| Rgb([(x * 7) as u8, (y * 13) as u8, ((x ^ y) * 31) as u8]));
+        encode(&DynamicImage::ImageRgb8(image), ImageType::Png).unwrap()
+    }
+
+    #[test]
+    fn test_detect_types() {
+        assert_eq!(ImageType::detect(&png(2, 2)), Some(ImageType::Png));
+        assert_eq!(ImageType::detect(b"\xff\xd8\xff\xe0rest"), Some(ImageType::Jpeg));
+        assert_eq!(ImageType::detect(b"RIFF\0\0\0\0WEBPVP8 "), Some(ImageType::Webp));
+        assert_eq!(ImageType::detect(b"plain text"), None);
+        assert_eq!(mime_type(b"%PDF-1.7"), "application/pdf");
+        assert_eq!(mime_type(b"\0\x01\x02"), "application/octet-stream");
+    }
+
+    #[test]
+    fn test_fit_image() {
+        let bytes = png(400, 200);
+
+        // Already fits: returned unchanged
+        let fitted = fit_image(&bytes, ImageType::Png, None, 1 << 20).unwrap();
+        assert!(!fitted.scaled());
+        assert_eq!(fitted.data, bytes);
+
+        // Scaled to the maximum dimension, keeping the aspect ratio
+        let fitted = fit_image(&bytes, ImageType::Png, Some(100), 1 << 20).unwrap();
+        assert_eq!((fitted.width, fitted.height), (100, 50));
+        assert_eq!(ImageType::detect(&fitted.data), Some(ImageType::Png));
+
+        // Shrunk until it fits the byte limit
+        let fitted = fit_image(&bytes, ImageType::Png, None, 20_000).unwrap();
+        assert!(fitted.scaled());
+        assert!(fitted.data.len() <= 20_000);
+        assert_eq!(fitted.width, fitted.height * 2);
+    }
+}
@@ -1 +1,19 @@
+pub mod atomic;
+pub mod diff;
+pub mod encoding;
+pub mod journal;
+pub mod lock;
+pub mod media;
+pub mod patch;
 pub mod path;
+pub mod plan;
+pub mod precondition;
+pub mod result;
+pub mod scan;
+pub mod state;
+pub mod structured;
+pub mod syntax;
+pub mod tail;
+pub mod text;
+pub mod trash;
+pub mod validate;
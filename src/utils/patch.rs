@@ -0,0 +1,589 @@
+use serde::Serialize;
+use std::path::{Component, Path, PathBuf};
+use thiserror::Error;
+
+const NULL_PATH: &str = "/dev/null";
+
+#[derive(Error, Debug)]
+pub enum PatchError {
+    #[error("Invalid hunk header at line {line}: '{header}'")]
+    InvalidHunkHeader { line: usize, header: String },
+
+    #[error("Hunk at line {0} appears before any '---'/'+++' file header")]
+    HunkWithoutFile(usize),
+
+    #[error("Hunk at line {line} is truncated (expected {expected} more lines)")]
+    TruncatedHunk { line: usize, expected: usize },
+
+    #[error("Unexpected line in hunk at line {line}: '{text}'")]
+    UnexpectedLine { line: usize, text: String },
+
+    #[error("Patch does not contain any file changes")]
+    Empty,
+}
+
+/// A line of a hunk body
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HunkLine {
+    Context(String),
+    Remove(String),
+    Add(String),
+}
+
+/// A single `@@ -a,b +c,d @@` section of a unified diff
+#[derive(Debug, Clone)]
+pub struct Hunk {
+    pub header: String,
+    pub old_start: usize,
+    pub old_len: usize,
+    pub lines: Vec<HunkLine>,
+    /// The last line of the old side has no trailing newline
+    pub old_no_newline: bool,
+    /// The last line of the new side has no trailing newline
+    pub new_no_newline: bool,
+}
+
+impl Hunk {
+    fn old_lines(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter_map(|l| match l {
+                HunkLine::Context(s) | HunkLine::Remove(s) => Some(s.as_str()),
+                HunkLine::Add(_) => None,
+            })
+            .collect()
+    }
+
+    fn new_lines(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter_map(|l| match l {
+                HunkLine::Context(s) | HunkLine::Add(s) => Some(s.as_str()),
+                HunkLine::Remove(_) => None,
+            })
+            .collect()
+    }
+
+    fn leading_context(&self) -> usize {
+        self.lines.iter().take_while(|l| matches!(l, HunkLine::Context(_))).count()
+    }
+
+    fn trailing_context(&self) -> usize {
+        self.lines.iter().rev().take_while(|l| matches!(l, HunkLine::Context(_))).count()
+    }
+}
+
+/// The changes to one file in a (possibly multi-file) patch
+#[derive(Debug, Clone)]
+pub struct FilePatch {
+    /// Path from the `---` header, `None` for `/dev/null` (file creation)
+    pub old_path: Option<String>,
+    /// Path from the `+++` header, `None` for `/dev/null` (file deletion)
+    pub new_path: Option<String>,
+    pub hunks: Vec<Hunk>,
+}
+
+impl FilePatch {
+    pub fn is_creation(&self) -> bool {
+        self.old_path.is_none()
+    }
+
+    pub fn is_deletion(&self) -> bool {
+        self.new_path.is_none()
+    }
+
+    /// The path the patch applies to: the new path, or the old one for deletions
+    pub fn target_path(&self) -> Option<&str> {
+        self.new_path.as_deref().or(self.old_path.as_deref())
+    }
+}
+
+/// Tolerances used when locating hunks in the target file
+#[derive(Debug, Clone, Copy)]
+pub struct ApplyOptions {
+    /// Number of leading/trailing context lines that may be ignored
+    pub fuzz: usize,
+    /// How many lines away from the stated position a hunk may be found
+    pub max_offset: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HunkStatus {
+    Applied,
+    Rejected,
+}
+
+/// What happened to a single hunk
+#[derive(Debug, Clone, Serialize)]
+pub struct HunkReport {
+    /// 1-based index of the hunk within its file
+    pub index: usize,
+    pub header: String,
+    pub status: HunkStatus,
+    /// 0-based line in the patched file where the hunk starts
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub line: Option<usize>,
+    /// Lines between the stated and the actual position
+    pub offset: isize,
+    /// Context lines ignored to make the hunk apply
+    pub fuzz: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl HunkReport {
+    pub fn describe(&self) -> String {
+        match self.status {
+            HunkStatus::Applied => {
+                let mut text = format!(
+                    "Hunk {} {}: applied at line {}",
+                    self.index,
+                    self.header,
+                    self.line.unwrap_or_default()
+                );
+                if self.offset != 0 {
+                    text.push_str(&format!(" (offset {:+} lines)", self.offset));
+                }
+                if self.fuzz > 0 {
+                    text.push_str(&format!(" (fuzz {})", self.fuzz));
+                }
+                text
+            }
+            HunkStatus::Rejected => format!(
+                "Hunk {} {}: REJECTED: {}",
+                self.index,
+                self.header,
+                self.reason.as_deref().unwrap_or("did not apply")
+            ),
+        }
+    }
+}
+
+/// Result of applying a file's hunks to its content
+#[derive(Debug)]
+pub struct AppliedFile {
+    pub content: String,
+    pub reports: Vec<HunkReport>,
+}
+
+/// Parse a unified diff into per-file patches
+///
+/// Accepts the output of `diff -u` and `git diff`: extended git headers
+/// (`diff --git`, `index`, `new file mode`, ...) and any text between files
+/// are ignored. `/dev/null` on either side marks creation or deletion.
+///
+/// # Arguments
+///
+/// * `text` - The patch text
+///
+/// # Returns
+///
+/// * `Result<Vec<FilePatch>, PatchError>` - The patches in the order they appear
+pub fn parse(text: &str) -> Result<Vec<FilePatch>, PatchError> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut patches: Vec<FilePatch> = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let line = lines[i];
+
+        if line.starts_with("--- ") && i + 1 < lines.len() && lines[i + 1].starts_with("+++ ") {
+            patches.push(FilePatch {
+                old_path: header_path(&line[4..]),
+                new_path: header_path(&lines[i + 1][4..]),
+                hunks: Vec::new(),
+            });
+            i += 2;
+            continue;
+        }
+
+        if line.starts_with("@@") {
+            let file = patches.last_mut().ok_or(PatchError::HunkWithoutFile(i))?;
+            let (hunk, next) = parse_hunk(&lines, i)?;
+            file.hunks.push(hunk);
+            i = next;
+            continue;
+        }
+
+        i += 1;
+    }
+
+    if patches.is_empty() {
+        return Err(PatchError::Empty);
+    }
+    Ok(patches)
+}
+
+// Parse the hunk starting at `start`, returning it and the index of the next line
+fn parse_hunk(lines: &[&str], start: usize) -> Result<(Hunk, usize), PatchError> {
+    let header_line = lines[start];
+    let invalid = || PatchError::InvalidHunkHeader {
+        line: start,
+        header: header_line.to_string(),
+    };
+
+    // @@ -old_start[,old_len] +new_start[,new_len] @@ [section]
+    let inner = header_line
+        .strip_prefix("@@ ")
+        .and_then(|rest| rest.split(" @@").next())
+        .ok_or_else(invalid)?;
+    let mut ranges = inner.split_whitespace();
+    let (old_start, old_len) = ranges
+        .next()
+        .and_then(|r| r.strip_prefix('-'))
+        .and_then(parse_range)
+        .ok_or_else(invalid)?;
+    let (new_start, new_len) = ranges
+        .next()
+        .and_then(|r| r.strip_prefix('+'))
+        .and_then(parse_range)
+        .ok_or_else(invalid)?;
+
+    let mut hunk = Hunk {
+        header: format!("@@ -{},{} +{},{} @@", old_start, old_len, new_start, new_len),
+        old_start,
+        old_len,
+        lines: Vec::new(),
+        old_no_newline: false,
+        new_no_newline: false,
+    };
+
+    let mut old_remaining = old_len;
+    let mut new_remaining = new_len;
+    let mut i = start + 1;
+
+    while old_remaining > 0 || new_remaining > 0 {
+        let Some(line) = lines.get(i) else {
+            return Err(PatchError::TruncatedHunk {
+                line: start,
+                expected: old_remaining.max(new_remaining),
+            });
+        };
+
+        // Some tools strip the space from empty context lines
+        let (marker, text) = match line.chars().next() {
+            Some(c) => (c, &line[c.len_utf8()..]),
+            None => (' ', ""),
+        };
+
+        match marker {
+            ' ' if old_remaining > 0 && new_remaining > 0 => {
+                hunk.lines.push(HunkLine::Context(text.to_string()));
+                old_remaining -= 1;
+                new_remaining -= 1;
+            }
+            '-' if old_remaining > 0 => {
+                hunk.lines.push(HunkLine::Remove(text.to_string()));
+                old_remaining -= 1;
+            }
+            '+' if new_remaining > 0 => {
+                hunk.lines.push(HunkLine::Add(text.to_string()));
+                new_remaining -= 1;
+            }
+            '\\' => mark_no_newline(&mut hunk),
+            _ => {
+                return Err(PatchError::UnexpectedLine {
+                    line: i,
+                    text: line.to_string(),
+                });
+            }
+        }
+        i += 1;
+    }
+
+    // A "\ No newline at end of file" marker may follow the last line
+    if lines.get(i).is_some_and(|l| l.starts_with('\\')) {
+        mark_no_newline(&mut hunk);
+        i += 1;
+    }
+
+    Ok((hunk, i))
+}
+
+fn mark_no_newline(hunk: &mut Hunk) {
+    match hunk.lines.last() {
+        Some(HunkLine::Context(_)) => {
+            hunk.old_no_newline = true;
+            hunk.new_no_newline = true;
+        }
+        Some(HunkLine::Remove(_)) => hunk.old_no_newline = true,
+        Some(HunkLine::Add(_)) => hunk.new_no_newline = true,
+        None => {}
+    }
+}
+
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    match range.split_once(',') {
+        Some((start, len)) => Some((start.parse().ok()?, len.parse().ok()?)),
+        None => Some((range.parse().ok()?, 1)),
+    }
+}
+
+// Extract the path from a ---/+++ header, dropping any timestamp after a tab
+fn header_path(header: &str) -> Option<String> {
+    let path = header.split('\t').next().unwrap_or_default().trim_end();
+    let path = path
+        .strip_prefix('"')
+        .and_then(|p| p.strip_suffix('"'))
+        .unwrap_or(path);
+
+    if path == NULL_PATH {
+        None
+    } else {
+        Some(path.to_string())
+    }
+}
+
+/// Remove `strip` leading components from a patch path (like `patch -p`)
+///
+/// Returns `None` if the path has too few components or tries to escape its
+/// base directory with `..`.
+pub fn strip_path(path: &str, strip: usize) -> Option<PathBuf> {
+    let path = Path::new(path);
+    let mut components = path.components().peekable();
+
+    // Absolute paths are kept as-is
+    if path.is_absolute() {
+        if components.any(|c| c == Component::ParentDir) {
+            return None;
+        }
+        return Some(path.to_path_buf());
+    }
+
+    let mut stripped = PathBuf::new();
+    for (i, component) in components.enumerate() {
+        match component {
+            Component::Normal(part) if i >= strip => stripped.push(part),
+            Component::Normal(_) | Component::CurDir => {}
+            _ => return None,
+        }
+    }
+
+    if stripped.as_os_str().is_empty() {
+        None
+    } else {
+        Some(stripped)
+    }
+}
+
+/// Guess the `-p` level of a patch: 1 for git-style `a/` and `b/` prefixes, 0 otherwise
+pub fn default_strip(patches: &[FilePatch]) -> usize {
+    let prefixed = patches.iter().all(|p| {
+        p.old_path.as_deref().is_none_or(|path| path.starts_with("a/"))
+            && p.new_path.as_deref().is_none_or(|path| path.starts_with("b/"))
+    });
+    if prefixed {
+        1
+    } else {
+        0
+    }
+}
+
+/// Apply the hunks of a file patch to `original`
+///
+/// Each hunk is looked for at its stated position (adjusted by the changes
+/// of earlier hunks), then at increasing distances up to `max_offset`, then
+/// again with up to `fuzz` leading and trailing context lines ignored.
+/// Hunks that cannot be placed are reported as rejected and skipped.
+pub fn apply(original: &str, hunks: &[Hunk], options: &ApplyOptions) -> AppliedFile {
+    let eol = if original.contains("\r\n") { "\r\n" } else { "\n" };
+    let mut ends_with_newline = original.is_empty() || original.ends_with('\n');
+    let mut lines: Vec<String> = original.lines().map(|l| l.to_string()).collect();
+
+    let mut reports = Vec::new();
+    // Net number of lines added by the hunks applied so far
+    let mut shift: isize = 0;
+    // Offset of the previous hunk, which later hunks are likely to share
+    let mut last_offset: isize = 0;
+    // Hunks may not overlap text produced by earlier hunks
+    let mut min_pos = 0;
+
+    for (index, hunk) in hunks.iter().enumerate() {
+        let old = hunk.old_lines();
+        let new = hunk.new_lines();
+        let max_fuzz = options.fuzz.min(hunk.leading_context().max(hunk.trailing_context()));
+        // The header's 1-based start as a 0-based line; an empty old side starts after it
+        let base = if hunk.old_len == 0 { hunk.old_start } else { hunk.old_start.saturating_sub(1) };
+
+        let mut placed = None;
+        'fuzz: for fuzz in 0..=max_fuzz {
+            let front = fuzz.min(hunk.leading_context());
+            let back = fuzz.min(hunk.trailing_context());
+            if front + back > old.len() {
+                break;
+            }
+            let pattern = &old[front..old.len() - back];
+
+            let stated = base as isize + front as isize + shift;
+            let expected = stated + last_offset;
+
+            for distance in 0..=options.max_offset as isize {
+                for candidate in [expected + distance, expected - distance] {
+                    if candidate < min_pos as isize {
+                        continue;
+                    }
+                    let pos = candidate as usize;
+                    if pos + pattern.len() > lines.len() {
+                        continue;
+                    }
+                    if lines_match(&lines[pos..pos + pattern.len()], pattern) {
+                        placed = Some((pos, fuzz, front, back, pos as isize - stated));
+                        break 'fuzz;
+                    }
+                    if distance == 0 {
+                        break;
+                    }
+                }
+            }
+        }
+
+        let Some((pos, fuzz, front, back, offset)) = placed else {
+            reports.push(HunkReport {
+                index: index + 1,
+                header: hunk.header.clone(),
+                status: HunkStatus::Rejected,
+                line: None,
+                offset: 0,
+                fuzz: 0,
+                reason: Some(format!(
+                    "context not found within {} lines of line {} (fuzz {})",
+                    options.max_offset, base, max_fuzz
+                )),
+            });
+            continue;
+        };
+
+        let pattern_len = old.len() - front - back;
+        let replacement: Vec<String> = new[front..new.len() - back].iter().map(|l| l.to_string()).collect();
+        let replacement_len = replacement.len();
+        let touches_end = pos + pattern_len == lines.len() && back == 0;
+
+        lines.splice(pos..pos + pattern_len, replacement);
+        if touches_end {
+            ends_with_newline = !hunk.new_no_newline;
+        }
+
+        shift += replacement_len as isize - pattern_len as isize;
+        last_offset = offset;
+        min_pos = pos + replacement_len;
+
+        reports.push(HunkReport {
+            index: index + 1,
+            header: hunk.header.clone(),
+            status: HunkStatus::Applied,
+            line: Some(pos - front),
+            offset,
+            fuzz,
+            reason: None,
+        });
+    }
+
+    let mut content = lines.join(eol);
+    if ends_with_newline && !lines.is_empty() {
+        content.push_str(eol);
+    }
+
+    AppliedFile { content, reports }
+}
+
+// Compare lines ignoring carriage returns left over from CRLF patches
+fn lines_match(actual: &[String], expected: &[&str]) -> bool {
+    actual
+        .iter()
+        .zip(expected)
+        .all(|(a, e)| a.trim_end_matches('\r') == e.trim_end_matches('\r'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const OPTIONS: ApplyOptions = ApplyOptions { fuzz: 2, max_offset: 100 };
+
+    fn rejected(applied: &AppliedFile) -> usize {
+        applied.reports.iter().filter(|r| r.status == HunkStatus::Rejected).count()
+    }
+
+    #[test]
+    fn test_parse_multi_file_patch() {
+        let patch = concat!(
+            "diff --git a/src/a.txt b/src/a.txt\n",
+            "index 123..456 100644\n",
+            "--- a/src/a.txt\n",
+            "+++ b/src/a.txt\n",
+            "@@ -1,2 +1,2 @@\n",
+            " one\n",
+            "-two\n",
+            "+TWO\n",
+            "--- /dev/null\n",
+            "+++ b/new.txt\n",
+            "@@ -0,0 +1 @@\n",
+            "+created\n",
+            "\\ No newline at end of file\n",
+            "--- a/old.txt\t2024-01-01 00:00:00\n",
+            "+++ /dev/null\n",
+            "@@ -1 +0,0 @@\n",
+            "-gone\n",
+        );
+
+        let patches = parse(patch).unwrap();
+        assert_eq!(patches.len(), 3);
+        assert_eq!(default_strip(&patches), 1);
+
+        assert_eq!(patches[0].target_path(), Some("b/src/a.txt"));
+        assert_eq!(patches[0].hunks[0].lines.len(), 3);
+
+        assert!(patches[1].is_creation());
+        assert!(patches[1].hunks[0].new_no_newline);
+
+        assert!(patches[2].is_deletion());
+        assert_eq!(patches[2].old_path.as_deref(), Some("a/old.txt"));
+
+        assert_eq!(strip_path("b/src/a.txt", 1), Some(PathBuf::from("src/a.txt")));
+        assert_eq!(strip_path("a/../../etc/passwd", 1), None);
+    }
+
+    #[test]
+    fn test_apply_with_offset_and_fuzz() {
+        let original = "header\nextra\na\nb\nc\nd\ne\n";
+        let patch = "--- a/f\n+++ b/f\n@@ -1,5 +1,5 @@\n a\n b\n-c\n+C\n d\n X\n";
+        let patches = parse(patch).unwrap();
+
+        // Trailing context "X" does not match, and the hunk moved down two lines
+        let applied = apply(original, &patches[0].hunks, &OPTIONS);
+        assert_eq!(rejected(&applied), 0);
+        assert_eq!(applied.content, "header\nextra\na\nb\nC\nd\ne\n");
+        assert_eq!(applied.reports[0].line, Some(2));
+        assert_eq!(applied.reports[0].offset, 2);
+        assert_eq!(applied.reports[0].fuzz, 1);
+
+        let strict = apply(original, &patches[0].hunks, &ApplyOptions { fuzz: 0, max_offset: 100 });
+        assert_eq!(rejected(&strict), 1);
+        assert_eq!(strict.content, original);
+    }
+
+    #[test]
+    fn test_apply_preserves_line_endings() {
+        let original = "one\r\ntwo\r\nthree";
+        let patch = "--- a/f\n+++ b/f\n@@ -2,2 +2,2 @@\n two\n-three\n\\ No newline at end of file\n+THREE\n";
+        let patches = parse(patch).unwrap();
+
+        let applied = apply(original, &patches[0].hunks, &OPTIONS);
+        assert_eq!(applied.content, "one\r\ntwo\r\nTHREE\r\n");
+    }
+
+    #[test]
+    fn test_apply_creation_and_rejection() {
+        let patch = "--- /dev/null\n+++ b/new\n@@ -0,0 +1,2 @@\n+hello\n+world\n";
+        let patches = parse(patch).unwrap();
+        let applied = apply("", &patches[0].hunks, &OPTIONS);
+        assert_eq!(applied.content, "hello\nworld\n");
+
+        let patch = "--- a/f\n+++ b/f\n@@ -1,1 +1,1 @@\n-missing\n+replacement\n";
+        let patches = parse(patch).unwrap();
+        let applied = apply("other\n", &patches[0].hunks, &OPTIONS);
+        assert_eq!(rejected(&applied), 1);
+        assert_eq!(applied.content, "other\n");
+    }
+}
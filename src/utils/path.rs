@@ -1,8 +1,10 @@
 use std::io;
-use std::path::{Path, PathBuf};
+use std::path::{Component, Path, PathBuf};
 use thiserror::Error;
 use tracing::{debug, warn};
 
+use crate::utils::encoding;
+
 #[derive(Error, Debug)]
 pub enum PathError {
     #[error("Path is outside of all allowed directories")]
@@ -159,6 +161,25 @@ impl AllowedPaths {
         best_relative
     }
     
+    /// Get the allowed directory that contains a path
+    ///
+    /// When allowed directories are nested, the deepest one is returned.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The canonicalized path to look up
+    ///
+    /// # Returns
+    ///
+    /// * `Option<&Path>` - The containing allowed directory, if any
+    pub fn root_for(&self, path: &Path) -> Option<&Path> {
+        self.paths
+            .iter()
+            .filter(|allowed_path| path.starts_with(allowed_path))
+            .max_by_key(|allowed_path| allowed_path.components().count())
+            .map(|p| p.as_path())
+    }
+
     /// Get a list of all allowed directories
     ///
     /// # Returns
@@ -169,6 +190,39 @@ impl AllowedPaths {
     }
 }
 
+/// Normalize a path so the same file always maps to the same key, even before it exists
+///
+/// The deepest existing ancestor is canonicalized and the remaining components are appended.
+///
+/// # Arguments
+///
+/// * `path` - The path to normalize
+///
+/// # Returns
+///
+/// * `PathBuf` - The normalized path
+pub fn path_key(path: &Path) -> PathBuf {
+    let mut suffix = Vec::new();
+    let mut current = path;
+
+    loop {
+        if let Ok(canonical) = current.canonicalize() {
+            let mut key = canonical;
+            for component in suffix.iter().rev() {
+                key.push(component);
+            }
+            return key;
+        }
+        match (current.parent(), current.components().next_back()) {
+            (Some(parent), Some(Component::Normal(name))) => {
+                suffix.push(name.to_os_string());
+                current = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
 /// Check if a path is a possible binary file based on extension
 ///
 /// # Arguments
@@ -223,6 +277,11 @@ pub fn is_text_file(path: &Path) -> Result<bool, io::Error> {
         return Ok(true); // Empty files are considered text
     }
     
+    // UTF-16 text is full of null bytes, but is still text
+    if encoding::looks_like_utf16(&buffer[0..bytes_read]) {
+        return Ok(true);
+    }
+    
     // Check for null bytes or high density of non-ASCII characters
     let mut null_bytes = 0;
     let mut non_ascii = 0;
@@ -0,0 +1,236 @@
+use mcp_protocol::types::tool::{ToolCallResult, ToolContent};
+use serde::Serialize;
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::utils::path::AllowedPaths;
+
+/// A kind of filesystem change a mutating tool would make
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionKind {
+    CreateFile,
+    OverwriteFile,
+    AppendFile,
+    CreateDir,
+    Remove,
+    Trash,
+    Move,
+}
+
+impl ActionKind {
+    fn describe(&self) -> &'static str {
+        match self {
+            ActionKind::CreateFile => "create file",
+            ActionKind::OverwriteFile => "overwrite file",
+            ActionKind::AppendFile => "append to file",
+            ActionKind::CreateDir => "create directory",
+            ActionKind::Remove => "remove",
+            ActionKind::Trash => "move to trash",
+            ActionKind::Move => "move",
+        }
+    }
+}
+
+/// A single planned change
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedAction {
+    pub action: ActionKind,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bytes: Option<u64>,
+    /// Why this action would fail or be skipped, if it would
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub conflict: Option<String>,
+}
+
+/// The set of changes a mutating tool would make, reported by `dry_run`
+///
+/// Tools fill a plan using the same validation as a real run, then either
+/// return it as-is (dry run) or carry it out.
+#[derive(Debug, Serialize)]
+pub struct Plan {
+    pub tool: &'static str,
+    pub actions: Vec<PlannedAction>,
+    /// Extra information about the planned actions (e.g. per-hunk patch results)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub notes: Vec<String>,
+    /// Problems that are not tied to a single path (e.g. failed edit operations)
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+}
+
+impl Plan {
+    /// Create an empty plan for a tool
+    pub fn new(tool: &'static str) -> Self {
+        Plan {
+            tool,
+            actions: Vec::new(),
+            notes: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Record a change to a path
+    pub fn add(&mut self, action: ActionKind, path: &Path, bytes: Option<u64>) -> &mut PlannedAction {
+        self.actions.push(PlannedAction {
+            action,
+            path: path.display().to_string(),
+            source: None,
+            bytes,
+            conflict: None,
+        });
+        self.actions.last_mut().expect("action was just pushed")
+    }
+
+    /// Record a change that would not happen because of a conflict
+    pub fn add_conflict(&mut self, action: ActionKind, path: &Path, reason: impl Into<String>) {
+        self.add(action, path, None).conflict = Some(reason.into());
+    }
+
+    /// Record extra information shown with the planned actions
+    pub fn add_note(&mut self, note: impl Into<String>) {
+        self.notes.push(note.into());
+    }
+
+    /// Record a problem that is not tied to a single path
+    pub fn add_error(&mut self, error: impl Into<String>) {
+        self.errors.push(error.into());
+    }
+
+    /// Whether a real run would fail or be incomplete
+    pub fn has_problems(&self) -> bool {
+        !self.errors.is_empty() || self.actions.iter().any(|a| a.conflict.is_some())
+    }
+
+    /// Total number of bytes the planned actions would write
+    pub fn total_bytes(&self) -> u64 {
+        self.actions
+            .iter()
+            .filter(|a| a.conflict.is_none())
+            .filter_map(|a| a.bytes)
+            .sum()
+    }
+
+    /// Render the plan as a dry-run tool result
+    ///
+    /// Paths are shown relative to the allowed directories, as in the results of a real run.
+    pub fn into_result(mut self, allowed_paths: &AllowedPaths) -> ToolCallResult {
+        for action in &mut self.actions {
+            action.path = allowed_paths.closest_relative_path(Path::new(&action.path));
+            action.source = action.source.as_deref().map(|source| allowed_paths.closest_relative_path(Path::new(source)));
+        }
+
+        let mut text = format!("Dry run for {}: no changes were made\n", self.tool);
+        let conflicts = self.actions.iter().filter(|a| a.conflict.is_some()).count();
+
+        let _ = writeln!(
+            text,
+            "Planned actions: {} ({} bytes), conflicts: {}",
+            self.actions.len() - conflicts,
+            self.total_bytes(),
+            conflicts
+        );
+
+        for action in &self.actions {
+            let mut line = match &action.source {
+                Some(source) => format!("  - {} '{}' -> '{}'", action.action.describe(), source, action.path),
+                None => format!("  - {} '{}'", action.action.describe(), action.path),
+            };
+            if let Some(bytes) = action.bytes {
+                let _ = write!(line, " ({} bytes)", bytes);
+            }
+            if let Some(conflict) = &action.conflict {
+                let _ = write!(line, " [CONFLICT: {}]", conflict);
+            }
+            let _ = writeln!(text, "{}", line);
+        }
+
+        if !self.notes.is_empty() {
+            let _ = writeln!(text, "Details:");
+            for note in &self.notes {
+                let _ = writeln!(text, "  {}", note);
+            }
+        }
+
+        if !self.errors.is_empty() {
+            let _ = writeln!(text, "Errors:");
+            for error in &self.errors {
+                let _ = writeln!(text, "  - {}", error);
+            }
+        }
+
+        let is_error = self.has_problems();
+        if let Ok(json) = serde_json::to_string(&self) {
+            let _ = write!(text, "\nPlan: {}", json);
+        }
+
+        ToolCallResult {
+            content: vec![ToolContent::Text { text }],
+            is_error: Some(is_error),
+        }
+    }
+}
+
+/// Add a `create directory` action for every missing ancestor of `path` (outermost first)
+pub fn add_missing_dirs(plan: &mut Plan, path: &Path) {
+    let mut missing: Vec<&Path> = path.ancestors().take_while(|p| !p.as_os_str().is_empty() && !p.exists()).collect();
+    missing.reverse();
+    for dir in missing {
+        plan.add(ActionKind::CreateDir, dir, None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_plan_summary() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        let allowed_paths = AllowedPaths::new(vec![root.clone()]).unwrap();
+
+        let mut plan = Plan::new("copy");
+        plan.add(ActionKind::CreateFile, Path::new("/a/x.txt"), Some(10));
+        plan.add(ActionKind::OverwriteFile, &root.join("y.txt"), Some(5)).source = Some(root.join("b").join("y.txt").display().to_string());
+        plan.add_conflict(ActionKind::OverwriteFile, Path::new("/a/z.txt"), "exists");
+
+        assert!(plan.has_problems());
+        assert_eq!(plan.total_bytes(), 15);
+
+        // Paths inside the allowed directories are shown relative to them, others as they are
+        let result = plan.into_result(&allowed_paths);
+        assert_eq!(result.is_error, Some(true));
+        match &result.content[0] {
+            ToolContent::Text { text } => {
+                assert!(text.contains("Planned actions: 2 (15 bytes), conflicts: 1"));
+                assert!(text.contains("overwrite file 'b/y.txt' -> 'y.txt' (5 bytes)"));
+                assert!(text.contains("create file '/a/x.txt'"));
+                assert!(text.contains("[CONFLICT: exists]"));
+            }
+            _ => panic!("Expected text content"),
+        }
+    }
+
+    #[test]
+    fn test_add_missing_dirs() {
+        let temp_dir = tempdir().unwrap();
+        let target = temp_dir.path().join("a").join("b");
+
+        let mut plan = Plan::new("mkdir");
+        add_missing_dirs(&mut plan, &target);
+
+        let paths: Vec<_> = plan.actions.iter().map(|a| a.path.clone()).collect();
+        assert_eq!(
+            paths,
+            vec![
+                temp_dir.path().join("a").display().to_string(),
+                target.display().to_string()
+            ]
+        );
+    }
+}
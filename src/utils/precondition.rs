@@ -0,0 +1,233 @@
+use chrono::{DateTime, Utc};
+use mcp_protocol::types::tool::{ToolCallResult, ToolContent};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum PreconditionError {
+    #[error("Conflict: file content changed (expected hash {expected}, current hash {actual})")]
+    HashMismatch { expected: String, actual: String },
+
+    #[error("Conflict: file was modified at {modified}, after {since}")]
+    ModifiedSince { modified: String, since: String },
+
+    #[error("Conflict: file no longer exists")]
+    Missing,
+
+    #[error("Hash preconditions can only be used with files")]
+    NotAFile,
+
+    #[error("Invalid if_unmodified_since timestamp '{0}' (expected RFC 3339)")]
+    InvalidTimestamp(String),
+
+    #[error("IO error: {0}")]
+    IoError(#[from] io::Error),
+}
+
+impl PreconditionError {
+    /// Whether the error means the file changed, as opposed to a bad request
+    pub fn is_conflict(&self) -> bool {
+        matches!(
+            self,
+            PreconditionError::HashMismatch { .. }
+                | PreconditionError::ModifiedSince { .. }
+                | PreconditionError::Missing
+        )
+    }
+}
+
+/// Optimistic concurrency checks accepted by the mutating tools
+///
+/// Agents pass back the `hash` or `modified` value returned by `read` or `info`;
+/// the write is refused if the file has changed since.
+#[derive(Debug, Default)]
+pub struct Preconditions {
+    if_match_hash: Option<String>,
+    if_unmodified_since: Option<DateTime<Utc>>,
+}
+
+impl Preconditions {
+    /// Parse the `if_match_hash` and `if_unmodified_since` arguments of a tool call
+    pub fn from_args(args: &Value) -> Result<Self, PreconditionError> {
+        let if_match_hash = args
+            .get("if_match_hash")
+            .and_then(|v| v.as_str())
+            .map(normalize_hash);
+
+        let if_unmodified_since = match args.get("if_unmodified_since").and_then(|v| v.as_str()) {
+            Some(since) => Some(
+                DateTime::parse_from_rfc3339(since)
+                    .map_err(|_| PreconditionError::InvalidTimestamp(since.to_string()))?
+                    .with_timezone(&Utc),
+            ),
+            None => None,
+        };
+
+        Ok(Preconditions {
+            if_match_hash,
+            if_unmodified_since,
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.if_match_hash.is_none() && self.if_unmodified_since.is_none()
+    }
+
+    /// Check the preconditions against the current state of `path`
+    pub fn check(&self, path: &Path) -> Result<(), PreconditionError> {
+        if self.is_empty() {
+            return Ok(());
+        }
+
+        let metadata = match fs::metadata(path) {
+            Ok(m) => m,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Err(PreconditionError::Missing),
+            Err(e) => return Err(e.into()),
+        };
+
+        if let Some(since) = &self.if_unmodified_since {
+            let modified = DateTime::<Utc>::from(metadata.modified()?);
+            if modified > *since {
+                return Err(PreconditionError::ModifiedSince {
+                    modified: modified.to_rfc3339(),
+                    since: since.to_rfc3339(),
+                });
+            }
+        }
+
+        if let Some(expected) = &self.if_match_hash {
+            if !metadata.is_file() {
+                return Err(PreconditionError::NotAFile);
+            }
+            let actual = hash_file(path)?;
+            if actual != *expected {
+                return Err(PreconditionError::HashMismatch {
+                    expected: expected.clone(),
+                    actual,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Compute the content hash reported by `read` and `info` (hex SHA-256)
+pub fn hash_file(path: &Path) -> io::Result<String> {
+    hash_file_with(path, |_| {})
+}
+
+/// Compute a file's hash while passing each block read to `inspect`, so other
+/// statistics can be gathered in the same pass over the file
+pub fn hash_file_with<F: FnMut(&[u8])>(path: &Path, mut inspect: F) -> io::Result<String> {
+    let mut file = File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 64 * 1024];
+
+    loop {
+        let bytes_read = file.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+        inspect(&buffer[..bytes_read]);
+    }
+
+    Ok(to_hex(&hasher.finalize()))
+}
+
+/// Compute the content hash of in-memory data
+pub fn hash_bytes(data: &[u8]) -> String {
+    to_hex(&Sha256::digest(data))
+}
+
+/// Build the tool result for a failed precondition
+///
+/// Conflicts include the file's current hash and modification time so the
+/// caller can re-read and retry.
+pub fn error_result(error: PreconditionError, path: &Path) -> ToolCallResult {
+    let mut text = error.to_string();
+
+    if error.is_conflict() {
+        if let Ok(metadata) = fs::metadata(path) {
+            if let Ok(modified) = metadata.modified() {
+                text.push_str(&format!("\nCurrent modified: {}", DateTime::<Utc>::from(modified).to_rfc3339()));
+            }
+            if metadata.is_file() {
+                if let Ok(hash) = hash_file(path) {
+                    text.push_str(&format!("\nCurrent hash: {}", hash));
+                }
+            }
+        }
+        text.push_str("\nRe-read the file and retry the operation.");
+    }
+
+    ToolCallResult {
+        content: vec![ToolContent::Text { text }],
+        is_error: Some(true),
+    }
+}
+
+// Accept hashes with an optional "sha256:" prefix and any letter case
+fn normalize_hash(hash: &str) -> String {
+    let lower = hash.trim().to_ascii_lowercase();
+    match lower.strip_prefix("sha256:") {
+        Some(hex) => hex.to_string(),
+        None => lower,
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_hash_precondition() {
+        let temp_dir = tempdir().unwrap();
+        let file = temp_dir.path().join("a.txt");
+        fs::write(&file, "hello").unwrap();
+
+        let hash = hash_file(&file).unwrap();
+        assert_eq!(hash, hash_bytes(b"hello"));
+
+        let ok = Preconditions::from_args(&json!({ "if_match_hash": format!("SHA256:{}", hash.to_uppercase()) })).unwrap();
+        assert!(ok.check(&file).is_ok());
+
+        fs::write(&file, "changed").unwrap();
+        let err = ok.check(&file).unwrap_err();
+        assert!(err.is_conflict());
+        assert!(matches!(err, PreconditionError::HashMismatch { .. }));
+    }
+
+    #[test]
+    fn test_unmodified_since_precondition() {
+        let temp_dir = tempdir().unwrap();
+        let file = temp_dir.path().join("a.txt");
+        fs::write(&file, "hello").unwrap();
+
+        let modified = DateTime::<Utc>::from(fs::metadata(&file).unwrap().modified().unwrap());
+        let current = Preconditions::from_args(&json!({ "if_unmodified_since": modified.to_rfc3339() })).unwrap();
+        assert!(current.check(&file).is_ok());
+
+        let stale = Preconditions::from_args(&json!({ "if_unmodified_since": "2000-01-01T00:00:00Z" })).unwrap();
+        assert!(matches!(stale.check(&file), Err(PreconditionError::ModifiedSince { .. })));
+
+        fs::remove_file(&file).unwrap();
+        assert!(matches!(current.check(&file), Err(PreconditionError::Missing)));
+
+        assert!(matches!(
+            Preconditions::from_args(&json!({ "if_unmodified_since": "yesterday" })),
+            Err(PreconditionError::InvalidTimestamp(_))
+        ));
+    }
+}
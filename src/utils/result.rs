@@ -0,0 +1,9 @@
+use mcp_protocol::types::tool::{ToolCallResult, ToolContent};
+
+/// Build the tool result for a failed call from its message
+pub fn error_result(text: String) -> ToolCallResult {
+    ToolCallResult {
+        content: vec![ToolContent::Text { text }],
+        is_error: Some(true),
+    }
+}
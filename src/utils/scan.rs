@@ -0,0 +1,305 @@
+use encoding_rs::{Decoder, UTF_16BE, UTF_16LE, WINDOWS_1252};
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use crate::utils::encoding::Charset;
+use crate::utils::precondition;
+use crate::utils::text::{LineEnding, TextFormat, UTF8_BOM};
+
+/// Size of the blocks read while scanning or reading lines
+const BLOCK_SIZE: usize = 64 * 1024;
+
+/// Hash, charset, format and line count of a text file
+#[derive(Debug)]
+pub struct TextScan {
+    pub hash: String,
+    pub charset: Charset,
+    pub format: TextFormat,
+    pub lines: usize,
+}
+
+/// Scan a text file in one pass with fixed-size buffers
+///
+/// Without an explicit charset the encoding is detected as `Charset::detect` would on the
+/// whole file: from the first block, except that UTF-8 falls back to windows-1252 if any
+/// later block is not valid UTF-8.
+///
+/// # Arguments
+///
+/// * `path` - The file to scan
+/// * `explicit` - The file's charset, if known
+///
+/// # Returns
+///
+/// * `io::Result<TextScan>` - The file's hash, charset, format and line count
+pub fn scan_text(path: &Path, explicit: Option<Charset>) -> io::Result<TextScan> {
+    let mut detected = explicit;
+    let mut decoder: Option<Decoder> = None;
+    let mut replaced = false;
+    let mut stats = Stats::default();
+    let mut decoded = String::new();
+
+    let hash = precondition::hash_file_with(path, |block| {
+        let charset = *detected.get_or_insert_with(|| Charset::detect(block));
+        let decoder = decoder.get_or_insert_with(|| new_decoder(charset));
+        replaced |= decode_block(decoder, block, false, &mut decoded);
+        stats.add(&decoded);
+        decoded.clear();
+    })?;
+    if let Some(decoder) = decoder.as_mut() {
+        replaced |= decode_block(decoder, &[], true, &mut decoded);
+        stats.add(&decoded);
+    }
+
+    let mut charset = detected.unwrap_or(Charset::UTF8);
+    let mut format = stats.format();
+
+    // Invalid UTF-8 past the first block; windows-1252 agrees with it on line breaks
+    if explicit.is_none() && charset.is_utf8() && replaced {
+        charset = Charset { encoding: WINDOWS_1252, bom: false };
+        format.bom = false;
+    }
+
+    Ok(TextScan {
+        hash,
+        charset,
+        format,
+        lines: stats.lines(),
+    })
+}
+
+/// The bytes of a line break in a charset: LF, or its UTF-16 code unit
+pub fn newline(charset: Charset) -> &'static [u8] {
+    if charset.encoding == UTF_16LE {
+        b"\n\0"
+    } else if charset.encoding == UTF_16BE {
+        b"\0\n"
+    } else {
+        b"\n"
+    }
+}
+
+/// Length of the longest prefix of `bytes` that ends on a whole character
+pub fn char_boundary(bytes: &[u8], charset: Charset) -> usize {
+    if charset.is_utf16() {
+        let mut end = bytes.len() & !1;
+        // A high surrogate waits for its low half
+        let high_byte = |i: usize| if charset.encoding == UTF_16LE { bytes[i + 1] } else { bytes[i] };
+        if end >= 2 && (0xD8..0xDC).contains(&high_byte(end - 2)) {
+            end -= 2;
+        }
+        end
+    } else if charset.is_utf8() {
+        match std::str::from_utf8(bytes) {
+            Err(e) if e.error_len().is_none() => e.valid_up_to(),
+            _ => bytes.len(),
+        }
+    } else {
+        bytes.len()
+    }
+}
+
+/// Reads a text file line by line, keeping only as much of each line as asked for
+pub struct LineReader {
+    file: File,
+    newline: &'static [u8],
+    buffer: Vec<u8>,
+    pos: usize,
+    eof: bool,
+}
+
+impl LineReader {
+    /// Read lines from the current position of `file`, which must be at a line start
+    pub fn new(file: File, charset: Charset) -> Self {
+        LineReader {
+            file,
+            newline: newline(charset),
+            buffer: Vec::new(),
+            pos: 0,
+            eof: false,
+        }
+    }
+
+    /// Consume the next line, including its line break, appending at most `keep` of its bytes
+    /// to `line`
+    ///
+    /// # Returns
+    ///
+    /// * `io::Result<u64>` - The length of the whole line in bytes, 0 at the end of the file
+    pub fn next_line(&mut self, line: &mut Vec<u8>, keep: usize) -> io::Result<u64> {
+        let unit = self.newline.len();
+        let mut consumed = 0;
+        let mut kept = 0;
+        loop {
+            self.fill(unit)?;
+            let available = &self.buffer[self.pos..];
+            if available.is_empty() {
+                return Ok(consumed);
+            }
+
+            // A trailing partial code unit ends the file
+            let whole = (available.len() / unit * unit).max(available.len().min(unit));
+            let found = available[..whole].chunks(unit).position(|u| u == self.newline);
+            let len = found.map_or(whole, |i| (i + 1) * unit);
+
+            let taken = len.min(keep - kept);
+            line.extend_from_slice(&available[..taken]);
+            kept += taken;
+            self.pos += len;
+            consumed += len as u64;
+            if found.is_some() {
+                return Ok(consumed);
+            }
+        }
+    }
+
+    // Make at least one code unit available, unless the file has ended
+    fn fill(&mut self, unit: usize) -> io::Result<()> {
+        while self.buffer.len() - self.pos < unit && !self.eof {
+            self.buffer.drain(..self.pos);
+            self.pos = 0;
+            let start = self.buffer.len();
+            self.buffer.resize(start + BLOCK_SIZE, 0);
+            let read = self.file.read(&mut self.buffer[start..])?;
+            self.buffer.truncate(start + read);
+            self.eof = read == 0;
+        }
+        Ok(())
+    }
+}
+
+// Line break and first/last character counts of decoded text
+#[derive(Default)]
+struct Stats {
+    newlines: usize,
+    crlf: usize,
+    bom: bool,
+    started: bool,
+    last: Option<u8>,
+}
+
+impl Stats {
+    fn add(&mut self, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        if !self.started {
+            self.started = true;
+            self.bom = text.starts_with(UTF8_BOM);
+        }
+        let mut previous = self.last;
+        for &byte in text.as_bytes() {
+            if byte == b'\n' {
+                self.newlines += 1;
+                if previous == Some(b'\r') {
+                    self.crlf += 1;
+                }
+            }
+            previous = Some(byte);
+        }
+        self.last = previous;
+    }
+
+    fn format(&self) -> TextFormat {
+        let line_ending = match (self.newlines - self.crlf, self.crlf) {
+            (0, 0) => LineEnding::None,
+            (_, 0) => LineEnding::Lf,
+            (0, _) => LineEnding::Crlf,
+            _ => LineEnding::Mixed,
+        };
+        TextFormat {
+            line_ending,
+            bom: self.bom,
+            trailing_newline: self.last == Some(b'\n'),
+        }
+    }
+
+    // Lines as `str::lines` would split them: a final line without a line ending counts
+    fn lines(&self) -> usize {
+        match self.last {
+            Some(b'\n') | None => self.newlines,
+            Some(_) => self.newlines + 1,
+        }
+    }
+}
+
+// UTF-16 byte order marks are dropped from the text; a UTF-8 one is kept, as `Charset::decode` does
+fn new_decoder(charset: Charset) -> Decoder {
+    if charset.bom {
+        charset.encoding.new_decoder_with_bom_removal()
+    } else {
+        charset.encoding.new_decoder_without_bom_handling()
+    }
+}
+
+// Decode a block, returning whether malformed input was replaced
+fn decode_block(decoder: &mut Decoder, block: &[u8], last: bool, decoded: &mut String) -> bool {
+    decoded.reserve(decoder.max_utf8_buffer_length(block.len()).unwrap_or(block.len() * 3 + 16));
+    let (_, _, replaced) = decoder.decode_to_string(block, decoded, last);
+    replaced
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    fn scan(bytes: &[u8]) -> TextScan {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("file.txt");
+        fs::write(&path, bytes).unwrap();
+        scan_text(&path, None).unwrap()
+    }
+
+    #[test]
+    fn test_scan_text() {
+        assert_eq!(scan(b"").lines, 0);
+        assert_eq!(scan(b"a\nb").lines, 2);
+        assert_eq!(scan(b"\n\n").lines, 2);
+
+        let text_scan = scan(b"a\r\nb\r\n");
+        assert_eq!(text_scan.lines, 2);
+        assert_eq!(text_scan.format.line_ending, LineEnding::Crlf);
+        assert_eq!(text_scan.hash, precondition::hash_bytes(b"a\r\nb\r\n"));
+
+        // Invalid UTF-8 after the first block still makes the file windows-1252
+        let mut bytes = vec![b'x'; BLOCK_SIZE * 2];
+        bytes.extend_from_slice(b"\ncaf\xe9\n");
+        let text_scan = scan(&bytes);
+        assert_eq!(text_scan.charset.encoding, WINDOWS_1252);
+        assert_eq!(text_scan.lines, 2);
+
+        let text_scan = scan(b"\xff\xfea\x00\n\x00b\x00");
+        assert!(text_scan.charset.is_utf16());
+        assert_eq!(text_scan.lines, 2);
+        assert!(!text_scan.format.bom);
+    }
+
+    #[test]
+    fn test_line_reader() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("file.txt");
+        fs::write(&path, "first\nsecond line\nlast").unwrap();
+
+        let mut reader = LineReader::new(File::open(&path).unwrap(), Charset::UTF8);
+        let mut line = Vec::new();
+        assert_eq!(reader.next_line(&mut line, 0).unwrap(), 6);
+        assert!(line.is_empty());
+        assert_eq!(reader.next_line(&mut line, 3).unwrap(), 12);
+        assert_eq!(line, b"sec");
+        line.clear();
+        assert_eq!(reader.next_line(&mut line, 100).unwrap(), 4);
+        assert_eq!(line, b"last");
+        assert_eq!(reader.next_line(&mut line, 100).unwrap(), 0);
+
+        // UTF-16 line breaks are whole code units, not any 0x0A byte
+        fs::write(&path, b"\n\x01\n\x00x\x00").unwrap();
+        let charset = Charset { encoding: UTF_16LE, bom: false };
+        let mut reader = LineReader::new(File::open(&path).unwrap(), charset);
+        assert_eq!(reader.next_line(&mut Vec::new(), 0).unwrap(), 4);
+        assert_eq!(reader.next_line(&mut Vec::new(), 0).unwrap(), 2);
+        assert_eq!(char_boundary(b"a\x00\x3d\xd8", charset), 2);
+    }
+}
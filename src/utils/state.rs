@@ -0,0 +1,106 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const APP_DIR: &str = "fs-mcp-server";
+
+/// Directory for the server's own state, such as the edit journal and locks
+///
+/// Uses `$XDG_STATE_HOME/fs-mcp-server/<name>`, or `~/.local/state/fs-mcp-server/<name>`,
+/// falling back to a per-user directory in the system temp directory when neither is set.
+/// The directory is private to the user: see [`ensure_private_dir`].
+pub fn state_dir(name: &str) -> PathBuf {
+    let base = std::env::var_os("XDG_STATE_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| {
+            std::env::var_os("HOME")
+                .map(PathBuf::from)
+                .filter(|home| home.is_absolute())
+                .map(|home| home.join(".local").join("state"))
+        });
+    match base {
+        Some(base) => base.join(APP_DIR).join(name),
+        // A single level, so no directory on the way can belong to someone else
+        None => std::env::temp_dir().join(format!("{}-{}-{}", APP_DIR, current_uid(), name)),
+    }
+}
+
+/// Create a state directory readable only by the current user, or check an existing one
+///
+/// Missing directories are created with mode 0700. A directory owned by another user is
+/// refused, since whoever created it could read the state or plant entries in it.
+///
+/// # Arguments
+///
+/// * `dir` - The directory to use
+///
+/// # Returns
+///
+/// * `io::Result<()>` - An error if the directory cannot be created or is not the user's own
+pub fn ensure_private_dir(dir: &Path) -> io::Result<()> {
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+    builder.create(dir)?;
+    check_owner(dir)
+}
+
+/// The effective user id of the server process
+#[cfg(unix)]
+pub fn current_uid() -> u32 {
+    // SAFETY: geteuid has no preconditions and cannot fail
+    unsafe { libc::geteuid() }
+}
+
+#[cfg(not(unix))]
+pub fn current_uid() -> u32 {
+    0
+}
+
+#[cfg(unix)]
+fn check_owner(dir: &Path) -> io::Result<()> {
+    use std::os::unix::fs::MetadataExt;
+
+    // symlink_metadata, so a link planted in place of the directory is refused too
+    let metadata = fs::symlink_metadata(dir)?;
+    if !metadata.is_dir() || metadata.uid() != current_uid() {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("'{}' is not a directory owned by the current user; refusing to use it", dir.display()),
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn check_owner(_dir: &Path) -> io::Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[cfg(unix)]
+    #[test]
+    fn test_private_dir() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = tempdir().unwrap();
+        let dir = temp_dir.path().join("state").join("journal");
+        ensure_private_dir(&dir).unwrap();
+        assert_eq!(fs::metadata(&dir).unwrap().permissions().mode() & 0o777, 0o700);
+
+        // Existing directories are accepted again, a symlink to one is not
+        ensure_private_dir(&dir).unwrap();
+        let link = temp_dir.path().join("link");
+        std::os::unix::fs::symlink(&dir, &link).unwrap();
+        assert_eq!(ensure_private_dir(&link).unwrap_err().kind(), io::ErrorKind::PermissionDenied);
+    }
+}
@@ -0,0 +1,841 @@
+use serde_json::{Map, Value};
+use std::fmt;
+use std::path::Path;
+use thiserror::Error;
+use toml_edit::{Array, ArrayOfTables, DocumentMut, InlineTable, Item, Table};
+
+use crate::utils::text::{TextFormat, UTF8_BOM};
+
+#[derive(Error, Debug)]
+pub enum StructuredError {
+    #[error("Failed to parse {format}: {message}")]
+    Parse { format: DataFormat, message: String },
+
+    #[error("Invalid key path '{0}'")]
+    InvalidPath(String),
+
+    #[error("Key path not found: '{0}'")]
+    NotFound(String),
+
+    #[error("Cannot {operation} at '{path}': found {found}, expected {expected}")]
+    TypeMismatch {
+        operation: Operation,
+        path: String,
+        found: String,
+        expected: &'static str,
+    },
+
+    #[error("Invalid value: {0}")]
+    InvalidValue(String),
+
+    #[error("Failed to write {format}: {message}")]
+    Serialize { format: DataFormat, message: String },
+
+    #[error("The YAML document has comments, which are lost when it is re-serialized; pass allow_comment_loss=true to edit it anyway")]
+    CommentLoss,
+}
+
+/// A structured file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataFormat {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl DataFormat {
+    /// Parse a `format` argument ("json", "yaml" or "toml")
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "json" => Some(DataFormat::Json),
+            "yaml" | "yml" => Some(DataFormat::Yaml),
+            "toml" => Some(DataFormat::Toml),
+            _ => None,
+        }
+    }
+
+    /// Detect the format from a file extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        path.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Self::parse)
+    }
+}
+
+impl fmt::Display for DataFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            DataFormat::Json => "JSON",
+            DataFormat::Yaml => "YAML",
+            DataFormat::Toml => "TOML",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A change made at a key path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// Replace or add the value
+    Set,
+    /// Remove the key or array element
+    Delete,
+    /// Push the value onto the array, creating the array if missing
+    Append,
+    /// Merge an object into the existing one (JSON Merge Patch: `null` removes a key)
+    Merge,
+}
+
+impl Operation {
+    /// Parse an `operation` argument
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "set" => Some(Operation::Set),
+            "delete" => Some(Operation::Delete),
+            "append" => Some(Operation::Append),
+            "merge" => Some(Operation::Merge),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Operation::Set => "set",
+            Operation::Delete => "delete",
+            Operation::Append => "append",
+            Operation::Merge => "merge",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The edited document
+#[derive(Debug)]
+pub struct Edited {
+    pub content: String,
+    /// Formatting that could not be kept (e.g. YAML comments)
+    pub warnings: Vec<String>,
+}
+
+/// Split a key path into its segments
+///
+/// A path starting with `/` is a JSON Pointer (RFC 6901, `~1` for `/` and `~0` for `~`).
+/// Otherwise it is a dotted path such as `dependencies.serde.features[0]`, where
+/// `[n]` indexes an array and `\` escapes a `.`, `[` or `\` inside a key.
+/// An empty path (or `/`) addresses the whole document.
+///
+/// # Arguments
+///
+/// * `path` - The key path
+///
+/// # Returns
+///
+/// * `Result<Vec<String>, StructuredError>` - The keys and array indices, in order
+pub fn parse_key_path(path: &str) -> Result<Vec<String>, StructuredError> {
+    if path.is_empty() || path == "/" {
+        return Ok(Vec::new());
+    }
+
+    if let Some(pointer) = path.strip_prefix('/') {
+        return Ok(pointer
+            .split('/')
+            .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+            .collect());
+    }
+
+    let invalid = || StructuredError::InvalidPath(path.to_string());
+    let mut segments = Vec::new();
+    let mut current = String::new();
+    // Whether the previous segment was an index, so "a[0].b" does not add an empty key
+    let mut after_index = false;
+    let mut chars = path.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => current.push(chars.next().ok_or_else(invalid)?),
+            '.' => {
+                if current.is_empty() && !after_index {
+                    return Err(invalid());
+                }
+                if !current.is_empty() {
+                    segments.push(std::mem::take(&mut current));
+                }
+                after_index = false;
+            }
+            '[' => {
+                if !current.is_empty() {
+                    segments.push(std::mem::take(&mut current));
+                }
+                let index: String = chars.by_ref().take_while(|&c| c != ']').collect();
+                if index != "-" && index.parse::<usize>().is_err() {
+                    return Err(invalid());
+                }
+                segments.push(index);
+                after_index = true;
+            }
+            _ => {
+                if after_index && current.is_empty() {
+                    // A key must be separated from a preceding index by a dot
+                    return Err(invalid());
+                }
+                current.push(c);
+            }
+        }
+    }
+    if !current.is_empty() {
+        segments.push(current);
+    } else if !after_index {
+        return Err(invalid());
+    }
+
+    Ok(segments)
+}
+
+/// Apply an operation to a JSON, YAML or TOML document
+///
+/// TOML is edited in place, keeping comments and formatting. JSON keeps its key order
+/// and indentation. YAML is re-serialized, so its comments are lost: a commented YAML
+/// document is refused unless `allow_comment_loss` is set, and then the loss is reported
+/// as a warning. Line endings, BOM and trailing newline are kept for all formats.
+///
+/// # Arguments
+///
+/// * `content` - The document, empty for a new file
+/// * `format` - The format of the document
+/// * `path` - The key path segments, from `parse_key_path`
+/// * `operation` - The change to make
+/// * `value` - The value to set, append or merge (ignored for delete)
+/// * `allow_comment_loss` - Whether a YAML document may lose its comments
+///
+/// # Returns
+///
+/// * `Result<Edited, StructuredError>` - The edited document
+pub fn edit(
+    content: &str,
+    format: DataFormat,
+    path: &[String],
+    operation: Operation,
+    value: &Value,
+    allow_comment_loss: bool,
+) -> Result<Edited, StructuredError> {
+    let text_format = TextFormat::detect(content);
+    let body = content.strip_prefix(UTF8_BOM).unwrap_or(content);
+    let mut warnings = Vec::new();
+
+    let mut edited = match format {
+        DataFormat::Json => {
+            let mut document = if body.trim().is_empty() {
+                Value::Null
+            } else {
+                serde_json::from_str(body).map_err(|e| parse_error(format, e))?
+            };
+            apply_json(&mut document, path, operation, value)?;
+            write_json(&document, body).map_err(|e| serialize_error(format, e))?
+        }
+        DataFormat::Yaml => {
+            let mut document = if body.trim().is_empty() {
+                Value::Null
+            } else {
+                serde_yaml::from_str(body).map_err(|e| parse_error(format, e))?
+            };
+            apply_json(&mut document, path, operation, value)?;
+            if has_yaml_comments(body) {
+                if !allow_comment_loss {
+                    return Err(StructuredError::CommentLoss);
+                }
+                warnings.push("YAML comments are not preserved".to_string());
+            }
+            let mut yaml = serde_yaml::to_string(&document).map_err(|e| serialize_error(format, e))?;
+            if body.starts_with("---") {
+                yaml.insert_str(0, "---\n");
+            }
+            yaml
+        }
+        DataFormat::Toml => {
+            let mut document: DocumentMut = body.parse().map_err(|e| parse_error(format, e))?;
+            apply_toml(&mut document, path, operation, value)?;
+            document.to_string()
+        }
+    };
+
+    if content.is_empty() {
+        if !edited.ends_with('\n') {
+            edited.push('\n');
+        }
+    } else {
+        if text_format.bom {
+            edited.insert_str(0, UTF8_BOM);
+        }
+        text_format.restore(&mut edited);
+    }
+
+    Ok(Edited { content: edited, warnings })
+}
+
+// Whether a YAML document has a comment: a `#` starting a line or following whitespace,
+// outside a quoted scalar. Block scalars are not tracked, so a `#` in them counts too.
+fn has_yaml_comments(body: &str) -> bool {
+    body.lines().any(|line| {
+        let mut quote = None;
+        let mut escaped = false;
+        let mut previous = ' ';
+        for c in line.chars() {
+            match quote {
+                None if c == '#' && previous.is_whitespace() => return true,
+                None if (c == '"' || c == '\'') && (previous.is_whitespace() || "[{,".contains(previous)) => {
+                    quote = Some(c);
+                }
+                Some('"') if escaped => escaped = false,
+                Some('"') if c == '\\' => escaped = true,
+                Some(q) if c == q => quote = None,
+                _ => {}
+            }
+            previous = c;
+        }
+        false
+    })
+}
+
+fn parse_error(format: DataFormat, e: impl fmt::Display) -> StructuredError {
+    StructuredError::Parse { format, message: e.to_string() }
+}
+
+fn serialize_error(format: DataFormat, e: impl fmt::Display) -> StructuredError {
+    StructuredError::Serialize { format, message: e.to_string() }
+}
+
+// Render a path prefix as a JSON Pointer for error messages
+fn pointer(path: &[String]) -> String {
+    path.iter()
+        .map(|segment| format!("/{}", segment.replace('~', "~0").replace('/', "~1")))
+        .collect()
+}
+
+fn json_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+// Index of an array element; "-" (or the array length) addresses a new element at the end
+fn array_index(segment: &str, len: usize) -> Option<usize> {
+    if segment == "-" {
+        Some(len)
+    } else {
+        segment.parse::<usize>().ok().filter(|&index| index <= len)
+    }
+}
+
+fn apply_json(document: &mut Value, path: &[String], operation: Operation, value: &Value) -> Result<(), StructuredError> {
+    match operation {
+        Operation::Set => {
+            *json_target(document, path, 0, operation)? = value.clone();
+        }
+        Operation::Delete => {
+            let Some((last, parent_path)) = path.split_last() else {
+                return Err(StructuredError::InvalidPath("cannot delete the whole document".to_string()));
+            };
+            let parent = json_existing(document, parent_path)?;
+            let removed = match parent {
+                Value::Object(map) => map.shift_remove(last).is_some(),
+                Value::Array(items) => match last.parse::<usize>() {
+                    Ok(index) if index < items.len() => {
+                        items.remove(index);
+                        true
+                    }
+                    _ => false,
+                },
+                _ => false,
+            };
+            if !removed {
+                return Err(StructuredError::NotFound(pointer(path)));
+            }
+        }
+        Operation::Append => {
+            let target = json_target(document, path, 0, operation)?;
+            if target.is_null() {
+                *target = Value::Array(Vec::new());
+            }
+            match target {
+                Value::Array(items) => items.push(value.clone()),
+                other => return Err(mismatch(operation, path, json_type(other), "an array")),
+            }
+        }
+        Operation::Merge => {
+            if !value.is_object() {
+                return Err(StructuredError::InvalidValue("merge requires an object value".to_string()));
+            }
+            let target = json_target(document, path, 0, operation)?;
+            if !target.is_null() && !target.is_object() {
+                return Err(mismatch(operation, path, json_type(target), "an object"));
+            }
+            merge_patch(target, value);
+        }
+    }
+    Ok(())
+}
+
+fn mismatch(operation: Operation, path: &[String], found: impl Into<String>, expected: &'static str) -> StructuredError {
+    StructuredError::TypeMismatch {
+        operation,
+        path: pointer(path),
+        found: found.into(),
+        expected,
+    }
+}
+
+// Walk to the value at `path`, creating missing object keys and new array elements as null
+fn json_target<'a>(value: &'a mut Value, path: &[String], depth: usize, operation: Operation) -> Result<&'a mut Value, StructuredError> {
+    let Some(segment) = path.get(depth) else {
+        return Ok(value);
+    };
+    if value.is_null() {
+        *value = Value::Object(Map::new());
+    }
+    let child = match value {
+        Value::Object(map) => map.entry(segment.clone()).or_insert(Value::Null),
+        Value::Array(items) => match array_index(segment, items.len()) {
+            Some(index) => {
+                if index == items.len() {
+                    items.push(Value::Null);
+                }
+                &mut items[index]
+            }
+            None => return Err(StructuredError::NotFound(pointer(&path[..=depth]))),
+        },
+        other => return Err(mismatch(operation, &path[..depth], json_type(other), "an object or array")),
+    };
+    json_target(child, path, depth + 1, operation)
+}
+
+// Walk to an existing value
+fn json_existing<'a>(value: &'a mut Value, path: &[String]) -> Result<&'a mut Value, StructuredError> {
+    let mut current = value;
+    for (depth, segment) in path.iter().enumerate() {
+        let child = match current {
+            Value::Object(map) => map.get_mut(segment),
+            Value::Array(items) => segment.parse::<usize>().ok().and_then(|index| items.get_mut(index)),
+            _ => None,
+        };
+        current = child.ok_or_else(|| StructuredError::NotFound(pointer(&path[..=depth])))?;
+    }
+    Ok(current)
+}
+
+// JSON Merge Patch (RFC 7396)
+fn merge_patch(target: &mut Value, patch: &Value) {
+    let Value::Object(patch) = patch else {
+        *target = patch.clone();
+        return;
+    };
+    if !target.is_object() {
+        *target = Value::Object(Map::new());
+    }
+    if let Value::Object(map) = target {
+        for (key, value) in patch {
+            if value.is_null() {
+                map.shift_remove(key);
+            } else {
+                merge_patch(map.entry(key.clone()).or_insert(Value::Null), value);
+            }
+        }
+    }
+}
+
+// Serialize JSON with the indentation of the original document, or compactly if it was on one line
+fn write_json(document: &Value, original: &str) -> serde_json::Result<String> {
+    let original = original.trim();
+    if !original.is_empty() && !original.contains('\n') {
+        return serde_json::to_string(document);
+    }
+
+    let indent = original
+        .lines()
+        .skip(1)
+        .map(|line| &line[..line.len() - line.trim_start().len()])
+        .find(|indent| !indent.is_empty())
+        .unwrap_or("  ");
+
+    let mut out = Vec::new();
+    let formatter = serde_json::ser::PrettyFormatter::with_indent(indent.as_bytes());
+    let mut serializer = serde_json::Serializer::with_formatter(&mut out, formatter);
+    serde::Serialize::serialize(document, &mut serializer)?;
+    Ok(String::from_utf8(out).expect("serde_json produces UTF-8"))
+}
+
+fn apply_toml(document: &mut DocumentMut, path: &[String], operation: Operation, value: &Value) -> Result<(), StructuredError> {
+    let Some((last, parent_path)) = path.split_last() else {
+        return match operation {
+            Operation::Set => {
+                let Value::Object(map) = value else {
+                    return Err(StructuredError::InvalidValue("a TOML document must be a table".to_string()));
+                };
+                *document.as_table_mut() = toml_table(map)?;
+                Ok(())
+            }
+            Operation::Merge => {
+                let Value::Object(patch) = value else {
+                    return Err(StructuredError::InvalidValue("merge requires an object value".to_string()));
+                };
+                toml_merge(document.as_item_mut(), patch, path)
+            }
+            _ => Err(StructuredError::InvalidPath(format!("cannot {} the whole document", operation))),
+        };
+    };
+
+    let create = operation != Operation::Delete;
+    let parent = toml_target(document.as_item_mut(), parent_path, 0, create, operation)?;
+
+    match operation {
+        Operation::Set => toml_set(parent, last, value, path),
+        Operation::Delete => {
+            let removed = if let Some(table) = parent.as_table_like_mut() {
+                table.remove(last).is_some()
+            } else if let Some(array) = parent.as_array_mut() {
+                match last.parse::<usize>() {
+                    Ok(index) if index < array.len() => {
+                        array.remove(index);
+                        true
+                    }
+                    _ => false,
+                }
+            } else if let Some(tables) = parent.as_array_of_tables_mut() {
+                match last.parse::<usize>() {
+                    Ok(index) if index < tables.len() => {
+                        tables.remove(index);
+                        true
+                    }
+                    _ => false,
+                }
+            } else {
+                false
+            };
+            if !removed {
+                return Err(StructuredError::NotFound(pointer(path)));
+            }
+            Ok(())
+        }
+        Operation::Append => {
+            let target = toml_child(parent, last, operation, path)?;
+            if let Some(tables) = target.as_array_of_tables_mut() {
+                let Value::Object(map) = value else {
+                    return Err(mismatch(operation, path, "an array of tables", "an object value"));
+                };
+                tables.push(toml_table(map)?);
+            } else if let Some(array) = target.as_array_mut() {
+                array.push(toml_value(value)?);
+            } else {
+                return Err(mismatch(operation, path, target.type_name(), "an array"));
+            }
+            Ok(())
+        }
+        Operation::Merge => {
+            let Value::Object(patch) = value else {
+                return Err(StructuredError::InvalidValue("merge requires an object value".to_string()));
+            };
+            let target = toml_child(parent, last, operation, path)?;
+            toml_merge(target, patch, path)
+        }
+    }
+}
+
+// Walk to the item at `path`, creating missing tables (standard tables under standard
+// tables, inline tables inside inline tables) when `create` is set
+fn toml_target<'a>(
+    item: &'a mut Item,
+    path: &[String],
+    depth: usize,
+    create: bool,
+    operation: Operation,
+) -> Result<&'a mut Item, StructuredError> {
+    let Some(segment) = path.get(depth) else {
+        return Ok(item);
+    };
+
+    let child = if item.is_table() {
+        let table = item.as_table_mut().expect("checked above");
+        if !table.contains_key(segment) {
+            if !create {
+                return Err(StructuredError::NotFound(pointer(&path[..=depth])));
+            }
+            let mut new_table = Table::new();
+            new_table.set_implicit(true);
+            table.insert(segment, Item::Table(new_table));
+        }
+        table.get_mut(segment).expect("key was just checked or inserted")
+    } else if item.is_inline_table() {
+        let table = item.as_inline_table_mut().expect("checked above");
+        if !table.contains_key(segment) {
+            if !create {
+                return Err(StructuredError::NotFound(pointer(&path[..=depth])));
+            }
+            table.insert(segment, toml_edit::Value::InlineTable(InlineTable::new()));
+        }
+        toml_edit::TableLike::get_mut(table, segment).expect("key was just checked or inserted")
+    } else if item.is_array() || item.is_array_of_tables() {
+        let index = segment.parse::<usize>().map_err(|_| StructuredError::NotFound(pointer(&path[..=depth])))?;
+        item.get_mut(index).ok_or_else(|| StructuredError::NotFound(pointer(&path[..=depth])))?
+    } else {
+        return Err(mismatch(operation, &path[..depth], item.type_name(), "a table or array"));
+    };
+
+    toml_target(child, path, depth + 1, create, operation)
+}
+
+// The item at `key` of a table or array; a missing table key is created as an empty
+// array (append) or table (merge)
+fn toml_child<'a>(parent: &'a mut Item, key: &str, operation: Operation, path: &[String]) -> Result<&'a mut Item, StructuredError> {
+    let not_found = || StructuredError::NotFound(pointer(path));
+    if parent.is_table() {
+        let table = parent.as_table_mut().expect("checked above");
+        return Ok(table.entry(key).or_insert_with(|| match operation {
+            Operation::Merge => Item::Table(Table::new()),
+            _ => Item::Value(toml_edit::Value::Array(Array::new())),
+        }));
+    }
+    if parent.is_inline_table() {
+        let table = parent.as_inline_table_mut().expect("checked above");
+        if !table.contains_key(key) {
+            let empty = match operation {
+                Operation::Merge => toml_edit::Value::InlineTable(InlineTable::new()),
+                _ => toml_edit::Value::Array(Array::new()),
+            };
+            table.insert(key, empty);
+        }
+        return toml_edit::TableLike::get_mut(table, key).ok_or_else(not_found);
+    }
+    if parent.is_array() || parent.is_array_of_tables() {
+        let index = key.parse::<usize>().map_err(|_| not_found())?;
+        return parent.get_mut(index).ok_or_else(not_found);
+    }
+    Err(mismatch(operation, &path[..path.len() - 1], parent.type_name(), "a table or array"))
+}
+
+// Set `key` of a table or element `key` of an array, keeping the decoration (comments,
+// spacing) of a replaced value
+fn toml_set(parent: &mut Item, key: &str, value: &Value, path: &[String]) -> Result<(), StructuredError> {
+    if let Some(table) = parent.as_table_mut() {
+        let existing = table.get(key);
+        let item = match value {
+            // New tables are standard tables at the top level ([package]) and inline below it
+            // (serde = { ... }); existing tables keep their style
+            Value::Object(map) if existing.map_or(path.len() == 1, |item| item.is_table()) => {
+                Item::Table(toml_table(map)?)
+            }
+            Value::Array(items) if existing.is_some_and(|item| item.is_array_of_tables()) => {
+                let mut tables = ArrayOfTables::new();
+                for item in items {
+                    let Value::Object(map) = item else {
+                        return Err(StructuredError::InvalidValue("an array of tables can only hold objects".to_string()));
+                    };
+                    tables.push(toml_table(map)?);
+                }
+                Item::ArrayOfTables(tables)
+            }
+            _ => {
+                let mut new_value = toml_value(value)?;
+                if let Some(old) = existing.and_then(|item| item.as_value()) {
+                    *new_value.decor_mut() = old.decor().clone();
+                }
+                Item::Value(new_value)
+            }
+        };
+        table.insert(key, item);
+        return Ok(());
+    }
+
+    if let Some(table) = parent.as_inline_table_mut() {
+        let mut new_value = toml_value(value)?;
+        if let Some(old) = table.get(key) {
+            *new_value.decor_mut() = old.decor().clone();
+        }
+        table.insert(key, new_value);
+        return Ok(());
+    }
+
+    if let Some(array) = parent.as_array_mut() {
+        let index = array_index(key, array.len()).ok_or_else(|| StructuredError::NotFound(pointer(path)))?;
+        let new_value = toml_value(value)?;
+        if index == array.len() {
+            array.push(new_value);
+        } else {
+            array.replace(index, new_value);
+        }
+        return Ok(());
+    }
+
+    if let Some(tables) = parent.as_array_of_tables_mut() {
+        let Value::Object(map) = value else {
+            return Err(StructuredError::InvalidValue("an array of tables can only hold objects".to_string()));
+        };
+        let index = array_index(key, tables.len()).ok_or_else(|| StructuredError::NotFound(pointer(path)))?;
+        let table = toml_table(map)?;
+        if index == tables.len() {
+            tables.push(table);
+        } else {
+            *tables.get_mut(index).expect("index is in bounds") = table;
+        }
+        return Ok(());
+    }
+
+    Err(mismatch(Operation::Set, &path[..path.len() - 1], parent.type_name(), "a table or array"))
+}
+
+// Merge an object into a table, recursing into nested tables; null removes a key
+fn toml_merge(target: &mut Item, patch: &Map<String, Value>, path: &[String]) -> Result<(), StructuredError> {
+    if !target.is_table_like() {
+        return Err(mismatch(Operation::Merge, path, target.type_name(), "a table"));
+    }
+    for (key, value) in patch {
+        let mut child_path = path.to_vec();
+        child_path.push(key.clone());
+        let table = target.as_table_like_mut().expect("checked above");
+        if value.is_null() {
+            table.remove(key);
+            continue;
+        }
+        match (value, table.get_mut(key)) {
+            (Value::Object(nested), Some(child)) if child.is_table_like() => {
+                toml_merge(child, nested, &child_path)?;
+            }
+            _ => toml_set(target, key, value, &child_path)?,
+        }
+    }
+    Ok(())
+}
+
+fn toml_table(map: &Map<String, Value>) -> Result<Table, StructuredError> {
+    let mut table = Table::new();
+    for (key, value) in map {
+        table.insert(key, Item::Value(toml_value(value)?));
+    }
+    Ok(table)
+}
+
+fn toml_value(value: &Value) -> Result<toml_edit::Value, StructuredError> {
+    Ok(match value {
+        Value::Null => return Err(StructuredError::InvalidValue("TOML has no null value".to_string())),
+        Value::Bool(b) => (*b).into(),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => i.into(),
+            None => n.as_f64()
+                .ok_or_else(|| StructuredError::InvalidValue(format!("number {} does not fit in TOML", n)))?
+                .into(),
+        },
+        Value::String(s) => s.as_str().into(),
+        Value::Array(items) => {
+            let mut array = Array::new();
+            for item in items {
+                array.push(toml_value(item)?);
+            }
+            toml_edit::Value::Array(array)
+        }
+        Value::Object(map) => {
+            let mut table = InlineTable::new();
+            for (key, value) in map {
+                table.insert(key, toml_value(value)?);
+            }
+            toml_edit::Value::InlineTable(table)
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn edit_str(content: &str, format: DataFormat, path: &str, operation: Operation, value: Value) -> String {
+        let path = parse_key_path(path).unwrap();
+        edit(content, format, &path, operation, &value, false).unwrap().content
+    }
+
+    #[test]
+    fn test_parse_key_path() {
+        assert_eq!(parse_key_path("a.b[0].c").unwrap(), vec!["a", "b", "0", "c"]);
+        assert_eq!(parse_key_path("/a~1b/0/~0c").unwrap(), vec!["a/b", "0", "~c"]);
+        assert_eq!(parse_key_path(r"a\.b.c").unwrap(), vec!["a.b", "c"]);
+        assert_eq!(parse_key_path("items[-]").unwrap(), vec!["items", "-"]);
+        assert!(parse_key_path("").unwrap().is_empty());
+        assert!(parse_key_path("a..b").is_err());
+        assert!(parse_key_path("a[x]").is_err());
+        assert!(parse_key_path("a.").is_err());
+    }
+
+    #[test]
+    fn test_toml_keeps_comments() {
+        let original = "# Package\n[package]\nname = \"demo\" # the name\nversion = \"0.1.0\"\n\n[dependencies]\nserde = \"1\"\n";
+
+        let edited = edit_str(original, DataFormat::Toml, "package.version", Operation::Set, json!("0.2.0"));
+        assert_eq!(edited, original.replace("0.1.0", "0.2.0"));
+
+        let edited = edit_str(original, DataFormat::Toml, "package.name", Operation::Set, json!("other"));
+        assert!(edited.contains("name = \"other\" # the name\n"));
+
+        let edited = edit_str(original, DataFormat::Toml, "dependencies.tokio", Operation::Set, json!({"version": "1", "features": ["full"]}));
+        assert!(edited.ends_with("serde = \"1\"\ntokio = { version = \"1\", features = [\"full\"] }\n"));
+
+        let edited = edit_str(original, DataFormat::Toml, "workspace", Operation::Set, json!({"members": ["a"]}));
+        assert!(edited.ends_with("\n[workspace]\nmembers = [\"a\"]\n"));
+
+        let edited = edit_str(original, DataFormat::Toml, "dependencies.serde", Operation::Delete, Value::Null);
+        assert!(!edited.contains("serde"));
+        assert!(edited.starts_with("# Package\n"));
+
+        let edited = edit_str(original, DataFormat::Toml, "package.keywords", Operation::Append, json!("cli"));
+        assert!(edited.contains("keywords = [\"cli\"]"));
+
+        let edited = edit_str(original, DataFormat::Toml, "package", Operation::Merge, json!({"version": "1.0.0", "name": null}));
+        assert!(edited.contains("version = \"1.0.0\""));
+        assert!(!edited.contains("name"));
+    }
+
+    #[test]
+    fn test_json_keeps_order_and_indent() {
+        let original = "{\n    \"z\": 1,\n    \"a\": [1, 2]\n}\n";
+
+        let edited = edit_str(original, DataFormat::Json, "/a/-", Operation::Set, json!(3));
+        assert_eq!(edited, "{\n    \"z\": 1,\n    \"a\": [\n        1,\n        2,\n        3\n    ]\n}\n");
+
+        let edited = edit_str(original, DataFormat::Json, "b.c", Operation::Set, json!(true));
+        assert_eq!(edited, "{\n    \"z\": 1,\n    \"a\": [\n        1,\n        2\n    ],\n    \"b\": {\n        \"c\": true\n    }\n}\n");
+
+        let edited = edit_str("{\"a\":1,\"b\":2}", DataFormat::Json, "a", Operation::Delete, Value::Null);
+        assert_eq!(edited, "{\"b\":2}");
+
+        let path = parse_key_path("z.x").unwrap();
+        let err = edit(original, DataFormat::Json, &path, Operation::Set, &json!(1), false).unwrap_err();
+        assert!(matches!(err, StructuredError::TypeMismatch { .. }));
+
+        let path = parse_key_path("missing").unwrap();
+        let err = edit(original, DataFormat::Json, &path, Operation::Delete, &Value::Null, false).unwrap_err();
+        assert!(matches!(err, StructuredError::NotFound(_)));
+    }
+
+    #[test]
+    fn test_yaml_edit() {
+        let original = "# config\nname: demo\nlist:\n  - a\n";
+        let path = parse_key_path("list").unwrap();
+        let err = edit(original, DataFormat::Yaml, &path, Operation::Append, &json!("b"), false).unwrap_err();
+        assert!(matches!(err, StructuredError::CommentLoss));
+
+        let edited = edit(original, DataFormat::Yaml, &path, Operation::Append, &json!("b"), true).unwrap();
+        assert_eq!(edited.content, "name: demo\nlist:\n- a\n- b\n");
+        assert_eq!(edited.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_yaml_comment_detection() {
+        assert!(has_yaml_comments("name: demo # trailing\n"));
+        assert!(has_yaml_comments("  # indented\nname: demo\n"));
+        assert!(has_yaml_comments("name: it's # after an apostrophe\n"));
+        assert!(!has_yaml_comments("url: http://host/#anchor\n"));
+        assert!(!has_yaml_comments("title: \"a # b \\\" c\"\nother: 'x # y'\n"));
+    }
+}
@@ -0,0 +1,602 @@
+use std::fmt;
+use std::path::Path;
+use thiserror::Error;
+use tree_sitter::{Language, Node, Parser};
+
+/// Maximum number of item paths listed when an item is not found
+const MAX_LISTED_ITEMS: usize = 20;
+
+#[derive(Error, Debug)]
+pub enum SyntaxError {
+    #[error("Failed to parse the file as {0}")]
+    ParseFailed(SourceLanguage),
+
+    #[error("Item '{path}' not found{}", list_items(.available))]
+    NotFound { path: String, available: Vec<String> },
+
+    #[error("Item '{path}' is ambiguous: matches {}; use the full path", .candidates.join(", "))]
+    Ambiguous { path: String, candidates: Vec<String> },
+}
+
+fn list_items(available: &[String]) -> String {
+    if available.is_empty() {
+        return " (the file has no named items)".to_string();
+    }
+    let mut listed = available.iter().take(MAX_LISTED_ITEMS).cloned().collect::<Vec<_>>().join(", ");
+    if available.len() > MAX_LISTED_ITEMS {
+        listed.push_str(&format!(", ... ({} more)", available.len() - MAX_LISTED_ITEMS));
+    }
+    format!(" (available items: {})", listed)
+}
+
+/// A programming language with an embedded parser
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SourceLanguage {
+    Rust,
+    Python,
+    TypeScript,
+    Tsx,
+    Go,
+}
+
+impl SourceLanguage {
+    /// Parse a `language` argument
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "rust" | "rs" => Some(SourceLanguage::Rust),
+            "python" | "py" => Some(SourceLanguage::Python),
+            "typescript" | "ts" | "javascript" | "js" => Some(SourceLanguage::TypeScript),
+            "tsx" | "jsx" => Some(SourceLanguage::Tsx),
+            "go" => Some(SourceLanguage::Go),
+            _ => None,
+        }
+    }
+
+    /// Detect the language from a file extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "rs" => Some(SourceLanguage::Rust),
+            "py" | "pyi" => Some(SourceLanguage::Python),
+            "ts" | "mts" | "cts" | "js" | "mjs" | "cjs" => Some(SourceLanguage::TypeScript),
+            "tsx" | "jsx" => Some(SourceLanguage::Tsx),
+            "go" => Some(SourceLanguage::Go),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn grammar(&self) -> Language {
+        match self {
+            SourceLanguage::Rust => tree_sitter_rust::LANGUAGE.into(),
+            SourceLanguage::Python => tree_sitter_python::LANGUAGE.into(),
+            SourceLanguage::TypeScript => tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            SourceLanguage::Tsx => tree_sitter_typescript::LANGUAGE_TSX.into(),
+            SourceLanguage::Go => tree_sitter_go::LANGUAGE.into(),
+        }
+    }
+
+    // Separator between a container and its members in item paths
+    fn separator(&self) -> &'static str {
+        match self {
+            SourceLanguage::Rust => "::",
+            _ => ".",
+        }
+    }
+}
+
+impl fmt::Display for SourceLanguage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SourceLanguage::Rust => "Rust",
+            SourceLanguage::Python => "Python",
+            SourceLanguage::TypeScript => "TypeScript",
+            SourceLanguage::Tsx => "TSX",
+            SourceLanguage::Go => "Go",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A named item of a source file (function, type, impl block, class, method, ...)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxItem {
+    /// Kind of item, e.g. "fn", "struct", "impl", "class" or "method"
+    pub kind: &'static str,
+    /// The item's own name
+    pub name: String,
+    /// Qualified path, e.g. "impl Foo::bar" (Rust), "Foo.bar" (Python, TypeScript, Go)
+    pub path: String,
+    /// Nesting depth, 0 for top-level items
+    pub depth: usize,
+    /// Byte range, including leading doc comments, attributes, decorators and `export`
+    pub start_byte: usize,
+    pub end_byte: usize,
+    /// 0-based lines of the range
+    pub start_line: usize,
+    pub end_line: usize,
+}
+
+/// Parse a source file and list its named items in document order
+///
+/// # Arguments
+///
+/// * `source` - The file content
+/// * `language` - The language of the file
+///
+/// # Returns
+///
+/// * `Result<Vec<SyntaxItem>, SyntaxError>` - The items, containers before their members
+pub fn items(source: &str, language: SourceLanguage) -> Result<Vec<SyntaxItem>, SyntaxError> {
+    let mut parser = Parser::new();
+    parser
+        .set_language(&language.grammar())
+        .map_err(|_| SyntaxError::ParseFailed(language))?;
+    let tree = parser
+        .parse(source, None)
+        .ok_or(SyntaxError::ParseFailed(language))?;
+
+    let mut collector = Collector {
+        source: source.as_bytes(),
+        language,
+        items: Vec::new(),
+    };
+    collector.visit_children(tree.root_node(), None, 0);
+    Ok(collector.items)
+}
+
+/// Find the item addressed by a path
+///
+/// The path is matched against the full item paths first, then (for Rust) with an
+/// implied `impl ` prefix so `Foo::bar` finds a method of `impl Foo`, and finally
+/// against bare item names.
+///
+/// # Arguments
+///
+/// * `source` - The file content
+/// * `language` - The language of the file
+/// * `path` - The item path or name
+///
+/// # Returns
+///
+/// * `Result<SyntaxItem, SyntaxError>` - The single matching item
+pub fn find_item(source: &str, language: SourceLanguage, path: &str) -> Result<SyntaxItem, SyntaxError> {
+    let items = items(source, language)?;
+    let path = path.trim();
+    let implied_impl = format!("impl {}", path);
+
+    let strategies: [&dyn Fn(&SyntaxItem) -> bool; 3] = [
+        &|item| item.path == path,
+        &|item| language == SourceLanguage::Rust && item.path == implied_impl,
+        &|item| item.name == path,
+    ];
+    for matches in strategies {
+        let found: Vec<&SyntaxItem> = items.iter().filter(|item| matches(item)).collect();
+        match found.as_slice() {
+            [] => continue,
+            [item] => return Ok((*item).clone()),
+            _ => {
+                return Err(SyntaxError::Ambiguous {
+                    path: path.to_string(),
+                    candidates: found.iter().map(|item| format!("'{}' (line {})", item.path, item.start_line)).collect(),
+                })
+            }
+        }
+    }
+
+    Err(SyntaxError::NotFound {
+        path: path.to_string(),
+        available: items.into_iter().map(|item| item.path).collect(),
+    })
+}
+
+/// List the headings of a Markdown document as items
+///
+/// ATX (`# Title`) and setext (`Title` underlined with `===` or `---`) headings are
+/// recognized outside fenced code blocks. A heading's range runs up to the next heading
+/// of the same or a higher level, and its path joins the enclosing headings with " > ".
+///
+/// # Arguments
+///
+/// * `source` - The Markdown content
+///
+/// # Returns
+///
+/// * `Vec<SyntaxItem>` - The headings in document order, of kind "h1" to "h6"
+pub fn markdown_headings(source: &str) -> Vec<SyntaxItem> {
+    const KINDS: [&str; 6] = ["h1", "h2", "h3", "h4", "h5", "h6"];
+
+    // (level, title, first line, byte offset of that line)
+    let mut headings: Vec<(usize, String, usize, usize)> = Vec::new();
+    let mut fence: Option<&str> = None;
+    let mut previous: Option<(&str, usize)> = None;
+    let mut offset = 0;
+    let line_count = source.lines().count();
+
+    for (index, raw_line) in source.split_inclusive('\n').enumerate() {
+        let line = raw_line.trim_end_matches(['\n', '\r']);
+        let line_offset = offset;
+        offset += raw_line.len();
+        let trimmed = line.trim_start();
+        let indented = line.len() - trimmed.len() >= 4;
+
+        // Skip fenced code blocks, which end with the same fence
+        if let Some(marker) = fence {
+            if trimmed.starts_with(marker) {
+                fence = None;
+            }
+            previous = None;
+            continue;
+        }
+        if !indented && (trimmed.starts_with("```") || trimmed.starts_with("~~~")) {
+            fence = Some(&trimmed[..3]);
+            previous = None;
+            continue;
+        }
+
+        let hashes = trimmed.chars().take_while(|&c| c == '#').count();
+        let is_atx = !indented
+            && (1..=6).contains(&hashes)
+            && trimmed[hashes..].chars().next().is_none_or(|c| c == ' ' || c == '\t');
+        if is_atx {
+            let title = trimmed[hashes..].trim().trim_end_matches('#').trim_end();
+            headings.push((hashes, title.to_string(), index, line_offset));
+            previous = None;
+            continue;
+        }
+
+        // A setext underline turns the preceding paragraph line into a heading
+        let underline = trimmed.trim_end();
+        let setext_level = if underline.is_empty() || indented {
+            None
+        } else if underline.chars().all(|c| c == '=') {
+            Some(1)
+        } else if underline.chars().all(|c| c == '-') {
+            Some(2)
+        } else {
+            None
+        };
+        if let (Some(level), Some((title, title_offset))) = (setext_level, previous) {
+            headings.push((level, title.trim().to_string(), index - 1, title_offset));
+            previous = None;
+            continue;
+        }
+
+        previous = if trimmed.is_empty() { None } else { Some((line, line_offset)) };
+    }
+
+    let mut items = Vec::new();
+    let mut parents: Vec<(usize, String)> = Vec::new();
+    for (position, (level, title, start_line, start_byte)) in headings.iter().enumerate() {
+        let next = headings[position + 1..].iter().find(|(next_level, ..)| next_level <= level);
+        let (end_line, end_byte) = match next {
+            Some((_, _, next_line, next_offset)) => (next_line.saturating_sub(1), *next_offset),
+            None => (line_count.saturating_sub(1), source.len()),
+        };
+
+        while parents.last().is_some_and(|(parent_level, _)| parent_level >= level) {
+            parents.pop();
+        }
+        let mut path = parents.iter().map(|(_, parent)| parent.as_str()).collect::<Vec<_>>().join(" > ");
+        if !path.is_empty() {
+            path.push_str(" > ");
+        }
+        path.push_str(title);
+
+        items.push(SyntaxItem {
+            kind: KINDS[level - 1],
+            name: title.clone(),
+            path,
+            depth: parents.len(),
+            start_byte: *start_byte,
+            end_byte,
+            start_line: *start_line,
+            end_line: end_line.max(*start_line),
+        });
+        parents.push((*level, title.clone()));
+    }
+    items
+}
+
+struct Collector<'a> {
+    source: &'a [u8],
+    language: SourceLanguage,
+    items: Vec<SyntaxItem>,
+}
+
+impl Collector<'_> {
+    fn text(&self, node: Node) -> &str {
+        node.utf8_text(self.source).unwrap_or_default()
+    }
+
+    fn field_text(&self, node: Node, field: &str) -> Option<String> {
+        node.child_by_field_name(field).map(|child| self.text(child).to_string())
+    }
+
+    fn visit_children(&mut self, node: Node, prefix: Option<&str>, depth: usize) {
+        let mut cursor = node.walk();
+        let children: Vec<Node> = node.named_children(&mut cursor).collect();
+        for child in children {
+            self.visit(child, prefix, depth);
+        }
+    }
+
+    fn visit(&mut self, node: Node, prefix: Option<&str>, depth: usize) {
+        match self.language {
+            SourceLanguage::Rust => self.visit_rust(node, prefix, depth),
+            SourceLanguage::Python => self.visit_python(node, node, prefix, depth),
+            SourceLanguage::TypeScript | SourceLanguage::Tsx => self.visit_typescript(node, node, prefix, depth),
+            SourceLanguage::Go => self.visit_go(node, prefix, depth),
+        }
+    }
+
+    // Record an item; `span` is the node whose range (plus leading comments) the item covers
+    fn push(&mut self, kind: &'static str, name: String, path: String, span: Node, depth: usize) {
+        let start = self.leading_start(span);
+        self.items.push(SyntaxItem {
+            kind,
+            name,
+            path,
+            depth,
+            start_byte: start.start_byte(),
+            end_byte: span.end_byte(),
+            start_line: start.start_position().row,
+            end_line: span.end_position().row,
+        });
+    }
+
+    fn qualify(&self, prefix: Option<&str>, name: &str) -> String {
+        match prefix {
+            Some(prefix) => format!("{}{}{}", prefix, self.language.separator(), name),
+            None => name.to_string(),
+        }
+    }
+
+    // Extend a span backwards over the comments and attributes directly above it
+    fn leading_start<'t>(&self, node: Node<'t>) -> Node<'t> {
+        let mut start = node;
+        while let Some(prev) = start.prev_sibling() {
+            let leading = matches!(prev.kind(), "line_comment" | "block_comment" | "comment" | "attribute_item");
+            // Stop at a blank line or a comment that trails code on the same line
+            if !leading || prev.end_position().row + 1 < start.start_position().row || self.trails_code(prev) {
+                break;
+            }
+            start = prev;
+        }
+        start
+    }
+
+    fn trails_code(&self, node: Node) -> bool {
+        let line_start = self.source[..node.start_byte()]
+            .iter()
+            .rposition(|&b| b == b'\n')
+            .map_or(0, |i| i + 1);
+        self.source[line_start..node.start_byte()].iter().any(|b| !b.is_ascii_whitespace())
+    }
+
+    fn visit_rust(&mut self, node: Node, prefix: Option<&str>, depth: usize) {
+        let kind = match node.kind() {
+            "function_item" | "function_signature_item" => "fn",
+            "struct_item" => "struct",
+            "enum_item" => "enum",
+            "union_item" => "union",
+            "trait_item" => "trait",
+            "type_item" => "type",
+            "const_item" => "const",
+            "static_item" => "static",
+            "mod_item" => "mod",
+            "macro_definition" => "macro",
+            "impl_item" => {
+                // Generic parameters of the self type are left out: `impl<T> Foo<T>` is "impl Foo"
+                let self_type = self.field_text(node, "type").unwrap_or_default();
+                let self_type = self_type.split('<').next().unwrap_or_default().trim().to_string();
+                let name = match self.field_text(node, "trait") {
+                    Some(trait_name) => format!("impl {} for {}", trait_name, self_type),
+                    None => format!("impl {}", self_type),
+                };
+                let path = self.qualify(prefix, &name);
+                self.push("impl", name, path.clone(), node, depth);
+                if let Some(body) = node.child_by_field_name("body") {
+                    self.visit_children(body, Some(&path), depth + 1);
+                }
+                return;
+            }
+            _ => return,
+        };
+
+        let Some(name) = self.field_text(node, "name") else {
+            return;
+        };
+        let path = self.qualify(prefix, &name);
+        self.push(kind, name, path.clone(), node, depth);
+        if matches!(kind, "trait" | "mod") {
+            if let Some(body) = node.child_by_field_name("body") {
+                self.visit_children(body, Some(&path), depth + 1);
+            }
+        }
+    }
+
+    fn visit_python(&mut self, node: Node, span: Node, prefix: Option<&str>, depth: usize) {
+        let kind = match node.kind() {
+            "decorated_definition" => {
+                if let Some(definition) = node.child_by_field_name("definition") {
+                    self.visit_python(definition, span, prefix, depth);
+                }
+                return;
+            }
+            "function_definition" if prefix.is_some() => "method",
+            "function_definition" => "function",
+            "class_definition" => "class",
+            _ => return,
+        };
+
+        let Some(name) = self.field_text(node, "name") else {
+            return;
+        };
+        let path = self.qualify(prefix, &name);
+        self.push(kind, name, path.clone(), span, depth);
+        if kind == "class" {
+            if let Some(body) = node.child_by_field_name("body") {
+                self.visit_children(body, Some(&path), depth + 1);
+            }
+        }
+    }
+
+    fn visit_typescript(&mut self, node: Node, span: Node, prefix: Option<&str>, depth: usize) {
+        let kind = match node.kind() {
+            "export_statement" => {
+                if let Some(declaration) = node.child_by_field_name("declaration") {
+                    self.visit_typescript(declaration, span, prefix, depth);
+                }
+                return;
+            }
+            "lexical_declaration" | "variable_declaration" => {
+                // `const foo = () => ...` counts as a function named foo
+                let mut cursor = node.walk();
+                let declarators: Vec<Node> = node.named_children(&mut cursor)
+                    .filter(|child| child.kind() == "variable_declarator")
+                    .collect();
+                if let [declarator] = declarators.as_slice() {
+                    let is_function = declarator.child_by_field_name("value")
+                        .is_some_and(|value| matches!(value.kind(), "arrow_function" | "function_expression" | "function"));
+                    if let (true, Some(name)) = (is_function, self.field_text(*declarator, "name")) {
+                        let path = self.qualify(prefix, &name);
+                        self.push("function", name, path, span, depth);
+                    }
+                }
+                return;
+            }
+            "function_declaration" | "generator_function_declaration" | "function_signature" => "function",
+            "class_declaration" | "abstract_class_declaration" => "class",
+            "interface_declaration" => "interface",
+            "type_alias_declaration" => "type",
+            "enum_declaration" => "enum",
+            "internal_module" | "module" => "namespace",
+            "method_definition" | "abstract_method_signature" => "method",
+            "public_field_definition" => "field",
+            _ => return,
+        };
+
+        let Some(name) = self.field_text(node, "name") else {
+            return;
+        };
+        let path = self.qualify(prefix, &name);
+        self.push(kind, name, path.clone(), span, depth);
+        if matches!(kind, "class" | "namespace") {
+            if let Some(body) = node.child_by_field_name("body") {
+                self.visit_children(body, Some(&path), depth + 1);
+            }
+        }
+    }
+
+    fn visit_go(&mut self, node: Node, prefix: Option<&str>, depth: usize) {
+        match node.kind() {
+            "function_declaration" => {
+                if let Some(name) = self.field_text(node, "name") {
+                    let path = self.qualify(prefix, &name);
+                    self.push("func", name, path, node, depth);
+                }
+            }
+            "method_declaration" => {
+                let Some(name) = self.field_text(node, "name") else {
+                    return;
+                };
+                // Methods are addressed by their receiver type: `func (s *Server) Run()` is "Server.Run"
+                let receiver = node.child_by_field_name("receiver")
+                    .and_then(|receiver| {
+                        let mut cursor = receiver.walk();
+                        let parameter = receiver.named_children(&mut cursor).next();
+                        parameter.and_then(|parameter| self.field_text(parameter, "type"))
+                    })
+                    .map(|receiver| receiver.trim_start_matches('*').split('[').next().unwrap_or_default().to_string());
+                let path = match receiver {
+                    Some(receiver) => format!("{}.{}", receiver, name),
+                    None => name.clone(),
+                };
+                self.push("method", name, path, node, depth);
+            }
+            "type_declaration" => {
+                let mut cursor = node.walk();
+                let specs: Vec<Node> = node.named_children(&mut cursor)
+                    .filter(|child| matches!(child.kind(), "type_spec" | "type_alias"))
+                    .collect();
+                let grouped = specs.len() > 1;
+                for spec in specs {
+                    if let Some(name) = self.field_text(spec, "name") {
+                        let path = self.qualify(prefix, &name);
+                        // A spec inside `type ( ... )` is its own item; a lone one covers the declaration
+                        self.push("type", name, path, if grouped { spec } else { node }, depth);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(source: &str, language: SourceLanguage) -> Vec<String> {
+        items(source, language).unwrap().into_iter().map(|item| item.path).collect()
+    }
+
+    #[test]
+    fn test_rust_items() {
+        let source = "use std::fmt;\n\n/// A thing\n#[derive(Debug)]\nstruct Foo;\n\nimpl Foo {\n    fn bar(&self) {}\n}\n\nimpl fmt::Display for Foo {\n    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result { Ok(()) }\n}\n\nmod inner {\n    fn helper() {}\n}\n";
+        assert_eq!(
+            paths(source, SourceLanguage::Rust),
+            vec!["Foo", "impl Foo", "impl Foo::bar", "impl fmt::Display for Foo", "impl fmt::Display for Foo::fmt", "inner", "inner::helper"]
+        );
+
+        // Doc comments and attributes belong to the item
+        let foo = find_item(source, SourceLanguage::Rust, "Foo").unwrap();
+        assert_eq!(&source[foo.start_byte..foo.end_byte], "/// A thing\n#[derive(Debug)]\nstruct Foo;");
+
+        let bar = find_item(source, SourceLanguage::Rust, "Foo::bar").unwrap();
+        assert_eq!(&source[bar.start_byte..bar.end_byte], "fn bar(&self) {}");
+
+        assert!(matches!(find_item(source, SourceLanguage::Rust, "missing"), Err(SyntaxError::NotFound { .. })));
+    }
+
+    #[test]
+    fn test_python_typescript_go_items() {
+        let python = "import os\n\n@dataclass\nclass A:\n    def run(self):\n        pass\n\n    def stop(self):\n        pass\n\ndef main():\n    pass\n";
+        assert_eq!(paths(python, SourceLanguage::Python), vec!["A", "A.run", "A.stop", "main"]);
+        let class = find_item(python, SourceLanguage::Python, "A").unwrap();
+        assert!(python[class.start_byte..].starts_with("@dataclass\nclass A:"));
+
+        let typescript = "export class Api {\n  get(id: string) { return id; }\n}\n\nexport const handler = async () => {};\ninterface Options { a: number }\n";
+        assert_eq!(paths(typescript, SourceLanguage::TypeScript), vec!["Api", "Api.get", "handler", "Options"]);
+
+        let go = "package main\n\ntype Server struct{}\n\n// Run starts the server\nfunc (s *Server) Run() {}\n\nfunc main() {}\n";
+        assert_eq!(paths(go, SourceLanguage::Go), vec!["Server", "Server.Run", "main"]);
+        let run = find_item(go, SourceLanguage::Go, "Run").unwrap();
+        assert!(go[run.start_byte..].starts_with("// Run starts the server\nfunc"));
+    }
+
+    #[test]
+    fn test_markdown_headings() {
+        let source = "# Title\n\nIntro\n\n## Install\n\n```sh\n# not a heading\n```\n\nUsage\n-----\n\n### Flags\n\n# Appendix\n";
+        let headings = markdown_headings(source);
+        let outline: Vec<(&str, &str, usize, usize)> = headings.iter()
+            .map(|h| (h.kind, h.path.as_str(), h.start_line, h.end_line))
+            .collect();
+        assert_eq!(outline, vec![
+            ("h1", "Title", 0, 14),
+            ("h2", "Title > Install", 4, 9),
+            ("h2", "Title > Usage", 10, 14),
+            ("h3", "Title > Usage > Flags", 13, 14),
+            ("h1", "Appendix", 15, 15),
+        ]);
+        assert!(source[headings[1].start_byte..headings[1].end_byte].starts_with("## Install"));
+        assert!(source[headings[1].end_byte..].starts_with("Usage"));
+    }
+
+    #[test]
+    fn test_ambiguous_item() {
+        let source = "struct A;\nstruct B;\nimpl A { fn new() {} }\nimpl B { fn new() {} }\n";
+        let err = find_item(source, SourceLanguage::Rust, "new").unwrap_err();
+        assert!(matches!(err, SyntaxError::Ambiguous { .. }));
+        assert!(find_item(source, SourceLanguage::Rust, "B::new").is_ok());
+    }
+}
@@ -0,0 +1,325 @@
+use std::fs::{File, Metadata};
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::utils::precondition;
+
+/// Size of the blocks read backwards when looking for line starts
+const BLOCK_SIZE: u64 = 8192;
+
+/// Number of bytes before a follow offset that must be unchanged for the file to count as the same
+const ANCHOR_LEN: u64 = 64;
+
+/// A range of a file read from its end or from a follow offset
+#[derive(Debug)]
+pub struct Chunk {
+    /// Offset of the first byte
+    pub start: u64,
+    /// Offset after the last byte, where a later follow read continues
+    pub end: u64,
+    pub data: Vec<u8>,
+    /// The requested range did not fit in the size limit
+    pub truncated: bool,
+}
+
+/// Why a follow read started over from the beginning of the file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Restart {
+    /// The file is now shorter than the follow offset
+    Truncated,
+    /// The path now names a different file, or the content before the offset changed
+    Rotated,
+}
+
+/// Position in a growing file, tied to the file's identity and the bytes just before it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FollowToken {
+    pub offset: u64,
+    file_id: u64,
+    anchor: String,
+}
+
+impl FollowToken {
+    /// Create a token for continuing to read `file` at `offset`
+    pub fn new(file: &mut File, metadata: &Metadata, offset: u64) -> io::Result<Self> {
+        Ok(FollowToken {
+            offset,
+            file_id: file_id(metadata),
+            anchor: anchor(file, offset)?,
+        })
+    }
+
+    /// Encode the token as an opaque string
+    pub fn encode(&self) -> String {
+        let raw = format!("{}:{}:{}", self.offset, self.file_id, self.anchor);
+        base64::encode_config(raw, base64::URL_SAFE_NO_PAD)
+    }
+
+    /// Decode a token produced by `encode`
+    pub fn decode(value: &str) -> Option<Self> {
+        let raw = String::from_utf8(base64::decode_config(value.trim(), base64::URL_SAFE_NO_PAD).ok()?).ok()?;
+        let mut parts = raw.splitn(3, ':');
+        Some(FollowToken {
+            offset: parts.next()?.parse().ok()?,
+            file_id: parts.next()?.parse().ok()?,
+            anchor: parts.next()?.to_string(),
+        })
+    }
+}
+
+/// Read the last lines of a file, seeking backwards from the end
+///
+/// A line ending at the very end of the file does not start another line. If the lines
+/// are longer than `max_size` in total, only the last lines that fit are returned.
+///
+/// # Arguments
+///
+/// * `file` - The open file
+/// * `size` - The file's size
+/// * `lines` - Number of lines to return
+/// * `max_size` - Maximum number of bytes to return
+/// * `utf8` - Whether the file is UTF-8, so that an incomplete character at the end is dropped
+///
+/// # Returns
+///
+/// * `io::Result<Chunk>` - The lines, from the start of the first one to the end of the file
+pub fn tail_lines(file: &mut File, size: u64, lines: usize, max_size: u64, utf8: bool) -> io::Result<Chunk> {
+    let limit = size.saturating_sub(max_size);
+    let mut start = size;
+    let mut found = 0;
+    let mut position = size;
+    let mut block = vec![0; BLOCK_SIZE as usize];
+
+    // Walk back to the newline before the first wanted line, or the size limit
+    'search: while position > limit && lines > 0 {
+        let block_start = position.saturating_sub(BLOCK_SIZE).max(limit);
+        let len = (position - block_start) as usize;
+        file.seek(SeekFrom::Start(block_start))?;
+        file.read_exact(&mut block[..len])?;
+        for i in (0..len).rev() {
+            let offset = block_start + i as u64;
+            if block[i] == b'\n' && offset + 1 < size {
+                found += 1;
+                if found == lines {
+                    start = offset + 1;
+                    break 'search;
+                }
+            }
+        }
+        position = block_start;
+        start = block_start;
+    }
+
+    // Lines that don't fit start at the first line boundary after the limit
+    let mut truncated = false;
+    if lines > 0 && found < lines && start > 0 {
+        truncated = true;
+        start = next_line_start(file, start, size)?.unwrap_or(start);
+    }
+    if lines == 0 {
+        start = size;
+    }
+
+    read_range(file, start, size, truncated, utf8)
+}
+
+/// Read the last `bytes` bytes of a file (at most `max_size`), starting at a character boundary
+/// of a UTF-8 file
+pub fn tail_bytes(file: &mut File, size: u64, bytes: u64, max_size: u64, utf8: bool) -> io::Result<Chunk> {
+    let truncated = bytes > max_size && size > max_size;
+    let mut start = size.saturating_sub(bytes.min(max_size));
+
+    // Skip UTF-8 continuation bytes so the chunk starts with a whole character
+    if utf8 {
+        let mut lead = [0; 4];
+        file.seek(SeekFrom::Start(start))?;
+        let len = file.read(&mut lead)?;
+        start += lead[..len].iter().take_while(|&&b| b & 0xC0 == 0x80).count() as u64;
+    }
+
+    read_range(file, start, size, truncated, utf8)
+}
+
+/// Read the content appended after a follow token, or the whole file if it was truncated or rotated
+///
+/// # Arguments
+///
+/// * `file` - The open file
+/// * `metadata` - The file's metadata
+/// * `token` - Where the previous read ended
+/// * `max_size` - Maximum number of bytes to return
+/// * `utf8` - Whether the file is UTF-8, so that an incomplete character at the end is dropped
+///
+/// # Returns
+///
+/// * `io::Result<(Chunk, Option<Restart>)>` - The new content, and why it starts over if it does
+pub fn follow(
+    file: &mut File,
+    metadata: &Metadata,
+    token: &FollowToken,
+    max_size: u64,
+    utf8: bool,
+) -> io::Result<(Chunk, Option<Restart>)> {
+    let size = metadata.len();
+    let restart = if size < token.offset {
+        Some(Restart::Truncated)
+    } else if file_id(metadata) != token.file_id || anchor(file, token.offset)? != token.anchor {
+        Some(Restart::Rotated)
+    } else {
+        None
+    };
+
+    let start = if restart.is_some() { 0 } else { token.offset };
+    let mut end = size.min(start.saturating_add(max_size));
+    let truncated = end < size;
+
+    // A chunk cut by the size limit ends after its last complete line, if it has one
+    if truncated {
+        let mut data = Vec::new();
+        file.seek(SeekFrom::Start(start))?;
+        file.take(end - start).read_to_end(&mut data)?;
+        if let Some(eol) = data.iter().rposition(|&b| b == b'\n') {
+            end = start + eol as u64 + 1;
+        }
+    }
+
+    Ok((read_range(file, start, end, truncated, utf8)?, restart))
+}
+
+// Read [start, end), dropping an incomplete character at the end of UTF-8 content
+fn read_range(file: &mut File, start: u64, end: u64, truncated: bool, utf8: bool) -> io::Result<Chunk> {
+    let mut data = Vec::new();
+    file.seek(SeekFrom::Start(start))?;
+    file.take(end.saturating_sub(start)).read_to_end(&mut data)?;
+
+    if utf8 {
+        if let Err(e) = std::str::from_utf8(&data) {
+            if e.error_len().is_none() {
+                data.truncate(e.valid_up_to());
+            }
+        }
+    }
+
+    Ok(Chunk {
+        start,
+        end: start + data.len() as u64,
+        data,
+        truncated,
+    })
+}
+
+// Offset just after the first newline at or after `from`, if any
+fn next_line_start(file: &mut File, from: u64, size: u64) -> io::Result<Option<u64>> {
+    let mut data = Vec::new();
+    file.seek(SeekFrom::Start(from.saturating_sub(1)))?;
+    file.take(size - from.saturating_sub(1)).read_to_end(&mut data)?;
+    Ok(data.iter().position(|&b| b == b'\n').map(|i| from.saturating_sub(1) + i as u64 + 1).filter(|&offset| offset < size))
+}
+
+// Hash of the bytes just before an offset
+fn anchor(file: &mut File, offset: u64) -> io::Result<String> {
+    let start = offset.saturating_sub(ANCHOR_LEN);
+    let mut data = Vec::new();
+    file.seek(SeekFrom::Start(start))?;
+    file.take(offset - start).read_to_end(&mut data)?;
+    Ok(precondition::hash_bytes(&data).chars().take(16).collect())
+}
+
+#[cfg(unix)]
+fn file_id(metadata: &Metadata) -> u64 {
+    use std::os::unix::fs::MetadataExt;
+
+    metadata.ino()
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &Metadata) -> u64 {
+    0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::{self, OpenOptions};
+    use std::io::Write;
+    use tempfile::tempdir;
+
+    fn open(path: &std::path::Path) -> (File, Metadata) {
+        let file = File::open(path).unwrap();
+        let metadata = file.metadata().unwrap();
+        (file, metadata)
+    }
+
+    #[test]
+    fn test_tail_lines() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("app.log");
+        let content: String = (0..5000).map(|i| format!("line {}\n", i)).collect();
+        fs::write(&path, &content).unwrap();
+        let (mut file, metadata) = open(&path);
+
+        let chunk = tail_lines(&mut file, metadata.len(), 3, 1 << 20, true).unwrap();
+        assert_eq!(chunk.data, b"line 4997\nline 4998\nline 4999\n");
+        assert!(!chunk.truncated);
+
+        // More lines than fit: whole lines up to the limit
+        let chunk = tail_lines(&mut file, metadata.len(), 100, 25, true).unwrap();
+        assert_eq!(chunk.data, b"line 4998\nline 4999\n");
+        assert!(chunk.truncated);
+
+        // More lines than the file has
+        let chunk = tail_lines(&mut file, metadata.len(), 10000, 1 << 20, true).unwrap();
+        assert_eq!(chunk.start, 0);
+        assert_eq!(chunk.data.len(), content.len());
+
+        let chunk = tail_bytes(&mut file, metadata.len(), 10, 1 << 20, true).unwrap();
+        assert_eq!(chunk.data, b"line 4999\n");
+    }
+
+    #[test]
+    fn test_tail_keeps_single_byte_charset_ends() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("app.log");
+        fs::write(&path, b"first\ncaf\xe9").unwrap();
+        let (mut file, metadata) = open(&path);
+
+        // A windows-1252 0xE9 at the end is a whole character, not a cut UTF-8 one
+        let chunk = tail_lines(&mut file, metadata.len(), 1, 1 << 20, false).unwrap();
+        assert_eq!(chunk.data, b"caf\xe9");
+        let chunk = tail_bytes(&mut file, metadata.len(), 1, 1 << 20, false).unwrap();
+        assert_eq!(chunk.data, b"\xe9");
+
+        let chunk = tail_lines(&mut file, metadata.len(), 1, 1 << 20, true).unwrap();
+        assert_eq!(chunk.data, b"caf");
+    }
+
+    #[test]
+    fn test_follow_appends_truncation_and_rotation() {
+        let temp_dir = tempdir().unwrap();
+        let path = temp_dir.path().join("app.log");
+        fs::write(&path, "first\n").unwrap();
+        let (mut file, metadata) = open(&path);
+        let token = FollowToken::new(&mut file, &metadata, metadata.len()).unwrap();
+        let token = FollowToken::decode(&token.encode()).unwrap();
+
+        OpenOptions::new().append(true).open(&path).unwrap().write_all(b"second\n").unwrap();
+        let (mut file, metadata) = open(&path);
+        let (chunk, restart) = follow(&mut file, &metadata, &token, 1 << 20, true).unwrap();
+        assert_eq!(chunk.data, b"second\n");
+        assert_eq!(restart, None);
+
+        // Truncated in place
+        fs::write(&path, "x\n").unwrap();
+        let (mut file, metadata) = open(&path);
+        let (chunk, restart) = follow(&mut file, &metadata, &token, 1 << 20, true).unwrap();
+        assert_eq!(chunk.data, b"x\n");
+        assert_eq!(restart, Some(Restart::Truncated));
+
+        // Replaced by a new, longer file
+        fs::rename(&path, temp_dir.path().join("app.log.1")).unwrap();
+        fs::write(&path, "rotated log\n").unwrap();
+        let (mut file, metadata) = open(&path);
+        let (chunk, restart) = follow(&mut file, &metadata, &token, 1 << 20, true).unwrap();
+        assert_eq!(chunk.data, b"rotated log\n");
+        assert_eq!(restart, Some(Restart::Rotated));
+    }
+}
@@ -0,0 +1,229 @@
+use serde::Serialize;
+use std::fmt;
+
+/// The UTF-8 byte order mark
+pub const UTF8_BOM: &str = "\u{feff}";
+
+/// Line endings used by a text file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    Lf,
+    Crlf,
+    /// Both LF and CRLF line endings
+    Mixed,
+    /// No line breaks at all
+    None,
+}
+
+impl LineEnding {
+    /// Parse a `line_ending` argument ("lf" or "crlf")
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "lf" | "\n" => Some(LineEnding::Lf),
+            "crlf" | "\r\n" => Some(LineEnding::Crlf),
+            _ => None,
+        }
+    }
+
+    /// The line break characters, for endings that have a single one
+    pub fn as_str(&self) -> Option<&'static str> {
+        match self {
+            LineEnding::Lf => Some("\n"),
+            LineEnding::Crlf => Some("\r\n"),
+            LineEnding::Mixed | LineEnding::None => None,
+        }
+    }
+}
+
+impl fmt::Display for LineEnding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            LineEnding::Lf => "LF",
+            LineEnding::Crlf => "CRLF",
+            LineEnding::Mixed => "mixed",
+            LineEnding::None => "none",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Line endings, byte order mark and trailing newline of a text file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct TextFormat {
+    pub line_ending: LineEnding,
+    pub bom: bool,
+    pub trailing_newline: bool,
+}
+
+impl TextFormat {
+    /// Detect the format of text content
+    ///
+    /// # Arguments
+    /// * `content` - The file content
+    ///
+    /// # Returns
+    /// * `TextFormat` - The detected line endings, BOM and trailing newline
+    pub fn detect(content: &str) -> Self {
+        let crlf = content.matches("\r\n").count();
+        let lf = content.matches('\n').count() - crlf;
+        let line_ending = match (lf, crlf) {
+            (0, 0) => LineEnding::None,
+            (_, 0) => LineEnding::Lf,
+            (0, _) => LineEnding::Crlf,
+            _ => LineEnding::Mixed,
+        };
+
+        TextFormat {
+            line_ending,
+            bom: content.starts_with(UTF8_BOM),
+            trailing_newline: content.ends_with('\n'),
+        }
+    }
+
+    /// The line break to use for new lines: the file's own, or LF if it has none or mixes both
+    pub fn eol(&self) -> &'static str {
+        self.line_ending.as_str().unwrap_or("\n")
+    }
+
+    /// Bring edited content back to this format
+    ///
+    /// Line endings are only rewritten when the original used a single style, the BOM is
+    /// restored if it was removed, and the trailing newline is added or removed to match,
+    /// whether or not the edit meant to change them. Empty content is left alone.
+    pub fn restore(&self, content: &mut String) {
+        if content.is_empty() {
+            return;
+        }
+
+        if let Some(eol) = self.line_ending.as_str() {
+            if TextFormat::detect(content).line_ending != self.line_ending {
+                *content = convert_line_endings(content, eol);
+            }
+        }
+
+        if self.bom && !content.starts_with(UTF8_BOM) {
+            content.insert_str(0, UTF8_BOM);
+        }
+
+        let has_trailing_newline = content.ends_with('\n');
+        if self.trailing_newline && !has_trailing_newline {
+            content.push_str(self.eol());
+        } else if !self.trailing_newline && has_trailing_newline {
+            content.pop();
+            if content.ends_with('\r') {
+                content.pop();
+            }
+        }
+    }
+
+    /// A one-line description, e.g. "Line endings: CRLF, BOM: no, trailing newline: yes"
+    pub fn describe(&self) -> String {
+        format!(
+            "Line endings: {}, BOM: {}, trailing newline: {}",
+            self.line_ending,
+            if self.bom { "yes" } else { "no" },
+            if self.trailing_newline { "yes" } else { "no" }
+        )
+    }
+}
+
+/// Convert every line ending (LF, CRLF or lone CR) to the given one
+///
+/// # Arguments
+/// * `content` - The text to convert
+/// * `eol` - The line ending to use ("\n" or "\r\n")
+///
+/// # Returns
+/// * `String` - The converted text
+pub fn convert_line_endings(content: &str, eol: &str) -> String {
+    let mut converted = String::with_capacity(content.len());
+    let mut chars = content.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\r' => {
+                if chars.peek() == Some(&'\n') {
+                    chars.next();
+                }
+                converted.push_str(eol);
+            }
+            '\n' => converted.push_str(eol),
+            _ => converted.push(c),
+        }
+    }
+    converted
+}
+
+/// Add or remove the UTF-8 byte order mark at the start of the content
+pub fn set_bom(content: &str, bom: bool) -> String {
+    let stripped = content.strip_prefix(UTF8_BOM).unwrap_or(content);
+    if bom {
+        format!("{}{}", UTF8_BOM, stripped)
+    } else {
+        stripped.to_string()
+    }
+}
+
+/// Prefix each line with its number, `cat -n` style: right-aligned and followed by a tab
+///
+/// # Arguments
+/// * `content` - The lines to number
+/// * `first_line` - Number of the first line
+///
+/// # Returns
+/// * `String` - The numbered lines, each ending with "\n"
+pub fn number_lines(content: &str, first_line: usize) -> String {
+    let line_count = content.lines().count();
+    let width = (first_line + line_count.saturating_sub(1)).to_string().len();
+    let mut numbered = String::with_capacity(content.len() + line_count * (width + 1));
+    for (index, line) in content.lines().enumerate() {
+        numbered.push_str(&format!("{:>width$}\t{}\n", first_line + index, line, width = width));
+    }
+    numbered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_format() {
+        let format = TextFormat::detect("\u{feff}a\r\nb\r\n");
+        assert_eq!(format.line_ending, LineEnding::Crlf);
+        assert!(format.bom);
+        assert!(format.trailing_newline);
+
+        assert_eq!(TextFormat::detect("a\nb\r\n").line_ending, LineEnding::Mixed);
+        assert_eq!(TextFormat::detect("a").line_ending, LineEnding::None);
+        assert!(!TextFormat::detect("a\nb").trailing_newline);
+    }
+
+    #[test]
+    fn test_restore_format() {
+        let format = TextFormat::detect("\u{feff}a\r\nb");
+
+        let mut content = String::from("a\r\nx\ny\nb\n");
+        format.restore(&mut content);
+        assert_eq!(content, "\u{feff}a\r\nx\r\ny\r\nb");
+
+        // Mixed files keep whatever endings the edit produced
+        let format = TextFormat::detect("a\nb\r\n");
+        let mut content = String::from("a\nc\nb\r\n");
+        format.restore(&mut content);
+        assert_eq!(content, "a\nc\nb\r\n");
+    }
+
+    #[test]
+    fn test_number_lines() {
+        assert_eq!(number_lines("a\nb\n", 9), " 9\ta\n10\tb\n");
+        assert_eq!(number_lines("x\r\n", 0), "0\tx\n");
+    }
+
+    #[test]
+    fn test_convert_line_endings() {
+        assert_eq!(convert_line_endings("a\r\nb\nc\rd", "\n"), "a\nb\nc\nd");
+        assert_eq!(convert_line_endings("a\nb\r\n", "\r\n"), "a\r\nb\r\n");
+        assert_eq!(set_bom("\u{feff}x", false), "x");
+        assert_eq!(set_bom("x", true), "\u{feff}x");
+    }
+}
@@ -0,0 +1,514 @@
+use chrono::{Duration, Local, NaiveDateTime};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+use tracing::{debug, warn};
+use walkdir::WalkDir;
+
+use crate::utils::path::{AllowedPaths, PathError};
+use crate::utils::state::current_uid;
+
+const TRASH_INFO_EXTENSION: &str = "trashinfo";
+const DELETION_DATE_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+#[derive(Error, Debug)]
+pub enum TrashError {
+    #[error("Trash entry not found: '{0}'")]
+    EntryNotFound(String),
+
+    #[error("Restore destination already exists: '{0}'")]
+    DestinationExists(String),
+
+    #[error("Invalid trash info file: {0}")]
+    InvalidInfo(String),
+
+    #[error("IO error: {0}")]
+    IoError(#[from] io::Error),
+}
+
+/// Trash settings shared by the delete tool and the trash management tools
+#[derive(Clone, Debug)]
+pub struct TrashConfig {
+    /// Whether `delete` moves items to the trash instead of removing them
+    pub enabled: bool,
+    /// Entries older than this are purged when retention is enforced
+    pub max_age_days: Option<u64>,
+    /// Oldest entries are purged until the trash is at most this many bytes
+    pub max_size: Option<u64>,
+}
+
+/// An item stored in a trash directory
+#[derive(Debug, Clone)]
+pub struct TrashEntry {
+    /// Name of the item inside the trash `files` directory
+    pub name: String,
+    /// Absolute path the item was deleted from
+    pub original_path: PathBuf,
+    /// Local time the item was moved to the trash
+    pub deletion_date: NaiveDateTime,
+    /// Total size in bytes (recursive for directories)
+    pub size: u64,
+    pub is_dir: bool,
+}
+
+/// Get the trash directory for an allowed root
+///
+/// Follows the FreeDesktop trash specification layout for top directories
+/// (`$topdir/.Trash-$uid`), with `files/` and `info/` subdirectories.
+pub fn trash_dir(root: &Path) -> PathBuf {
+    root.join(format!(".Trash-{}", current_uid()))
+}
+
+/// Check if a file name looks like a trash directory
+pub fn is_trash_dir_name(name: &str) -> bool {
+    name.starts_with(".Trash-")
+}
+
+/// Check if a path lies inside the trash directory of a root
+pub fn is_in_trash(path: &Path, root: &Path) -> bool {
+    path.starts_with(trash_dir(root))
+}
+
+/// Move a file or directory into the trash of the given root
+///
+/// # Arguments
+///
+/// * `path` - The validated path to trash
+/// * `root` - The allowed directory containing `path`
+///
+/// # Returns
+///
+/// * `Result<TrashEntry, TrashError>` - The created trash entry
+pub fn move_to_trash(path: &Path, root: &Path) -> Result<TrashEntry, TrashError> {
+    let trash = trash_dir(root);
+    let files_dir = trash.join("files");
+    let info_dir = trash.join("info");
+    fs::create_dir_all(&files_dir)?;
+    fs::create_dir_all(&info_dir)?;
+
+    let base_name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "Path has no file name"))?;
+
+    let metadata = fs::symlink_metadata(path)?;
+    let is_dir = metadata.is_dir();
+    let size = entry_size(path);
+    let deletion_date = Local::now().naive_local();
+
+    // The spec reserves a name by creating the .trashinfo file exclusively
+    // before moving the item, so concurrent deletes never collide.
+    let mut counter = 1;
+    loop {
+        let name = if counter == 1 {
+            base_name.clone()
+        } else {
+            format!("{}.{}", base_name, counter)
+        };
+        counter += 1;
+
+        let info_path = info_path(&info_dir, &name);
+        let mut info_file = match OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&info_path)
+        {
+            Ok(f) => f,
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.into()),
+        };
+
+        let target = files_dir.join(&name);
+        if fs::symlink_metadata(&target).is_ok() {
+            // Orphaned item without an info file; leave it alone and pick another name
+            drop(info_file);
+            let _ = fs::remove_file(&info_path);
+            continue;
+        }
+
+        let write_result = write!(
+            info_file,
+            "[Trash Info]\nPath={}\nDeletionDate={}\n",
+            encode_path(path),
+            deletion_date.format(DELETION_DATE_FORMAT)
+        );
+        if let Err(e) = write_result.and_then(|_| fs::rename(path, &target)) {
+            let _ = fs::remove_file(&info_path);
+            return Err(e.into());
+        }
+
+        debug!("Moved '{}' to trash as '{}'", path.display(), name);
+
+        return Ok(TrashEntry {
+            name,
+            original_path: path.to_path_buf(),
+            deletion_date,
+            size,
+            is_dir,
+        });
+    }
+}
+
+/// List all entries in the trash of a root, oldest first
+pub fn list_entries(root: &Path) -> Result<Vec<TrashEntry>, TrashError> {
+    let trash = trash_dir(root);
+    let info_dir = trash.join("info");
+    let files_dir = trash.join("files");
+
+    let mut entries = Vec::new();
+    if !info_dir.is_dir() {
+        return Ok(entries);
+    }
+
+    for dir_entry in fs::read_dir(&info_dir)? {
+        let info_file = dir_entry?.path();
+        if info_file.extension().and_then(|e| e.to_str()) != Some(TRASH_INFO_EXTENSION) {
+            continue;
+        }
+
+        let name = match info_file.file_stem() {
+            Some(stem) => stem.to_string_lossy().into_owned(),
+            None => continue,
+        };
+
+        let (original_path, deletion_date) = match fs::read_to_string(&info_file)
+            .map_err(TrashError::from)
+            .and_then(|content| parse_info(&content))
+        {
+            Ok(info) => info,
+            Err(e) => {
+                warn!("Skipping trash entry '{}': {}", info_file.display(), e);
+                continue;
+            }
+        };
+
+        let item = files_dir.join(&name);
+        let metadata = match fs::symlink_metadata(&item) {
+            Ok(m) => m,
+            Err(_) => {
+                warn!("Trash info without item: '{}'", info_file.display());
+                continue;
+            }
+        };
+
+        entries.push(TrashEntry {
+            name,
+            original_path,
+            deletion_date,
+            size: entry_size(&item),
+            is_dir: metadata.is_dir(),
+        });
+    }
+
+    entries.sort_by_key(|e| e.deletion_date);
+    Ok(entries)
+}
+
+/// Restore a trash entry to its original location or to `destination`
+///
+/// # Returns
+///
+/// * `Result<PathBuf, TrashError>` - The path the item was restored to
+pub fn restore(
+    root: &Path,
+    name: &str,
+    destination: Option<&Path>,
+    overwrite: bool,
+) -> Result<PathBuf, TrashError> {
+    let entry = find_entry(root, name)?;
+    let trash = trash_dir(root);
+    let item = trash.join("files").join(&entry.name);
+    let target = destination
+        .map(Path::to_path_buf)
+        .unwrap_or_else(|| entry.original_path.clone());
+
+    if let Ok(metadata) = fs::symlink_metadata(&target) {
+        if !overwrite {
+            return Err(TrashError::DestinationExists(target.display().to_string()));
+        }
+        if metadata.is_dir() {
+            fs::remove_dir_all(&target)?;
+        } else {
+            fs::remove_file(&target)?;
+        }
+    }
+
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::rename(&item, &target)?;
+    fs::remove_file(info_path(&trash.join("info"), &entry.name))?;
+
+    debug!("Restored trash entry '{}' to '{}'", entry.name, target.display());
+    Ok(target)
+}
+
+/// Permanently remove a single trash entry
+///
+/// # Returns
+///
+/// * `Result<u64, TrashError>` - Number of bytes freed
+pub fn remove_entry(root: &Path, name: &str) -> Result<u64, TrashError> {
+    let entry = find_entry(root, name)?;
+    purge(root, &entry)?;
+    Ok(entry.size)
+}
+
+/// Permanently remove trash entries, optionally only those older than `older_than`
+///
+/// # Returns
+///
+/// * `Result<(usize, u64), TrashError>` - Number of entries removed and bytes freed
+pub fn empty(root: &Path, older_than: Option<Duration>) -> Result<(usize, u64), TrashError> {
+    let cutoff = older_than.map(|age| Local::now().naive_local() - age);
+    let mut removed = 0;
+    let mut freed = 0;
+
+    for entry in list_entries(root)? {
+        if cutoff.is_some_and(|cutoff| entry.deletion_date > cutoff) {
+            continue;
+        }
+        purge(root, &entry)?;
+        removed += 1;
+        freed += entry.size;
+    }
+
+    Ok((removed, freed))
+}
+
+/// Apply the configured age and size limits to the trash of a root
+///
+/// # Returns
+///
+/// * `Result<(usize, u64), TrashError>` - Number of entries removed and bytes freed
+pub fn enforce_retention(root: &Path, config: &TrashConfig) -> Result<(usize, u64), TrashError> {
+    let (mut removed, mut freed) = match config.max_age_days {
+        Some(days) => empty(root, Some(Duration::days(days as i64)))?,
+        None => (0, 0),
+    };
+
+    if let Some(max_size) = config.max_size {
+        let entries = list_entries(root)?;
+        let mut total: u64 = entries.iter().map(|e| e.size).sum();
+
+        // Entries are sorted oldest first
+        for entry in entries {
+            if total <= max_size {
+                break;
+            }
+            purge(root, &entry)?;
+            total -= entry.size;
+            removed += 1;
+            freed += entry.size;
+        }
+    }
+
+    if removed > 0 {
+        debug!(
+            "Trash retention removed {} entries ({} bytes) from '{}'",
+            removed,
+            freed,
+            root.display()
+        );
+    }
+
+    Ok((removed, freed))
+}
+
+/// Resolve which allowed roots a trash tool call applies to
+///
+/// # Arguments
+///
+/// * `allowed_paths` - The configured allowed directories
+/// * `root` - Optional path selecting a single root (any path inside it works)
+///
+/// # Returns
+///
+/// * `Result<Vec<PathBuf>, PathError>` - The selected roots, or all roots when `root` is None
+pub fn selected_roots(
+    allowed_paths: &AllowedPaths,
+    root: Option<&str>,
+) -> Result<Vec<PathBuf>, PathError> {
+    match root {
+        Some(root_str) => {
+            let validated = allowed_paths.validate_path(Path::new(root_str))?;
+            let root = allowed_paths
+                .root_for(&validated)
+                .ok_or(PathError::OutsideAllowedPaths)?;
+            Ok(vec![root.to_path_buf()])
+        }
+        None => Ok(allowed_paths.all_paths().clone()),
+    }
+}
+
+fn find_entry(root: &Path, name: &str) -> Result<TrashEntry, TrashError> {
+    list_entries(root)?
+        .into_iter()
+        .find(|e| e.name == name)
+        .ok_or_else(|| TrashError::EntryNotFound(name.to_string()))
+}
+
+fn purge(root: &Path, entry: &TrashEntry) -> Result<(), TrashError> {
+    let trash = trash_dir(root);
+    let item = trash.join("files").join(&entry.name);
+
+    if entry.is_dir {
+        fs::remove_dir_all(&item)?;
+    } else {
+        fs::remove_file(&item)?;
+    }
+    fs::remove_file(info_path(&trash.join("info"), &entry.name))?;
+    Ok(())
+}
+
+fn info_path(info_dir: &Path, name: &str) -> PathBuf {
+    info_dir.join(format!("{}.{}", name, TRASH_INFO_EXTENSION))
+}
+
+fn entry_size(path: &Path) -> u64 {
+    WalkDir::new(path)
+        .follow_links(false)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+        .sum()
+}
+
+fn parse_info(content: &str) -> Result<(PathBuf, NaiveDateTime), TrashError> {
+    let mut lines = content.lines().map(str::trim).filter(|l| !l.is_empty());
+    if lines.next() != Some("[Trash Info]") {
+        return Err(TrashError::InvalidInfo("missing [Trash Info] header".to_string()));
+    }
+
+    let mut path = None;
+    let mut date = None;
+    for line in lines {
+        if let Some(value) = line.strip_prefix("Path=") {
+            path = Some(decode_path(value));
+        } else if let Some(value) = line.strip_prefix("DeletionDate=") {
+            date = NaiveDateTime::parse_from_str(value, DELETION_DATE_FORMAT).ok();
+        }
+    }
+
+    match (path, date) {
+        (Some(path), Some(date)) => Ok((path, date)),
+        (None, _) => Err(TrashError::InvalidInfo("missing Path key".to_string())),
+        (_, None) => Err(TrashError::InvalidInfo("missing or invalid DeletionDate key".to_string())),
+    }
+}
+
+// Percent-encode a path as required for the Path key of a .trashinfo file
+fn encode_path(path: &Path) -> String {
+    let mut encoded = String::new();
+    for byte in path.to_string_lossy().bytes() {
+        if byte.is_ascii_alphanumeric() || b"/-_.~".contains(&byte) {
+            encoded.push(byte as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", byte));
+        }
+    }
+    encoded
+}
+
+fn decode_path(value: &str) -> PathBuf {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).unwrap_or("");
+            if let Ok(byte) = u8::from_str_radix(hex, 16) {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+    PathBuf::from(String::from_utf8_lossy(&decoded).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn test_trash_and_restore_file() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        let file = root.join("notes file.txt");
+        fs::write(&file, "keep me").unwrap();
+
+        let entry = move_to_trash(&file, &root).unwrap();
+        assert!(!file.exists());
+        assert_eq!(entry.name, "notes file.txt");
+        assert!(trash_dir(&root).join("files").join(&entry.name).exists());
+
+        let entries = list_entries(&root).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].original_path, file);
+        assert_eq!(entries[0].size, 7);
+
+        let restored = restore(&root, &entry.name, None, false).unwrap();
+        assert_eq!(restored, file);
+        assert_eq!(fs::read_to_string(&file).unwrap(), "keep me");
+        assert!(list_entries(&root).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_trash_name_collisions() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+        let file = root.join("a.txt");
+
+        fs::write(&file, "one").unwrap();
+        let first = move_to_trash(&file, &root).unwrap();
+        fs::write(&file, "two").unwrap();
+        let second = move_to_trash(&file, &root).unwrap();
+
+        assert_eq!(first.name, "a.txt");
+        assert_eq!(second.name, "a.txt.2");
+
+        // Restoring onto an existing file requires overwrite
+        fs::write(&file, "three").unwrap();
+        assert!(matches!(
+            restore(&root, "a.txt", None, false),
+            Err(TrashError::DestinationExists(_))
+        ));
+        restore(&root, "a.txt", None, true).unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "one");
+    }
+
+    #[test]
+    fn test_retention_by_size() {
+        let temp_dir = tempdir().unwrap();
+        let root = temp_dir.path().canonicalize().unwrap();
+
+        for name in ["old.txt", "new.txt"] {
+            let file = root.join(name);
+            fs::write(&file, "0123456789").unwrap();
+            move_to_trash(&file, &root).unwrap();
+        }
+
+        let config = TrashConfig {
+            enabled: true,
+            max_age_days: None,
+            max_size: Some(15),
+        };
+        let (removed, freed) = enforce_retention(&root, &config).unwrap();
+        assert_eq!((removed, freed), (1, 10));
+        assert_eq!(list_entries(&root).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_path_encoding_round_trip() {
+        let path = Path::new("/tmp/dir with space/file%name.txt");
+        assert_eq!(decode_path(&encode_path(path)), path);
+    }
+}
@@ -0,0 +1,209 @@
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+use tree_sitter::{Node, Parser};
+
+use crate::utils::structured::DataFormat;
+use crate::utils::syntax::SourceLanguage;
+use crate::utils::text::UTF8_BOM;
+
+/// Maximum number of syntax errors reported for one file
+const MAX_ISSUES: usize = 5;
+
+/// Maximum length of the source excerpt quoted in an error message
+const MAX_EXCERPT_LEN: usize = 40;
+
+/// A syntax error, at a 0-based line and column (in characters)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SyntaxIssue {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for SyntaxIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.message)
+    }
+}
+
+/// Result of parsing a file's content
+#[derive(Debug, Clone, Serialize)]
+pub struct Validation {
+    /// Name of the parser used, e.g. "JSON" or "Rust"
+    pub parser: String,
+    pub valid: bool,
+    /// The first syntax errors, at most `MAX_ISSUES`
+    pub issues: Vec<SyntaxIssue>,
+}
+
+impl Validation {
+    /// A short report, e.g. "Rust syntax: 1 error (0-based positions)" followed by the errors
+    pub fn describe(&self) -> String {
+        if self.valid {
+            return format!("{} syntax: valid", self.parser);
+        }
+        let mut text = format!(
+            "{} syntax: {} error(s) (0-based positions)",
+            self.parser,
+            self.issues.len()
+        );
+        for issue in &self.issues {
+            text.push_str(&format!("\n  - {}", issue));
+        }
+        text
+    }
+}
+
+/// Check that content parses in the language of its file
+///
+/// # Arguments
+///
+/// * `content` - The content to check
+/// * `path` - The file path, whose extension selects the parser
+/// * `language` - Source language overriding the extension, if given
+///
+/// # Returns
+///
+/// * `Option<Validation>` - The result, or `None` if there is no parser for the file type
+pub fn validate(content: &str, path: &Path, language: Option<SourceLanguage>) -> Option<Validation> {
+    let body = content.strip_prefix(UTF8_BOM).unwrap_or(content);
+
+    if let Some(language) = language.or_else(|| SourceLanguage::from_path(path)) {
+        return Some(validate_source(body, language));
+    }
+
+    let format = DataFormat::from_path(path)?;
+    let issue = match format {
+        DataFormat::Json => serde_json::from_str::<serde_json::Value>(body).err().map(|e| SyntaxIssue {
+            line: e.line().saturating_sub(1),
+            column: e.column().saturating_sub(1),
+            message: strip_location(&e.to_string()),
+        }),
+        DataFormat::Yaml => serde_yaml::from_str::<serde_yaml::Value>(body).err().map(|e| {
+            let (line, column) = e.location().map_or((0, 0), |l| (l.line().saturating_sub(1), l.column().saturating_sub(1)));
+            SyntaxIssue { line, column, message: strip_location(&e.to_string()) }
+        }),
+        DataFormat::Toml => body.parse::<toml_edit::DocumentMut>().err().map(|e| {
+            let (line, column) = e.span().map_or((0, 0), |span| position(body, span.start));
+            SyntaxIssue { line, column, message: e.message().trim().to_string() }
+        }),
+    };
+
+    Some(Validation {
+        parser: format.to_string(),
+        valid: issue.is_none(),
+        issues: issue.into_iter().collect(),
+    })
+}
+
+// serde errors end with " at line L column C", which is reported separately
+fn strip_location(message: &str) -> String {
+    match message.rfind(" at line ") {
+        Some(i) => message[..i].to_string(),
+        None => message.to_string(),
+    }
+}
+
+// 0-based line and character column of a byte offset
+fn position(content: &str, offset: usize) -> (usize, usize) {
+    let offset = offset.min(content.len());
+    let before = &content[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (before.matches('\n').count(), before[line_start..].chars().count())
+}
+
+fn validate_source(content: &str, language: SourceLanguage) -> Validation {
+    let mut parser = Parser::new();
+    let tree = parser
+        .set_language(&language.grammar())
+        .ok()
+        .and_then(|_| parser.parse(content, None));
+
+    let mut issues = Vec::new();
+    match tree {
+        Some(tree) => collect_errors(tree.root_node(), content, &mut issues),
+        None => issues.push(SyntaxIssue { line: 0, column: 0, message: "The parser could not process the file".to_string() }),
+    }
+
+    Validation {
+        parser: language.to_string(),
+        valid: issues.is_empty(),
+        issues,
+    }
+}
+
+// Record ERROR and MISSING nodes, without descending into an ERROR node's children
+fn collect_errors(node: Node, content: &str, issues: &mut Vec<SyntaxIssue>) {
+    if issues.len() >= MAX_ISSUES || !node.has_error() {
+        return;
+    }
+
+    if node.is_error() || node.is_missing() {
+        let (line, column) = position(content, node.start_byte());
+        let message = if node.is_missing() {
+            let kind = node.kind();
+            if kind.chars().all(|c| c.is_alphanumeric() || c == '_') {
+                format!("Missing {}", kind)
+            } else {
+                format!("Missing `{}`", kind)
+            }
+        } else {
+            let text = content[node.start_byte()..node.end_byte()].lines().next().unwrap_or_default().trim();
+            let mut excerpt: String = text.chars().take(MAX_EXCERPT_LEN).collect();
+            if excerpt.len() < text.len() {
+                excerpt.push_str("...");
+            }
+            if excerpt.is_empty() {
+                "Syntax error".to_string()
+            } else {
+                format!("Syntax error near `{}`", excerpt)
+            }
+        };
+        issues.push(SyntaxIssue { line, column, message });
+        return;
+    }
+
+    let mut cursor = node.walk();
+    let children: Vec<Node> = node.children(&mut cursor).collect();
+    for child in children {
+        collect_errors(child, content, issues);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_data_formats() {
+        let valid = validate("{\"a\": 1}\n", Path::new("x.json"), None).unwrap();
+        assert!(valid.valid);
+
+        let invalid = validate("{\n  \"a\": 1,\n}\n", Path::new("x.json"), None).unwrap();
+        assert!(!invalid.valid);
+        assert_eq!(invalid.issues[0].line, 2);
+
+        let invalid = validate("[package]\nname = \n", Path::new("Cargo.toml"), None).unwrap();
+        assert_eq!(invalid.issues[0].line, 1);
+
+        let invalid = validate("a: [1, 2\n", Path::new("x.yaml"), None).unwrap();
+        assert!(!invalid.valid);
+
+        assert!(validate("anything", Path::new("notes.txt"), None).is_none());
+    }
+
+    #[test]
+    fn test_validate_source() {
+        let valid = validate("fn main() {\n    println!(\"hi\");\n}\n", Path::new("main.rs"), None).unwrap();
+        assert!(valid.valid);
+
+        let invalid = validate("fn main() {\n    let x = ;\n}\n", Path::new("main.rs"), None).unwrap();
+        assert!(!invalid.valid);
+        assert_eq!(invalid.issues[0].line, 1);
+        assert!(invalid.describe().starts_with("Rust syntax: 1 error(s)"));
+
+        let invalid = validate("def f(:\n    pass\n", Path::new("x.txt"), Some(SourceLanguage::Python)).unwrap();
+        assert!(!invalid.valid);
+    }
+}